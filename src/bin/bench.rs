@@ -20,128 +20,526 @@
 
 // author: Ignacio Corderi
 
-use kinetic::KineticResult;
+use std::{cmp, vec};
 use std::io::{BufReader, BufRead};
 use std::fs::File;
-use std::iter::Iterator;
-use kinetic::commands::Put;
 use std::default::Default;
-use std::vec;
-use std::time::Duration;
-use std::path::Path;
-use std::sync::mpsc::channel;
-use std::thread::spawn;
+use std::time::{Duration, Instant};
+use std::io;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use rand::{self, Rng};
+use serde_json::{self, Value};
+use kinetic::{KineticResult, KineticError};
+use kinetic::commands::{Put, Get, Delete};
+use kinetic::commands::common::Versioning;
+use kinetic::proto::StatusCode;
+use output::Format;
 
-#[derive(RustcDecodable, Debug)]
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum SizeDist {
+    Fixed,
+    Uniform,
+    Exponential,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum KeyDist {
+    Sequential,
+    Uniform,
+    Zipfian,
+}
+
+#[derive(Deserialize, Debug)]
 pub struct BenchArgs {
     flag_verbose: bool,
-    flag_count: Option<usize>,
-    flag_size: Option<usize>,
+    flag_count: Option<u64>,
+    flag_duration: Option<u64>,
+    flag_size: usize,
+    flag_max_size: Option<usize>,
+    flag_sizes: SizeDist,
+    flag_keys: KeyDist,
+    flag_key_count: u64,
+    flag_zipf_theta: f64,
+    flag_prefill: bool,
+    flag_mix: String,
+    flag_queue_depth: usize,
     flag_path: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+    arg_target: vec::Vec<String>,
 }
 
 static USAGE: &'static str = "
-Writes to a set of drives
+Generates load on a set of drives
+
+Each drive is driven by its own connection, keeping up to --queue-depth
+requests in flight. Targets are given as arguments, with --targets or --group,
+or in the file at --path, one per line.
 
-Usage: kinetic-rust bench [options]
+Usage: kinetic-rust bench [options] [<target>...]
        kinetic-rust bench (-h | --help)
 
 Options:
   -h, --help               Print this message
-  -s, --size BYTES         Size of the value in bytes (default: 1 MB)
-  -c, --count COUNT        Number of key/value pairs to send (default: 10)
   -p, --path PATH          Path to the file with the kinetic devices (default: drives)
+  -t, --targets LIST       Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP        Every drive of GROUP in the inventory
+  --inventory PATH         Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -d, --duration SECS      How long to run, in seconds (default: 10)
+  -c, --count COUNT        Stop after COUNT operations per drive, instead of --duration
+  -m, --mix MIX            Percentages of reads, writes and deletes, as R,W,D [default: 0,100,0]
+  -q, --queue-depth N      Requests in flight per drive [default: 8]
+  -s, --size BYTES         Size of the values, mean size for exponential [default: 1048576]
+  --max-size BYTES         Largest value for uniform and exponential sizes
+  --sizes DIST             Value sizes: fixed, uniform or exponential [default: fixed]
+  -k, --keys DIST          Keys: sequential, uniform or zipfian [default: sequential]
+  --key-count N            Number of distinct keys per drive [default: 10000]
+  --zipf-theta THETA       Skew of the zipfian keys, in [0, 1) [default: 0.99]
+  --prefill                Write every key before starting, so reads find them
   -v, --verbose            Use verbose output
 ";
 
-fn to_utf8(s: &[u8]) -> String {
-    String::from_utf8(s.to_vec()).unwrap()
-}
-
-// fn bench(c: &::kinetic::AsyncClient, size: usize, count: usize) -> KineticResult<(&::kinetic::AsyncClient, String)> {
-//     let d = Duration::span(|| {
-//         let mut responses = vec::Vec::with_capacity(count);
-
-//         for i in (0.. count) {
-//             let data = vec::Vec::from_elem(size, 0u8);
-//             let r = c.send_future(Put { key: format!("opt-bench.{}", i).as_bytes().to_vec(),
-//                                         value: data,
-//                                         ..Default::default()});
-//             responses.push(r);
-//         }
-
-//         // wait on all
-//         for r in responses.into_iter() {
-//             r.into_inner().unwrap();
-//         }
-//     });
-
-//     let ops = count as f64  / (d.num_milliseconds() as f64 / 1000.0);
-//     let transfered = (count as f64 * size as f64) / (1024.0 * 1024.0);
-//     let bw = transfered / (d.num_milliseconds() as f64 / 1000.0);
-
-//     Ok((c, format!("operation took {}ms ({:.2} MB/s, {:.2} op/s)", d.num_milliseconds(), bw, ops)))
-// }
-
-fn execute(cmd: &BenchArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
-//     // debug!("executing; cmd=kinetic-rust-bench; args={}", ::std::env::args());
-//     shell.set_verbose(cmd.flag_verbose);
-
-//     let path = Path::new(&cmd.flag_path.clone().unwrap_or("drives".to_string()));
-//     let file = try!(File::open(&path));
-//     let mut file = BufReader::new(file);
-//     let lines: Vec<String> = file.lines().map(|x| x.unwrap()).collect();
-
-//     let clients: Vec<KineticResult<::kinetic::AsyncClient>> = lines.into_iter().map(
-//         |mut x| {
-//             x = x.replace("\n","");
-//             if ! x.contains(":") {
-//                 x = x + ":8123";
-//             }
-//             println!("{}", x);
-//             ::kinetic::Client::new(x.as_ref())
-//         }).collect();;
-
-//     let size = cmd.flag_size.unwrap_or(1024*1024);
-//     let count = cmd.flag_count.unwrap_or(10);
-
-//     let (tx,rx) = channel();
-//     let mut pending = clients.len();
-
-//     let d = Duration::span(|| {
-//         //let clients = clients.clone(); // ugly... but apparently clients doesnt live long enough...
-//         for c in clients.iter() {
-//             match *c {
-//                 Ok(ref c)  => {
-//                     shell.status("Connected", to_utf8(c.get_config().get_serialNumber())).unwrap(); //ugly unwrap
-//                     let tx = tx.clone();
-//                     spawn(move|| {
-//                         let r = bench(c, size, count);
-//                         tx.send(r);
-//                     });
-//                 },
-//                 Err(ref e) => shell.error_full(e, true).unwrap(), //ungly unwrap
-//             };
-//         }
-
-//         while pending > 0 {
-//             let r = rx.recv();
-//             match r {
-//                 Ok((c,r)) => shell.tag(to_utf8(c.get_config().get_serialNumber()), r).unwrap(), //ugly unwrap
-//                 Err(e) => shell.error_full(&e, true).unwrap(), //ugly unwrap
-//             };
-
-//             pending -= 1;
-//         }
-//     });
-
-//     let count = count * clients.len();
-//     try!(shell.status("Count?", count));
-//     let ops = count as f64  / (d.num_milliseconds() as f64 / 1000.0);
-//     let transfered = (count as f64 * size as f64) / (1024.0 * 1024.0);
-//     let bw = transfered / (d.num_milliseconds() as f64 / 1000.0);
-
-//     try!(shell.status("Done",format!("benchmark took {}ms ({:.2} MB/s, {:.2} op/s)", d.num_milliseconds(), bw, ops)));
+/// How long to run when neither `--duration` nor `--count` is given
+static DEFAULT_DURATION_SECS: u64 = 10;
+
+/// How long to wait for a response before giving up on a drive
+static RESPONSE_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op { Read, Write, Delete }
+
+/// Zipfian generator over `[0, n)`, as described by Gray et al. in
+/// "Quickly Generating Billion-Record Synthetic Databases"
+///
+/// The method only holds for a skew `theta` in `[0, 1)`, 0 being uniform.
+struct Zipfian {
+    n: f64,
+    theta: f64,
+    alpha: f64,
+    zetan: f64,
+    eta: f64,
+}
+
+impl Zipfian {
+
+    /// # Panics
+    /// Panics if `theta` is not in `[0, 1)`, see `check_theta`.
+    fn new(n: u64, theta: f64) -> Zipfian {
+        assert!(Zipfian::check_theta(theta).is_ok(), "zipfian theta must be in [0, 1)");
+        let zeta = |n: u64| (1..n + 1).fold(0.0, |z, i| z + 1.0 / (i as f64).powf(theta));
+        let zetan = zeta(n);
+        let zeta2 = zeta(2);
+        let n = n as f64;
+
+        Zipfian { n: n,
+                  theta: theta,
+                  alpha: 1.0 / (1.0 - theta),
+                  zetan: zetan,
+                  eta: (1.0 - (2.0 / n).powf(1.0 - theta)) / (1.0 - zeta2 / zetan) }
+    }
+
+    /// Checks the skew is one the generator supports, `alpha` is infinite at 1
+    fn check_theta(theta: f64) -> KineticResult<()> {
+        if theta >= 0.0 && theta < 1.0 { Ok(()) }
+        else { Err(KineticError::Usage("--zipf-theta must be at least 0 and less than 1".to_string())) }
+    }
+
+    fn next<R: Rng>(&self, rng: &mut R) -> u64 {
+        let u = rng.gen::<f64>();
+        let uz = u * self.zetan;
+        if uz < 1.0 { return 0; }
+        if uz < 1.0 + 0.5f64.powf(self.theta) { return 1; }
+        let k = (self.n * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as u64;
+        cmp::min(k, self.n as u64 - 1)
+    }
+
+}
+
+/// What each drive thread needs, shared by all of them
+#[derive(Clone)]
+struct Workload {
+    // `None` when running for `count` operations
+    duration: Option<Duration>,
+    count: Option<u64>,
+    mix: (u32, u32, u32),
+    queue_depth: usize,
+    size: usize,
+    max_size: usize,
+    sizes: SizeDist,
+    keys: KeyDist,
+    key_count: u64,
+    zipf_theta: f64,
+    prefill: bool,
+}
+
+impl Workload {
+
+    fn pick_size<R: Rng>(&self, rng: &mut R) -> usize {
+        match self.sizes {
+            SizeDist::Fixed => self.size,
+            SizeDist::Uniform => rng.gen_range(self.size, self.max_size + 1),
+            SizeDist::Exponential => {
+                let s = -(1.0 - rng.gen::<f64>()).ln() * self.size as f64;
+                cmp::min(s as usize, self.max_size)
+            },
+        }
+    }
+
+}
+
+#[inline]
+fn key(index: u64) -> vec::Vec<u8> {
+    format!("bench.{:010}", index).into_bytes()
+}
+
+/// An operation, `micros` is `None` if the request never went out
+struct Sample {
+    op: Op,
+    micros: Option<u64>,
+    bytes: usize,
+    ok: bool,
+    // the key was not found
+    miss: bool,
+}
+
+struct DriveRun {
+    target: String,
+    elapsed: Duration,
+    samples: vec::Vec<Sample>,
+}
+
+#[derive(Serialize, Debug)]
+struct OpReport {
+    op: String,
+    count: usize,
+    errors: usize,
+    misses: usize,
+    ops_per_sec: f64,
+    mb_per_sec: f64,
+    p50_us: u64,
+    p99_us: u64,
+    p999_us: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct DriveReport {
+    target: String,
+    count: usize,
+    errors: usize,
+    misses: usize,
+    ops_per_sec: f64,
+    mb_per_sec: f64,
+    p50_us: u64,
+    p99_us: u64,
+    p999_us: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct BenchReport {
+    seconds: f64,
+    count: usize,
+    errors: usize,
+    ops_per_sec: f64,
+    mb_per_sec: f64,
+    operations: vec::Vec<OpReport>,
+    drives: vec::Vec<DriveReport>,
+}
+
+#[inline]
+fn seconds(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9
+}
+
+/// Gets the `p` percentile of sorted latencies
+#[inline]
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() { return 0; }
+    let i = ((sorted.len() as f64 * p).ceil() as usize).saturating_sub(1);
+    sorted[cmp::min(i, sorted.len() - 1)]
+}
+
+/// Summarizes samples as (count, errors, misses, ops/s, MB/s, p50, p99, p999)
+///
+/// Requests that never went out count as errors but have no latency.
+fn summarize<'a, I: Iterator<Item=&'a Sample>>(samples: I, secs: f64)
+        -> (usize, usize, usize, f64, f64, u64, u64, u64) {
+    let mut latencies = vec::Vec::new();
+    let mut count = 0;
+    let mut errors = 0;
+    let mut misses = 0;
+    let mut bytes = 0;
+    for s in samples {
+        count += 1;
+        if let Some(micros) = s.micros { latencies.push(micros); }
+        bytes += s.bytes;
+        if !s.ok { errors += 1; }
+        if s.miss { misses += 1; }
+    }
+    latencies.sort();
+
+    let secs = if secs > 0.0 { secs } else { 1.0 };
+    (count, errors, misses, count as f64 / secs, bytes as f64 / (1024.0 * 1024.0) / secs,
+     percentile(&latencies, 0.5), percentile(&latencies, 0.99), percentile(&latencies, 0.999))
+}
+
+fn parse_mix(mix: &str) -> KineticResult<(u32, u32, u32)> {
+    let parts: vec::Vec<u32> = mix.split(',').filter_map(|p| p.trim().parse().ok()).collect();
+    if parts.len() != 3 || parts.iter().sum::<u32>() == 0 {
+        return Err(KineticError::Usage("--mix must be three percentages, e.g. 70,20,10".to_string()));
+    }
+    Ok((parts[0], parts[1], parts[2]))
+}
+
+fn read_targets(path: &str) -> KineticResult<vec::Vec<String>> {
+    let file = BufReader::new(try!(File::open(path)));
+    let mut targets = vec::Vec::new();
+    for line in file.lines() {
+        let line = try!(line);
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            targets.push(line.to_string());
+        }
+    }
+    Ok(targets)
+}
+
+/// Runs the workload against a single drive
+fn run(connection: &::config::Connection, drive: ::inventory::Drive, w: Workload) -> KineticResult<DriveRun> {
+    let c = try!(connection.connect(&drive));
+
+    let mut rng = rand::thread_rng();
+    let zipf = match w.keys {
+        KeyDist::Zipfian => Some(Zipfian::new(w.key_count, w.zipf_theta)),
+        _ => None,
+    };
+    let data: vec::Vec<u8> = (0..w.max_size).map(|_| rng.gen::<u8>()).collect();
+    let (mix_r, mix_w, mix_d) = w.mix;
+
+    if w.prefill {
+        let puts: vec::Vec<Put> = (0..w.key_count).map(|i| Put { key: key(i),
+                                                                 value: data[..w.pick_size(&mut rng)].to_vec(),
+                                                                 current_version: Versioning::Force,
+                                                                 ..Default::default() }).collect();
+        for r in c.put_many(puts) {
+            try!(r);
+        }
+    }
+
+    let (tx, rx) = channel();
+    let mut samples = vec::Vec::new();
+    let mut in_flight = 0;
+    let mut issued = 0u64;
+    let mut closed = false;
+    let start = Instant::now();
+
+    loop {
+        let more = !closed
+                   && w.duration.map_or(true, |d| start.elapsed() < d)
+                   && w.count.map_or(true, |n| issued < n);
+
+        if more && in_flight < w.queue_depth {
+            let index = match w.keys {
+                KeyDist::Sequential => issued % w.key_count,
+                KeyDist::Uniform => rng.gen_range(0, w.key_count),
+                KeyDist::Zipfian => zipf.as_ref().unwrap().next(&mut rng),
+            };
+            let key = key(index);
+
+            let pick = rng.gen_range(0, mix_r + mix_w + mix_d);
+            let op = if pick < mix_r { Op::Read } else if pick < mix_r + mix_w { Op::Write } else { Op::Delete };
+
+            let tx = tx.clone();
+            let sent = Instant::now();
+            let done = move |op: Op, bytes: usize, ok: bool, miss: bool| {
+                let e = sent.elapsed();
+                let micros = e.as_secs() * 1000000 + e.subsec_micros() as u64;
+                let _ = tx.send(Sample { op: op, micros: Some(micros), bytes: bytes, ok: ok, miss: miss });
+            };
+
+            // a missing key is a served request, not an error, but it's reported apart
+            let r = match op {
+                Op::Read => c.send_with_callback(Get { key: key }, move |r| match r {
+                    Ok(r) => done(op, r.value.len(), true, false),
+                    Err(KineticError::RemoteError(ref s)) if s.get_code() == StatusCode::NOT_FOUND => done(op, 0, true, true),
+                    Err(_) => done(op, 0, false, false),
+                }),
+                Op::Write => {
+                    let size = w.pick_size(&mut rng);
+                    c.send_with_callback(Put { key: key,
+                                               value: data[..size].to_vec(),
+                                               current_version: Versioning::Force,
+                                               ..Default::default() },
+                                         move |r| done(op, size, r.is_ok(), false))
+                },
+                Op::Delete => c.send_with_callback(Delete::Forced { key: key }, move |r| match r {
+                    Ok(_) => done(op, 0, true, false),
+                    Err(KineticError::RemoteError(ref s)) if s.get_code() == StatusCode::NOT_FOUND => done(op, 0, true, true),
+                    Err(_) => done(op, 0, false, false),
+                }),
+            };
+
+            issued += 1;
+            match r {
+                Ok(()) => in_flight += 1,
+                // the request never went out, count it and carry on
+                Err(e) => {
+                    samples.push(Sample { op: op, micros: None, bytes: 0, ok: false, miss: false });
+                    if let KineticError::ConnectionClosed = e { closed = true; }
+                },
+            }
+        } else if in_flight > 0 {
+            match rx.recv_timeout(Duration::from_secs(RESPONSE_TIMEOUT_SECS)) {
+                Ok(sample) => samples.push(sample),
+                Err(RecvTimeoutError::Timeout) =>
+                    return Err(KineticError::IoError(io::Error::new(io::ErrorKind::TimedOut,
+                                                                    "the drive stopped answering"))),
+                Err(RecvTimeoutError::Disconnected) => return Err(KineticError::ConnectionClosed),
+            }
+            in_flight -= 1;
+        } else {
+            break;
+        }
+    }
+
+    Ok(DriveRun { target: drive.label(), elapsed: start.elapsed(), samples: samples })
+}
+
+fn report(runs: &[DriveRun]) -> BenchReport {
+    let secs = runs.iter().map(|r| seconds(r.elapsed)).fold(0.0, f64::max);
+    let all = || runs.iter().flat_map(|r| r.samples.iter());
+
+    let operations = [(Op::Read, "read"), (Op::Write, "write"), (Op::Delete, "delete")].iter()
+        .filter(|&&(op, _)| all().any(|s| s.op == op))
+        .map(|&(op, name)| {
+            let (count, errors, misses, ops, mbs, p50, p99, p999) = summarize(all().filter(|s| s.op == op), secs);
+            OpReport { op: name.to_string(), count: count, errors: errors, misses: misses, ops_per_sec: ops,
+                       mb_per_sec: mbs, p50_us: p50, p99_us: p99, p999_us: p999 }
+        }).collect();
+
+    let drives = runs.iter().map(|r| {
+        let (count, errors, misses, ops, mbs, p50, p99, p999) = summarize(r.samples.iter(), seconds(r.elapsed));
+        DriveReport { target: r.target.clone(), count: count, errors: errors, misses: misses, ops_per_sec: ops,
+                      mb_per_sec: mbs, p50_us: p50, p99_us: p99, p999_us: p999 }
+    }).collect();
+
+    let (count, errors, _, ops, mbs, _, _, _) = summarize(all(), secs);
+    BenchReport { seconds: secs, count: count, errors: errors, ops_per_sec: ops, mb_per_sec: mbs,
+                  operations: operations, drives: drives }
+}
+
+fn execute(cmd: &BenchArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    // debug!("executing; cmd=kinetic-rust-bench; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let drives = if !cmd.arg_target.is_empty() {
+        let targets = Some(cmd.arg_target.join(","));
+        try!(::inventory::select(&None, &targets, &None, &cmd.flag_inventory))
+    } else if cmd.flag_targets.is_some() || cmd.flag_group.is_some() {
+        try!(::inventory::select(&None, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory))
+    } else {
+        try!(read_targets(&cmd.flag_path.clone().unwrap_or("drives".to_string())))
+            .iter().map(|t| ::inventory::Drive::from_target(t)).collect()
+    };
+    if drives.is_empty() {
+        return Err(KineticError::Usage("no drives to benchmark".to_string()));
+    }
+
+    let max_size = cmd.flag_max_size.unwrap_or(cmd.flag_size);
+    if max_size < cmd.flag_size {
+        return Err(KineticError::Usage("--max-size is smaller than --size".to_string()));
+    }
+
+    let duration = match (cmd.flag_duration, cmd.flag_count) {
+        (Some(_), Some(_)) => return Err(KineticError::Usage("--duration and --count can't be used together".to_string())),
+        (Some(secs), None) => Some(secs),
+        (None, Some(_)) => None,
+        (None, None) => Some(DEFAULT_DURATION_SECS),
+    };
+
+    if let KeyDist::Zipfian = cmd.flag_keys {
+        try!(Zipfian::check_theta(cmd.flag_zipf_theta));
+    }
+
+    let workload = Workload { duration: duration.map(Duration::from_secs),
+                              count: cmd.flag_count,
+                              mix: try!(parse_mix(&cmd.flag_mix)),
+                              queue_depth: cmp::max(cmd.flag_queue_depth, 1),
+                              size: cmd.flag_size,
+                              max_size: max_size,
+                              sizes: cmd.flag_sizes,
+                              keys: cmd.flag_keys,
+                              key_count: cmp::max(cmd.flag_key_count, 1),
+                              zipf_theta: cmd.flag_zipf_theta,
+                              prefill: cmd.flag_prefill };
+
+    if globals.format == Format::Text {
+        let length = match (duration, cmd.flag_count) {
+            (Some(secs), _) => format!("{}s", secs),
+            (None, count) => format!("{} operations each", count.unwrap_or(0)),
+        };
+        let prefill = if cmd.flag_prefill { format!(", after writing {} keys each", workload.key_count) }
+                      else { String::new() };
+        try!(shell.status("Running", format!("{} drives for {}{}", drives.len(), length, prefill)));
+    }
+
+    let connection = globals.connection.clone();
+    let results = ::cli::fan_out(&drives, move |d| run(&connection, d.clone(), workload.clone()));
+
+    let mut runs = vec::Vec::new();
+    for r in results.into_iter() {
+        match r {
+            Ok(r) => runs.push(r),
+            Err(e) => try!(::cli::print_error(shell, &e)),
+        }
+    }
+
+    let report = report(&runs);
+
+    match globals.format {
+        Format::Json => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            return Ok(());
+        },
+        Format::Csv => {
+            // one table, operations then drives
+            let row = |scope: &str, name: &str, count: usize, errors: usize, misses: usize, ops: f64, mbs: f64,
+                       p50: u64, p99: u64, p999: u64| {
+                vec![Value::from(scope), Value::from(name), Value::from(count), Value::from(errors), Value::from(misses),
+                     ::output::round(ops, 2), ::output::round(mbs, 2),
+                     Value::from(p50), Value::from(p99), Value::from(p999)]
+            };
+            let mut rows: vec::Vec<_> = report.operations.iter()
+                .map(|o| row("operation", &o.op, o.count, o.errors, o.misses, o.ops_per_sec, o.mb_per_sec,
+                             o.p50_us, o.p99_us, o.p999_us))
+                .collect();
+            rows.extend(report.drives.iter()
+                .map(|d| row("drive", &d.target, d.count, d.errors, d.misses, d.ops_per_sec, d.mb_per_sec,
+                             d.p50_us, d.p99_us, d.p999_us)));
+
+            ::output::print_table(Format::Csv, &["scope", "name", "count", "errors", "misses", "ops_per_sec",
+                                                 "mb_per_sec", "p50_us", "p99_us", "p999_us"], &rows);
+            return Ok(());
+        },
+        Format::Text => (),
+    }
+
+    for op in report.operations.iter() {
+        try!(shell.header(&op.op));
+        try!(shell.tag("Operations", format!("{} ({} errors, {} not found)", op.count, op.errors, op.misses)));
+        try!(shell.tag("Throughput", format!("{:.2} op/s, {:.2} MB/s", op.ops_per_sec, op.mb_per_sec)));
+        try!(shell.tag("Latency", format!("p50 {}us, p99 {}us, p999 {}us", op.p50_us, op.p99_us, op.p999_us)));
+    }
+
+    try!(shell.header("Drives"));
+    for d in report.drives.iter() {
+        try!(shell.tag(&d.target, format!("{} ops ({} errors), {:.2} op/s, {:.2} MB/s, p99 {}us",
+                                          d.count, d.errors, d.ops_per_sec, d.mb_per_sec, d.p99_us)));
+    }
+
+    try!(shell.status("Done", format!("benchmark took {:.2}s ({:.2} MB/s, {:.2} op/s)",
+                                      report.seconds, report.mb_per_sec, report.ops_per_sec)));
 
     Ok(()) //return
 }
@@ -149,15 +547,71 @@ fn execute(cmd: &BenchArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()
 impl ::cli::CliCommand for BenchArgs {
     fn from_argv(argv: ::std::vec::Vec<String>) -> BenchArgs {
         ::docopt::Docopt::new(::cli::CliCommand::usage(None::<BenchArgs>))
-            .and_then(|d| d.argv(argv.clone().into_iter()).decode() )
+            .and_then(|d| d.argv(argv).deserialize() )
             .unwrap_or_else(|e| e.exit())
     }
 
     #[inline]
-    fn execute(&self, shell: &mut ::shell::MultiShell) -> ::kinetic::KineticResult<()> {
-        execute(self, shell)
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
     }
 
     #[inline]
     fn usage(_: Option<BenchArgs>) -> &'static str { USAGE }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+    use super::{Zipfian, Sample, Op, summarize};
+
+    fn histogram(n: u64, theta: f64, draws: usize) -> Vec<usize> {
+        let zipf = Zipfian::new(n, theta);
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut counts = vec![0usize; n as usize];
+        for _ in 0..draws {
+            let k = zipf.next(&mut rng);
+            assert!(k < n);
+            counts[k as usize] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn zipfian_is_skewed_towards_the_first_keys() {
+        let counts = histogram(1000, 0.99, 100000);
+        // rank 1 is drawn about twice as often as rank 2, and far more than the tail
+        assert!(counts[0] > counts[1] && counts[1] > counts[2]);
+        let ratio = counts[0] as f64 / counts[1] as f64;
+        assert!(ratio > 1.6 && ratio < 2.4, "ratio {}", ratio);
+        assert!(counts[0] > 50 * counts[999]);
+    }
+
+    #[test]
+    fn zipfian_without_skew_is_about_uniform() {
+        let counts = histogram(10, 0.0, 100000);
+        assert!(counts.iter().all(|&c| c > 8000 && c < 12000), "{:?}", counts);
+    }
+
+    #[test]
+    fn zipfian_rejects_theta_of_one_or_more() {
+        assert!(Zipfian::check_theta(0.0).is_ok());
+        assert!(Zipfian::check_theta(0.99).is_ok());
+        assert!(Zipfian::check_theta(1.0).is_err());
+        assert!(Zipfian::check_theta(1.5).is_err());
+        assert!(Zipfian::check_theta(-0.1).is_err());
+        assert!(Zipfian::check_theta(::std::f64::NAN).is_err());
+    }
+
+    #[test]
+    fn requests_never_sent_have_no_latency() {
+        let sample = |micros: Option<u64>, ok: bool, miss: bool|
+            Sample { op: Op::Read, micros: micros, bytes: 10, ok: ok, miss: miss };
+        let samples = [sample(Some(100), true, false), sample(Some(300), true, true),
+                           sample(None, false, false), sample(None, false, false)];
+
+        let (count, errors, misses, _, _, p50, p99, _) = summarize(samples.iter(), 1.0);
+        assert_eq!((count, errors, misses), (4, 2, 1));
+        assert_eq!((p50, p99), (100, 300));
+    }
+}
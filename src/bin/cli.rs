@@ -20,15 +20,25 @@
 
 // author: Ignacio Corderi
 
-use std::vec;
-use kinetic::KineticResult;
+use std::{io, vec};
+use std::thread;
+use std::sync::Arc;
+use serde_json::Value;
+use kinetic::{KineticResult, KineticError};
 
 
+/// Options given before the command, shared by every command
+#[derive(Debug, Clone)]
+pub struct GlobalArgs {
+    pub format: ::output::Format,
+    pub connection: ::config::Connection,
+}
+
 pub trait CliCommand {
 
     fn from_argv(argv: vec::Vec<String>) -> Self;
 
-    fn execute(&self, &mut ::shell::MultiShell) -> KineticResult<()>;
+    fn execute(&self, &mut ::shell::MultiShell, &GlobalArgs) -> KineticResult<()>;
 
     // the marker is a hack until a missing compiler feature gets finished
     fn usage(_:Option<Self>) -> &'static str where Self: Sized;
@@ -36,6 +46,39 @@ pub trait CliCommand {
 
 pub trait CliDispatcher {
 
-    fn dispatch(&self, vec::Vec<String>,  &mut ::shell::MultiShell) -> KineticResult<()>;
+    fn dispatch(&self, vec::Vec<String>,  &mut ::shell::MultiShell, &GlobalArgs) -> KineticResult<()>;
+
+}
+
+/// Port used when a target does not give one
+pub static DEFAULT_PORT: u16 = 8123;
+
+/// Runs `f` on every drive concurrently, results are in the same order as `drives`
+pub fn fan_out<T, F>(drives: &[::inventory::Drive], f: F) -> vec::Vec<KineticResult<T>>
+    where T: Send + 'static, F: Fn(&::inventory::Drive) -> KineticResult<T> + Send + Sync + 'static {
+
+    let f = Arc::new(f);
+    let handles: vec::Vec<_> = drives.iter().cloned().map(|d| {
+        let f = f.clone();
+        thread::spawn(move|| f(&d))
+    }).collect();
+
+    handles.into_iter()
+        .map(|h| h.join().unwrap_or(Err(KineticError::Panicked)))
+        .collect() // return
+}
+
+/// Prints an error through the shell, with its message rather than its description
+pub fn print_error(shell: &mut ::shell::MultiShell, err: &KineticError) -> io::Result<()> {
+    try!(shell.err().say_write("error: ", ::term::color::BRIGHT_RED));
+    shell.err().say(err, ::term::color::BLACK) // return
+}
 
+/// Gets the error column of an aggregated table, `null` when the drive succeeded
+#[inline]
+pub fn error_cell<T>(r: &KineticResult<T>) -> Value {
+    match *r {
+        Ok(_) => Value::Null,
+        Err(ref e) => ::output::error_json(e),
+    }
 }
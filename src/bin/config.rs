@@ -0,0 +1,290 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! CLI configuration and connection profiles
+//!
+//! The configuration is a TOML file, by default at `~/.config/kinetic/config.toml`:
+//!
+//! ```toml
+//! default_profile = "lab"
+//!
+//! [profiles.lab]
+//! identity = 1
+//! key = "asdfasdf"           # or key_file = "...", or key_env = "KINETIC_KEY"
+//! pin_env = "KINETIC_PIN"    # or pin_file = "...", or pin_prompt = true
+//! port = 8123
+//! tls = false
+//! ```
+//!
+//! Unknown settings make the file malformed, rather than being silently ignored.
+//! The client has no TLS transport yet, so profiles with `tls = true` can't connect
+//! and there are no settings for the TLS files.
+
+use std::{env, fs, io, vec};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use kinetic::{KineticResult, KineticError};
+use kinetic::authentication::Credentials;
+
+
+/// A named set of connection settings
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub identity: Option<i64>,
+    pub key: Option<String>,
+    pub key_file: Option<String>,
+    pub key_env: Option<String>,
+    pub pin_env: Option<String>,
+    pub pin_file: Option<String>,
+    pub pin_prompt: Option<bool>,
+    pub port: Option<u16>,
+    pub tls: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    pub profiles: Option<BTreeMap<String, Profile>>,
+}
+
+/// Reads a secret from a file, without its trailing newline
+fn read_secret(path: &str) -> KineticResult<vec::Vec<u8>> {
+    let mut s = vec::Vec::new();
+    try!(try!(fs::File::open(path)).read_to_end(&mut s));
+    while s.last().map_or(false, |&b| b == b'\n' || b == b'\r') {
+        s.pop();
+    }
+    Ok(s)
+}
+
+impl Config {
+
+    /// Gets the configuration path, `$KINETIC_CONFIG` or `~/.config/kinetic/config.toml`
+    pub fn default_path() -> PathBuf {
+        match env::var("KINETIC_CONFIG") {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => env::home_dir().unwrap_or(PathBuf::from("."))
+                          .join(".config").join("kinetic").join("config.toml"),
+        }
+    }
+
+    /// Loads the configuration, a missing file is an empty configuration
+    pub fn load() -> KineticResult<Config> {
+        let mut s = String::new();
+        match fs::File::open(Config::default_path()) {
+            Ok(mut f) => { try!(f.read_to_string(&mut s)); },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+            Err(e) => return Err(KineticError::IoError(e)),
+        }
+
+        ::toml::from_str(&s).map_err(|_| KineticError::Usage("malformed config file".to_string()))
+    }
+
+    /// Gets a profile by name
+    pub fn profile(&self, name: &str) -> KineticResult<Profile> {
+        self.profiles.as_ref().and_then(|ps| ps.get(name)).cloned()
+            .ok_or_else(|| KineticError::Usage(format!("unknown profile {}", name)))
+    }
+
+}
+
+impl Profile {
+
+    /// Gets the PIN, from the environment, a file or a prompt, if the profile has one
+    pub fn pin(&self) -> KineticResult<Option<vec::Vec<u8>>> {
+        if let Some(ref var) = self.pin_env {
+            return env::var(var).map(|p| Some(p.into_bytes()))
+                       .map_err(|_| KineticError::Usage("PIN environment variable is not set".to_string()));
+        }
+        if let Some(ref path) = self.pin_file {
+            return read_secret(path).map(Some);
+        }
+        if self.pin_prompt.unwrap_or(false) {
+            try!(write!(io::stderr(), "PIN: "));
+            let mut pin = String::new();
+            try!(io::stdin().read_line(&mut pin));
+            return Ok(Some(pin.trim_right_matches(&['\r', '\n'][..]).as_bytes().to_vec()));
+        }
+        Ok(None)
+    }
+
+}
+
+/// Connection settings given on the command line, over the profiles
+#[derive(Debug, Clone, Default)]
+pub struct Connection {
+    pub profile: Option<String>,
+    pub identity: Option<i64>,
+    pub key_file: Option<String>,
+    /// The configuration, loaded by the first connection and shared by the clones
+    config: Arc<Mutex<Option<Config>>>,
+}
+
+impl Connection {
+
+    /// Gets the connection settings of the `--profile`, `--identity` and `--key-file` flags
+    pub fn new(profile: Option<String>, identity: Option<i64>, key_file: Option<String>) -> Connection {
+        Connection { profile: profile, identity: identity, key_file: key_file, config: Default::default() }
+    }
+
+    /// Gets the profile for a drive: `--profile`, else the drive `credentials`, else the default one
+    ///
+    /// The configuration file is only read here, once, so a malformed one breaks the
+    /// commands that connect and nothing else.
+    fn profile_for(&self, drive: &::inventory::Drive) -> KineticResult<Profile> {
+        let mut config = self.config.lock().unwrap();
+        if config.is_none() {
+            *config = Some(try!(Config::load()));
+        }
+        let config = config.as_ref().unwrap();
+
+        let name = self.profile.as_ref()
+                       .or(drive.credentials.as_ref())
+                       .or(config.default_profile.as_ref());
+        match name {
+            Some(name) => config.profile(name),
+            None => Ok(Default::default()),
+        }
+    }
+
+    /// Gets the HMAC credentials for a drive
+    ///
+    /// `--identity` and `--key-file` win over the profile, which wins over the
+    /// library default credentials.
+    pub fn credentials(&self, drive: &::inventory::Drive) -> KineticResult<Credentials> {
+        let profile = try!(self.profile_for(drive));
+
+        let (default_identity, default_key) = match Credentials::default() {
+            Credentials::Hmac { identity, key } => (identity, key),
+            Credentials::Pin { .. } => unreachable!(),
+        };
+
+        let key = match (self.key_file.as_ref(), profile.key_file.as_ref(), profile.key_env.as_ref(), profile.key.as_ref()) {
+            (Some(path), _, _, _) | (None, Some(path), _, _) => try!(read_secret(path)),
+            (None, None, Some(var), _) => try!(env::var(var)
+                .map_err(|_| KineticError::Usage("key environment variable is not set".to_string()))).into_bytes(),
+            (None, None, None, Some(key)) => key.as_bytes().to_vec(),
+            (None, None, None, None) => default_key,
+        };
+
+        Ok(Credentials::Hmac { identity: self.identity.or(profile.identity).unwrap_or(default_identity),
+                               key: key })
+    }
+
+    /// Gets the PIN for a drive from its profile
+    pub fn pin(&self, drive: &::inventory::Drive) -> KineticResult<Option<vec::Vec<u8>>> {
+        try!(self.profile_for(drive)).pin()
+    }
+
+    /// Gets the `host:port` address to connect to a drive, with the profile port
+    pub fn address(&self, drive: &::inventory::Drive) -> KineticResult<String> {
+        let profile = try!(self.profile_for(drive));
+        let drive = ::inventory::Drive { port: drive.port.or(profile.port), ..drive.clone() };
+        Ok(drive.address())
+    }
+
+    /// Connects to a drive with its profile settings
+    pub fn connect(&self, drive: &::inventory::Drive) -> KineticResult<::kinetic::AsyncClient> {
+        if try!(self.profile_for(drive)).tls.unwrap_or(false) {
+            return Err(KineticError::Usage("TLS connections are not supported yet".to_string()));
+        }
+
+        let credentials = try!(self.credentials(drive));
+        ::kinetic::Client::new_with_credentials(try!(self.address(drive)).as_str(), credentials)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use std::sync::{Arc, Mutex};
+    use kinetic::authentication::Credentials;
+    use inventory::Drive;
+    use super::{Config, Connection};
+
+    static CONFIG: &'static str = "default_profile = \"lab\"\n\
+                                   [profiles.lab]\nidentity = 2\nkey = \"lab-key\"\nport = 9123\n\
+                                   [profiles.ops]\nidentity = 3\nkey = \"ops-key\"\n\
+                                   [profiles.secure]\ntls = true\n";
+
+    fn connection(profile: Option<&str>, identity: Option<i64>, key_file: Option<String>) -> Connection {
+        let config: Config = ::toml::from_str(CONFIG).unwrap();
+        Connection { profile: profile.map(|p| p.to_string()), identity: identity, key_file: key_file,
+                     config: Arc::new(Mutex::new(Some(config))) }
+    }
+
+    fn hmac(c: Credentials) -> (i64, Vec<u8>) {
+        match c {
+            Credentials::Hmac { identity, key } => (identity, key),
+            Credentials::Pin { .. } => panic!("expected HMAC credentials"),
+        }
+    }
+
+    #[test]
+    fn profiles() {
+        let c: Config = ::toml::from_str("default_profile = \"lab\"\n[profiles.lab]\nidentity = 2\npin_env = \"PIN\"\nport = 9123\n").unwrap();
+        let p = c.profile("lab").unwrap();
+        assert_eq!(p.identity, Some(2));
+        assert_eq!(p.port, Some(9123));
+        assert!(c.profile("ops").is_err());
+    }
+
+    #[test]
+    fn unknown_settings() {
+        assert!(::toml::from_str::<Config>("[profiles.lab]\ntls_port = 8443\n").is_err());
+    }
+
+    #[test]
+    fn profile_precedence() {
+        let mut drive = Drive::from_target("10.0.3.1");
+        assert_eq!(hmac(connection(None, None, None).credentials(&drive).unwrap()), (2, b"lab-key".to_vec()));
+
+        drive.credentials = Some("ops".to_string());
+        assert_eq!(hmac(connection(None, None, None).credentials(&drive).unwrap()), (3, b"ops-key".to_vec()));
+        assert_eq!(hmac(connection(Some("lab"), None, None).credentials(&drive).unwrap()), (2, b"lab-key".to_vec()));
+        assert!(connection(Some("none"), None, None).credentials(&drive).is_err());
+    }
+
+    #[test]
+    fn flags_win_over_the_profile() {
+        let path = env::temp_dir().join(format!("kinetic-test-key-{}", process::id()));
+        fs::write(&path, "flag-key\n").unwrap();
+        let key_file = Some(path.to_string_lossy().into_owned());
+
+        let drive = Drive::from_target("10.0.3.1");
+        assert_eq!(hmac(connection(None, Some(7), None).credentials(&drive).unwrap()), (7, b"lab-key".to_vec()));
+        assert_eq!(hmac(connection(None, Some(7), key_file).credentials(&drive).unwrap()), (7, b"flag-key".to_vec()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ports() {
+        let c = connection(None, None, None);
+        assert_eq!(c.address(&Drive::from_target("10.0.3.1")).unwrap(), "10.0.3.1:9123");
+        assert_eq!(c.address(&Drive::from_target("10.0.3.1:8000")).unwrap(), "10.0.3.1:8000");
+        assert!(connection(Some("secure"), None, None).connect(&Drive::from_target("10.0.3.1")).is_err());
+    }
+}
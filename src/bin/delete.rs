@@ -0,0 +1,130 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use kinetic::KineticResult;
+use kinetic::commands::Delete;
+use serde_json::Value;
+
+
+#[derive(Deserialize, Debug)]
+pub struct DeleteArgs {
+    flag_verbose: bool,
+    flag_version: Option<String>,
+    flag_force: bool,
+    flag_hex: bool,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+    arg_key: ::std::vec::Vec<String>,
+}
+
+static USAGE: &'static str = "
+Delete keys from a kinetic device
+
+Usage: kinetic-rust delete [options] (--version VERSION | --force) (<target> | --targets LIST | --group GROUP) <key>...
+       kinetic-rust delete (-h | --help)
+
+Options:
+  -h, --help            Print this message
+  --version VERSION     Only delete entries at version VERSION
+  -f, --force           Delete regardless of the current version
+  -x, --hex             The keys and version are given in hex
+  -t, --targets LIST    Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP     Every drive of GROUP in the inventory
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+#[inline]
+fn delete(key: ::std::vec::Vec<u8>, force: bool, version: &[u8]) -> Delete {
+    if force { Delete::Forced { key: key } }
+    else { Delete::Versioned { key: key, version: version.to_vec() } }
+}
+
+fn execute(cmd: &DeleteArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-delete; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let mut keys = ::std::vec::Vec::new();
+    for k in cmd.arg_key.iter() {
+        keys.push(try!(::keys::parse(k, cmd.flag_hex)));
+    }
+
+    // the usage pattern requires one of --version or --force
+    let force = cmd.flag_force;
+    let version = try!(::keys::parse_version(&cmd.flag_version, cmd.flag_hex));
+
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
+
+    if drives.len() == 1 {
+        let c = try!(globals.connection.connect(&drives[0]));
+        return run(cmd, &c, shell, globals);
+    }
+
+    let count = keys.len();
+    let connection = globals.connection.clone();
+    let results = ::cli::fan_out(&drives, move |d| {
+        let c = try!(connection.connect(d));
+        for key in keys.iter() {
+            try!(c.send(delete(key.clone(), force, &version)));
+        }
+        Ok(())
+    });
+
+    let rows: ::std::vec::Vec<_> = drives.iter().zip(results.iter())
+        .map(|(d, r)| vec![Value::from(d.label()), if r.is_ok() { Value::from(count) } else { Value::Null }, ::cli::error_cell(r)])
+        .collect();
+    ::output::print_table(globals.format, &["Drive", "Deleted", "Error"], &rows);
+
+    Ok(()) //return
+}
+
+/// Runs the command on an open connection
+pub fn run(cmd: &DeleteArgs, c: &::kinetic::AsyncClient, shell: &mut ::shell::MultiShell,
+           _: &::cli::GlobalArgs) -> KineticResult<()> {
+    let version = try!(::keys::parse_version(&cmd.flag_version, cmd.flag_hex));
+
+    for k in cmd.arg_key.iter() {
+        let key = try!(::keys::parse(k, cmd.flag_hex));
+        try!(c.send(delete(key, cmd.flag_force, &version)));
+        try!(shell.status("Deleted", k.clone()));
+    }
+
+    Ok(()) //return
+}
+
+impl ::cli::CliCommand for DeleteArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> DeleteArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<DeleteArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<DeleteArgs>) -> &'static str { USAGE }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{vec, time};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use serde_json::Value;
+use kinetic::{KineticResult, KineticError};
+use kinetic::discovery::{Discovery, Announcement};
+use inventory::{Inventory, Drive};
+use output::Format;
+
+
+#[derive(Deserialize, Debug)]
+pub struct DiscoverArgs {
+    flag_verbose: bool,
+    flag_duration: u64,
+    flag_multicast: String,
+    flag_port: u16,
+    flag_interface: String,
+    flag_write: bool,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+}
+
+static USAGE: &'static str = "
+Discover kinetic devices on the network
+
+Listens for the multicast announcements of kinetic devices and lists each device
+heard of. With --write the devices are added to the inventory, named by their
+world wide name, or updated when already there.
+
+Usage: kinetic-rust discover [options]
+       kinetic-rust discover (-h | --help)
+
+Options:
+  -h, --help            Print this message
+  -d, --duration SECS   Seconds to listen for [default: 10]
+  --multicast ADDR      Multicast group to join [default: 239.1.2.3]
+  --port PORT           Port of the multicast group [default: 8123]
+  --interface ADDR      Address of the interface to listen on [default: 0.0.0.0]
+  -w, --write           Add the devices found to the inventory
+  -g, --group GROUP     Put the devices added to the inventory in GROUP
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+/// Adds a device to the inventory, or updates the drive of the same name
+fn add(inventory: &mut Inventory, a: &Announcement, group: &Option<String>) {
+    let name = a.id().to_string();
+    let host = a.source.ip().to_string();
+
+    let i = match inventory.drive.iter().position(|d| d.name.as_ref() == Some(&name)) {
+        Some(i) => i,
+        None => {
+            inventory.drive.push(Drive { name: Some(name), host: host.clone(), port: None,
+                                         tls_port: None, credentials: None, groups: None });
+            inventory.drive.len() - 1
+        },
+    };
+
+    let d = &mut inventory.drive[i];
+    d.host = host;
+    d.port = a.port;
+    d.tls_port = a.tls_port;
+    if let Some(ref g) = *group {
+        if !d.in_group(g) {
+            let mut groups = d.groups.take().unwrap_or(vec![]);
+            groups.push(g.clone());
+            d.groups = Some(groups);
+        }
+    }
+}
+
+fn execute(cmd: &DiscoverArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-discover; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let group: Ipv4Addr = try!(cmd.flag_multicast.parse().map_err(|_| KineticError::Usage("invalid multicast group".to_string())));
+    let interface: Ipv4Addr = try!(cmd.flag_interface.parse().map_err(|_| KineticError::Usage("invalid interface address".to_string())));
+
+    let mut discovery = try!(Discovery::bind(group, cmd.flag_port, interface));
+    if globals.format == Format::Text {
+        try!(shell.status("Listening", format!("on {}:{} for {}s", group, cmd.flag_port, cmd.flag_duration)));
+    }
+    let found = try!(discovery.listen(time::Duration::from_secs(cmd.flag_duration)));
+
+    // malformed announcements go to stderr, one by one in verbose mode
+    let malformed = discovery.malformed();
+    try!(shell.verbose(|s| {
+        for &(ref source, ref e) in malformed.iter() {
+            try!(s.warn(format!("ignored a malformed announcement from {}: {}", source, e)));
+        }
+        if discovery.malformed_count() > malformed.len() {
+            try!(s.warn(format!("and {} earlier ones", discovery.malformed_count() - malformed.len())));
+        }
+        Ok(())
+    }));
+    if discovery.malformed_count() > 0 {
+        try!(shell.concise(|s| s.warn(format!("ignored {} malformed announcements, see --verbose", discovery.malformed_count()))));
+    }
+
+    let rows: vec::Vec<vec::Vec<Value>> = found.iter().map(|a| {
+        vec![Value::from(a.world_wide_name.clone()),
+             Value::from(a.serial_number.clone()),
+             Value::from(a.model.clone()),
+             Value::from(a.firmware_version.clone()),
+             Value::from(a.source.ip().to_string()),
+             Value::from(a.port),
+             Value::from(a.tls_port),
+             Value::from(a.interfaces.iter().map(|i| i.name.clone()).collect::<vec::Vec<String>>())]
+    }).collect();
+    ::output::print_table(globals.format,
+                          &["WWN", "Serial", "Model", "Firmware", "Address", "Port", "TLS Port", "Interfaces"],
+                          &rows);
+
+    if cmd.flag_write {
+        let path = cmd.flag_inventory.as_ref().map_or(Inventory::default_path(), PathBuf::from);
+        let mut inventory = try!(Inventory::load(&path));
+        for a in found.iter() {
+            add(&mut inventory, a, &cmd.flag_group);
+        }
+        try!(inventory.save(&path));
+        if globals.format == Format::Text {
+            try!(shell.status("Saved", format!("{} drives to {}", found.len(), path.display())));
+        }
+    }
+
+    Ok(()) //return
+}
+
+impl ::cli::CliCommand for DiscoverArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> DiscoverArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<DiscoverArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<DiscoverArgs>) -> &'static str { USAGE }
+}
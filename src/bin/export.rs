@@ -0,0 +1,114 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{fs, io, vec};
+use kinetic::{KineticResult, KineticError};
+use output::Format;
+
+
+#[derive(Deserialize, Debug)]
+pub struct ExportArgs {
+    flag_verbose: bool,
+    flag_range: Option<String>,
+    flag_output: Option<String>,
+    flag_hex: bool,
+    flag_inventory: Option<String>,
+    arg_target: String,
+}
+
+static USAGE: &'static str = "
+Export the keys of a kinetic device to an archive
+
+Writes every key, or those in --range, with its value, version and integrity
+tag to a kinetic archive that 'import' restores. Range keys may use \\\\ and
+\\xNN escapes, or be given in hex with --hex.
+
+Usage: kinetic-rust export [options] <target>
+       kinetic-rust export (-h | --help)
+
+Options:
+  -h, --help            Print this message
+  -r, --range RANGE     Only the keys from a to b, both included, given as a..b
+                        where either end may be left out
+  -o, --output FILE     Write the archive to FILE instead of stdout
+  -x, --hex             The range is given in hex
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+/// Parses an `a..b` range, a missing end is `None`
+fn parse_range(range: &str, hex: bool) -> KineticResult<(vec::Vec<u8>, Option<vec::Vec<u8>>)> {
+    let i = try!(range.find("..").ok_or_else(|| KineticError::Usage("a range is given as a..b".to_string())));
+    let (start, end) = (&range[..i], &range[i + 2..]);
+
+    let start = try!(::keys::parse(start, hex));
+    let end = if end.is_empty() { None } else { Some(try!(::keys::parse(end, hex))) };
+    Ok((start, end))
+}
+
+fn execute(cmd: &ExportArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-export; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let (start, end) = match cmd.flag_range {
+        Some(ref range) => try!(parse_range(range, cmd.flag_hex)),
+        None => (vec![], None),
+    };
+
+    let drives = try!(::inventory::select(&Some(cmd.arg_target.clone()), &None, &None, &cmd.flag_inventory));
+    let c = try!(globals.connection.connect(&drives[0]));
+
+    let end = end.unwrap_or_else(|| ::ls::last_key(&c));
+
+    match cmd.flag_output {
+        Some(ref path) => {
+            let f = io::BufWriter::new(try!(fs::File::create(path)));
+            let count = try!(::kinetic::archive::export(&c, start, end, f));
+            if globals.format == Format::Text {
+                try!(shell.status("Exported", format!("{} entries to {}", count, path)));
+            }
+        },
+        None => {
+            // the archive is the output, nothing else goes to stdout
+            let stdout = io::stdout();
+            try!(::kinetic::archive::export(&c, start, end, io::BufWriter::new(stdout.lock())));
+        },
+    }
+
+    Ok(()) //return
+}
+
+impl ::cli::CliCommand for ExportArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> ExportArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<ExportArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<ExportArgs>) -> &'static str { USAGE }
+}
@@ -0,0 +1,247 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{io, fs, process};
+use std::io::Write;
+use std::path::PathBuf;
+use serde_json::Value;
+use kinetic::{KineticResult, KineticError};
+use kinetic::commands::GetVersion;
+
+
+#[derive(Deserialize, Debug)]
+pub struct GetArgs {
+    flag_verbose: bool,
+    flag_output: Option<String>,
+    flag_meta: bool,
+    flag_hex: bool,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+    arg_key: String,
+}
+
+static USAGE: &'static str = "
+Get a value from a kinetic device
+
+Keys may use \\\\ and \\xNN escapes, or be given in hex with --hex.
+
+Usage: kinetic-rust get [options] (<target> | --targets LIST | --group GROUP) <key>
+       kinetic-rust get (-h | --help)
+
+Options:
+  -h, --help            Print this message
+  -o, --output FILE     Write the value to FILE instead of stdout
+  -m, --meta            Show the version and integrity tag instead of the value,
+                        the value itself is not read
+  -x, --hex             The key is given in hex
+  -t, --targets LIST    Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP     Every drive of GROUP in the inventory
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+fn execute(cmd: &GetArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-get; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let key = try!(::keys::parse(&cmd.arg_key, cmd.flag_hex));
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
+
+    if drives.len() > 1 {
+        if !cmd.flag_meta {
+            return Err(KineticError::Usage("values can only be read from a single drive, or use --meta".to_string()));
+        }
+
+        let hex = cmd.flag_hex;
+        let connection = globals.connection.clone();
+        let results = ::cli::fan_out(&drives, move |d| {
+            let c = try!(connection.connect(d));
+            let r = try!(c.send(GetVersion { key: key.clone() }));
+            Ok(vec![Value::from(::keys::display(&r.version, hex)),
+                    Value::from(format!("{:?}", r.integrity.algorithm)),
+                    Value::from(::keys::display(&r.integrity.tag, true))])
+        });
+
+        let rows: ::std::vec::Vec<_> = drives.iter().zip(results.iter()).map(|(d, r)| {
+            let mut row = vec![Value::from(d.label())];
+            match *r {
+                Ok(ref cells) => row.extend(cells.iter().cloned()),
+                Err(_) => row.extend((0..3).map(|_| Value::Null)),
+            }
+            row.push(::cli::error_cell(r));
+            row
+        }).collect();
+
+        ::output::print_table(globals.format, &["Drive", "Version", "Algorithm", "Tag", "Error"], &rows);
+        return Ok(());
+    }
+
+    let c = try!(globals.connection.connect(&drives[0]));
+    run(cmd, &c, shell, globals)
+}
+
+/// Receives the value in a temporary file next to the output, moved over it once complete
+///
+/// A failed `get` leaves the output file as it was, or missing.
+struct OutputFile {
+    path: PathBuf,
+    temp: PathBuf,
+    file: Option<fs::File>,
+}
+
+impl OutputFile {
+
+    fn new(path: &str) -> OutputFile {
+        let path = PathBuf::from(path);
+        let name = path.file_name().map_or("output".to_string(), |n| n.to_string_lossy().into_owned());
+        // in the same directory, so the rename never crosses file systems
+        let temp = path.with_file_name(format!(".{}.{}.part", name, process::id()));
+        OutputFile { path: path, temp: temp, file: None }
+    }
+
+    fn open(&mut self) -> io::Result<&mut fs::File> {
+        if self.file.is_none() {
+            self.file = Some(try!(fs::File::create(&self.temp)));
+        }
+        Ok(self.file.as_mut().unwrap())
+    }
+
+    /// Moves the complete value over the output file
+    fn commit(mut self) -> io::Result<()> {
+        let r = self.open().and_then(|f| f.sync_all()).and_then(|_| fs::rename(&self.temp, &self.path));
+        if r.is_err() { self.discard(); }
+        r
+    }
+
+    /// Removes the partial value
+    fn discard(&mut self) {
+        if self.file.take().is_some() { let _ = fs::remove_file(&self.temp); }
+    }
+
+}
+
+impl Write for OutputFile {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        try!(self.open()).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file {
+            Some(ref mut f) => f.flush(),
+            None => Ok(()),
+        }
+    }
+
+}
+
+/// Runs the command on an open connection
+pub fn run(cmd: &GetArgs, c: &::kinetic::AsyncClient, shell: &mut ::shell::MultiShell,
+           globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    let key = try!(::keys::parse(&cmd.arg_key, cmd.flag_hex));
+
+    if cmd.flag_meta {
+        let r = try!(c.send(GetVersion { key: key }));
+        return ::output::print_record(globals.format, shell, &[
+            ("Version", Value::from(::keys::display(&r.version, cmd.flag_hex))),
+            ("Algorithm", Value::from(format!("{:?}", r.integrity.algorithm))),
+            ("Tag", Value::from(::keys::display(&r.integrity.tag, true)))]);
+    }
+
+    // stream the value, it may be as large as maxValueSize
+    match cmd.flag_output {
+        Some(ref path) => {
+            let mut f = OutputFile::new(path);
+            match c.get_into_writer(key, &mut f) {
+                Ok(_) => try!(f.commit()),
+                Err(e) => {
+                    // don't leave a partial value behind
+                    f.discard();
+                    return Err(e);
+                },
+            }
+        },
+        None => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            try!(c.get_into_writer(key, &mut out));
+        },
+    }
+
+    Ok(()) //return
+}
+
+impl ::cli::CliCommand for GetArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> GetArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<GetArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<GetArgs>) -> &'static str { USAGE }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use std::io::{Read, Write};
+    use super::OutputFile;
+
+    fn contents(path: &str) -> String {
+        let mut s = String::new();
+        fs::File::open(path).unwrap().read_to_string(&mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn output_is_only_replaced_when_complete() {
+        let dir = env::temp_dir().join(format!("kinetic-get-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("value");
+        let path = path.to_str().unwrap();
+        fs::File::create(path).unwrap().write_all(b"original").unwrap();
+
+        // a failed download keeps the original
+        let mut f = OutputFile::new(path);
+        f.write_all(b"part").unwrap();
+        f.discard();
+        assert_eq!(contents(path), "original");
+
+        let mut f = OutputFile::new(path);
+        f.write_all(b"new value").unwrap();
+        assert_eq!(contents(path), "original");
+        f.commit().unwrap();
+        assert_eq!(contents(path), "new value");
+
+        // nothing but the output is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use kinetic::KineticResult;
+use serde_json::Value;
+use kinetic::responses::GetLogResponse;
+use output::Format;
+
+
+pub fn print(log: &GetLogResponse, format: Format, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+    let capacity = log.get_capacity();
+    let total = capacity.get_nominalCapacityInBytes();
+    let full = capacity.get_portionFull() as f64;
+
+    match format {
+        Format::Text => {
+            try!(shell.tag("Capacity", format!("{:.2} GB", total as f64 / 1e9)));
+            try!(shell.tag("Used", format!("{:.2} GB ({:.1}%)", total as f64 * full / 1e9, full * 100.0)));
+            try!(shell.tag("Free", format!("{:.2} GB", total as f64 * (1.0 - full) / 1e9)));
+        },
+        _ => try!(::output::print_record(format, shell, &[
+            ("nominal_capacity_bytes", Value::from(total)),
+            ("portion_full", Value::from(full))])),
+    }
+    Ok(()) //return
+}
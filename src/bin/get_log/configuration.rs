@@ -0,0 +1,66 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use kinetic::KineticResult;
+use serde_json::Value;
+use kinetic::responses::GetLogResponse;
+use output::Format;
+
+
+fn to_utf8(s: &[u8]) -> String {
+    String::from_utf8_lossy(s).into_owned()
+}
+
+pub fn print(log: &GetLogResponse, format: Format, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+    let c = log.get_configuration();
+
+    let mut fields = vec![("Vendor", Value::from(c.get_vendor())),
+                          ("Model", Value::from(c.get_model())),
+                          ("SN", Value::from(to_utf8(c.get_serialNumber()))),
+                          ("WWN", Value::from(to_utf8(c.get_worldWideName()))),
+                          ("Firmware", Value::from(c.get_version())),
+                          ("Protocol", Value::from(c.get_protocolVersion())),
+                          ("Port", Value::from(c.get_port())),
+                          ("Tls port", Value::from(c.get_tlsPort()))];
+
+    let headers = ["Interface", "MAC", "IPv4", "IPv6"];
+    let rows: Vec<Vec<Value>> = c.get_interface().iter()
+        .map(|i| vec![Value::from(i.get_name()),
+                      Value::from(to_utf8(i.get_MAC())),
+                      Value::from(to_utf8(i.get_ipv4Address())),
+                      Value::from(to_utf8(i.get_ipv6Address()))])
+        .collect();
+
+    if format == Format::Text {
+        try!(::output::print_record(format, shell, &fields));
+        ::output::print_table(format, &headers, &rows);
+    } else {
+        // a single record, so the output stays one document
+        let interfaces = rows.iter().map(|r| match format {
+            Format::Json => ::output::json_object(&headers, r),
+            _ => Value::Array(r.clone()),
+        }).collect();
+        fields.push(("Interfaces", Value::Array(interfaces)));
+        try!(::output::print_record(format, shell, &fields));
+    }
+    Ok(()) //return
+}
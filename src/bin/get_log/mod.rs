@@ -20,43 +20,182 @@
 
 // author: Ignacio Corderi
 
-use kinetic::KineticResult;
-
+use std::{cmp, io, thread};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use kinetic::{KineticResult, KineticError};
+use kinetic::commands::{GetLog, GetDeviceLog};
+use kinetic::responses::GetLogResponse;
+use kinetic::proto::command::LogType;
+use output::Format;
+
+mod messages;
+mod utilization;
+mod temperature;
+mod capacity;
+mod statistics;
+mod limits;
+mod configuration;
+
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LogKind {
+    Messages,
+    Utilization,
+    Temperature,
+    Capacity,
+    Statistics,
+    Limits,
+    Configuration,
+    Device,
+}
 
-#[derive(RustcDecodable, Debug)]
+#[derive(Deserialize, Debug)]
 pub struct LogArgs {
     flag_verbose: bool,
-    arg_target: String,
+    flag_watch: Option<u64>,
+    arg_type: LogKind,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+    arg_name: Option<String>,
+}
+
+impl LogArgs {
+
+    /// Whether the log is polled until interrupted
+    #[inline]
+    pub fn watches(&self) -> bool {
+        self.flag_watch.is_some()
+    }
+
 }
 
 static USAGE: &'static str = "
 Get log information from kinetic device
 
-Usage: kinetic-rust log messages [options] <target>
+<type> is one of messages, utilization, temperature, capacity, statistics,
+limits, configuration or device. The device log takes the <name> of the
+vendor specific log to get.
+
+With --watch the log is polled every SECS seconds, statistics then show
+the counters change since the previous poll and their rates.
+
+Usage: kinetic-rust log [options] <type> (<target> | --targets LIST | --group GROUP) [<name>]
        kinetic-rust log (-h | --help)
 
 Options:
   -h, --help            Print this message
+  -w, --watch SECS      Poll the log every SECS seconds
+  -t, --targets LIST    Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP     Every drive of GROUP in the inventory
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
   -v, --verbose         Use verbose output
 ";
 
-fn to_utf8(s: &[u8]) -> String {
-    String::from_utf8(s.to_vec()).unwrap()
+fn log_type(kind: LogKind) -> LogType {
+    match kind {
+        LogKind::Messages => LogType::MESSAGES,
+        LogKind::Utilization => LogType::UTILIZATIONS,
+        LogKind::Temperature => LogType::TEMPERATURES,
+        LogKind::Capacity => LogType::CAPACITIES,
+        LogKind::Statistics => LogType::STATISTICS,
+        LogKind::Limits => LogType::LIMITS,
+        LogKind::Configuration => LogType::CONFIGURATION,
+        LogKind::Device => LogType::DEVICE,
+    }
 }
 
-fn execute(cmd: &LogArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+/// Prints a log, `since` is the previous poll when watching
+fn print_log(kind: LogKind, x: &GetLogResponse, since: Option<(&GetLogResponse, Duration)>,
+             format: Format, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+    match kind {
+        LogKind::Messages => messages::print(x, format, shell),
+        LogKind::Utilization => utilization::print(x, format, shell),
+        LogKind::Temperature => temperature::print(x, format, shell),
+        LogKind::Capacity => capacity::print(x, format, shell),
+        LogKind::Statistics => statistics::print(x, since, format, shell),
+        LogKind::Limits => limits::print(x, format, shell),
+        LogKind::Configuration => configuration::print(x, format, shell),
+        LogKind::Device => unreachable!(),
+    } // return
+}
+
+/// Gets the log of every drive concurrently and prints them one after the other
+fn print_many(kind: LogKind, drives: &[::inventory::Drive], globals: &::cli::GlobalArgs,
+              shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+    let (connection, format) = (globals.connection.clone(), globals.format);
+    let results = ::cli::fan_out(drives, move |d| {
+        let c = try!(connection.connect(d));
+        c.send(GetLog { log_types: vec![log_type(kind)] })
+    });
+
+    for (d, r) in drives.iter().zip(results) {
+        if format == Format::Text {
+            try!(shell.header(d.label()));
+        }
+        match r {
+            Ok(x) => try!(print_log(kind, &x, None, format, shell)),
+            Err(ref e) if format == Format::Text => try!(::cli::print_error(shell, e)),
+            Err(ref e) => ::output::print_error(format, e),
+        }
+    }
+
+    Ok(()) //return
+}
+
+fn execute(cmd: &LogArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
     //debug!("executing; cmd=kinetic-rust-log; args={}", ::std::env::args());
     shell.set_verbose(cmd.flag_verbose);
 
-    try!(shell.status("Connecting", format!("device at {}:8123", cmd.arg_target)));
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
+    if drives.len() > 1 {
+        if cmd.arg_type == LogKind::Device || cmd.flag_watch.is_some() {
+            return Err(KineticError::Usage("device logs and --watch need a single drive".to_string()));
+        }
+        return print_many(cmd.arg_type, &drives, globals, shell);
+    }
 
-    let c = try!(::kinetic::Client::new(format!("{}:8123", cmd.arg_target).as_str()));
+    let target = try!(globals.connection.address(&drives[0]));
+    if globals.format == Format::Text {
+        try!(shell.status("Connecting", format!("device at {}", target)));
+    }
 
-    let x = try!(c.send(::kinetic::commands::GetLog { log_types: vec![::kinetic::proto::command::LogType::MESSAGES]}));
+    let c = try!(globals.connection.connect(&drives[0]));
+    run(cmd, &c, shell, globals)
+}
 
-    let s = x.get_messages();
+/// Runs the command on an open connection
+pub fn run(cmd: &LogArgs, c: &::kinetic::AsyncClient, shell: &mut ::shell::MultiShell,
+           globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    if cmd.arg_type == LogKind::Device {
+        let name = match cmd.arg_name {
+            Some(ref name) => name.as_bytes().to_vec(),
+            None => return Err(KineticError::Usage("the device log needs a <name>".to_string())),
+        };
+        let x = try!(c.send(GetDeviceLog { name: name }));
+        try!(io::stdout().write_all(&x.value));
+        return Ok(());
+    }
 
-    println!("{}", to_utf8(s));
+    let mut previous: Option<(GetLogResponse, Instant)> = None;
+    loop {
+        let x = try!(c.send(GetLog { log_types: vec![log_type(cmd.arg_type)] }));
+        let polled = Instant::now();
+
+        let since = previous.as_ref().map(|&(ref p, at)| (p, polled.duration_since(at)));
+        try!(print_log(cmd.arg_type, &x, since, globals.format, shell));
+
+        match cmd.flag_watch {
+            Some(secs) => {
+                previous = Some((x, polled));
+                thread::sleep(Duration::from_secs(cmp::max(secs, 1)));
+                if globals.format == Format::Text { println!(); }
+            },
+            None => break,
+        }
+    }
 
     Ok(()) //return
 }
@@ -64,13 +203,13 @@ fn execute(cmd: &LogArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()>
 impl ::cli::CliCommand for LogArgs {
     fn from_argv(argv: ::std::vec::Vec<String>) -> LogArgs {
         ::docopt::Docopt::new(::cli::CliCommand::usage(None::<LogArgs>))
-        .and_then(|d| d.argv(argv.clone().into_iter()).decode() )
+        .and_then(|d| d.argv(argv).deserialize() )
         .unwrap_or_else(|e| e.exit())
     }
 
     #[inline]
-    fn execute(&self, shell: &mut ::shell::MultiShell) -> ::kinetic::KineticResult<()> {
-        execute(self, shell)
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
     }
 
     #[inline]
@@ -0,0 +1,69 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::time::Duration;
+use kinetic::KineticResult;
+use serde_json::Value;
+use kinetic::responses::GetLogResponse;
+use output::Format;
+
+
+/// Prints the counters, and with a `previous` poll their change and rates since then
+pub fn print(log: &GetLogResponse, previous: Option<(&GetLogResponse, Duration)>,
+             format: Format, _: &mut ::shell::MultiShell) -> KineticResult<()> {
+
+    match previous {
+        None => {
+            let rows: Vec<Vec<Value>> = log.get_statistics().iter()
+                .map(|s| vec![Value::from(format!("{:?}", s.get_messageType())),
+                              Value::from(s.get_count()),
+                              Value::from(s.get_bytes())])
+                .collect();
+
+            ::output::print_table(format, &["Message", "Count", "Bytes"], &rows);
+        },
+        Some((before, elapsed)) => {
+            let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+
+            let rows: Vec<Vec<Value>> = log.get_statistics().iter().map(|s| {
+                let (count, bytes) = before.get_statistics().iter()
+                    .find(|b| b.get_messageType() == s.get_messageType())
+                    .map_or((0, 0), |b| (b.get_count(), b.get_bytes()));
+                // counters restart with the device
+                let delta_count = s.get_count().saturating_sub(count);
+                let delta_bytes = s.get_bytes().saturating_sub(bytes);
+
+                vec![Value::from(format!("{:?}", s.get_messageType())),
+                     Value::from(s.get_count()),
+                     Value::from(s.get_bytes()),
+                     Value::from(delta_count),
+                     Value::from(delta_bytes),
+                     ::output::round(delta_count as f64 / secs, 1),
+                     ::output::round(delta_bytes as f64 / (1024.0 * 1024.0) / secs, 2)]
+            }).collect();
+
+            ::output::print_table(format, &["Message", "Count", "Bytes", "+Count", "+Bytes", "op/s", "MB/s"], &rows);
+        },
+    }
+
+    Ok(()) //return
+}
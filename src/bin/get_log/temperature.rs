@@ -0,0 +1,40 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use kinetic::KineticResult;
+use serde_json::Value;
+use kinetic::responses::GetLogResponse;
+use output::Format;
+
+
+pub fn print(log: &GetLogResponse, format: Format, _: &mut ::shell::MultiShell) -> KineticResult<()> {
+    let rows: Vec<Vec<Value>> = log.get_temperatures().iter()
+        .map(|t| vec![Value::from(t.get_name()),
+                      ::output::round(t.get_current() as f64, 1),
+                      ::output::round(t.get_minimum() as f64, 1),
+                      ::output::round(t.get_maximum() as f64, 1),
+                      ::output::round(t.get_target() as f64, 1)])
+        .collect();
+
+    ::output::print_table(format, &["Name", "Current (C)", "Minimum", "Maximum", "Target"], &rows);
+    Ok(()) //return
+}
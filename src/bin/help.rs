@@ -24,7 +24,7 @@ use kinetic::KineticResult;
 //use std::ascii::OwnedAsciiExt;
 
 
-#[derive(RustcDecodable, Debug)]
+#[derive(Deserialize, Debug)]
 pub struct HelpArgs {
     flag_verbose: bool,
     arg_command: ::main::Command,
@@ -41,7 +41,7 @@ Options:
   -v, --verbose         Use verbose output
 ";
 
-fn execute(cmd: &HelpArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+fn execute(cmd: &HelpArgs, shell: &mut ::shell::MultiShell, _: &::cli::GlobalArgs) -> KineticResult<()> {
     //debug!("executing; cmd=kinetic-rust-help; args={}", ::std::env::args());
     shell.set_verbose(cmd.flag_verbose);
 
@@ -55,13 +55,13 @@ fn execute(cmd: &HelpArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()>
 impl ::cli::CliCommand for HelpArgs {
     fn from_argv(argv: ::std::vec::Vec<String>) -> HelpArgs {
         ::docopt::Docopt::new(::cli::CliCommand::usage(None::<HelpArgs>))
-            .and_then(|d| d.argv(argv.clone().into_iter()).decode() )
+            .and_then(|d| d.argv(argv).deserialize() )
             .unwrap_or_else(|e| e.exit())
     }
 
     #[inline]
-    fn execute(&self, shell: &mut ::shell::MultiShell) -> ::kinetic::KineticResult<()> {
-        execute(self, shell)
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
     }
 
     #[inline]
@@ -0,0 +1,105 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{fs, io};
+use serde_json::Value;
+use kinetic::{KineticResult, KineticError};
+use kinetic::archive::ImportMode;
+
+
+#[derive(Deserialize, Debug)]
+pub struct ImportArgs {
+    flag_verbose: bool,
+    flag_mode: String,
+    flag_input: Option<String>,
+    flag_inventory: Option<String>,
+    arg_target: String,
+}
+
+static USAGE: &'static str = "
+Import an archive into a kinetic device
+
+Restores the entries of an archive written by 'export', with their versions.
+The --mode decides what happens to keys already on the device: with preserve
+the import stops at the first one, with force they are overwritten and with
+skip-existing they are left alone.
+
+Usage: kinetic-rust import [options] <target>
+       kinetic-rust import (-h | --help)
+
+Options:
+  -h, --help            Print this message
+  -m, --mode MODE       preserve, force or skip-existing [default: preserve]
+  -i, --input FILE      Read the archive from FILE instead of stdin
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+#[inline]
+fn mode(mode: &str) -> KineticResult<ImportMode> {
+    match mode {
+        "preserve" => Ok(ImportMode::Preserve),
+        "force" => Ok(ImportMode::Force),
+        "skip-existing" => Ok(ImportMode::SkipExisting),
+        _ => Err(KineticError::Usage("the mode is one of preserve, force or skip-existing".to_string())),
+    }
+}
+
+fn execute(cmd: &ImportArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-import; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let mode = try!(mode(&cmd.flag_mode));
+    let drives = try!(::inventory::select(&Some(cmd.arg_target.clone()), &None, &None, &cmd.flag_inventory));
+    let c = try!(globals.connection.connect(&drives[0]));
+
+    let report = match cmd.flag_input {
+        Some(ref path) => {
+            let f = io::BufReader::new(try!(fs::File::open(path)));
+            try!(::kinetic::archive::import(&c, f, mode))
+        },
+        None => {
+            let stdin = io::stdin();
+            try!(::kinetic::archive::import(&c, stdin.lock(), mode))
+        },
+    };
+
+    ::output::print_record(globals.format, shell, &[
+        ("Imported", Value::from(report.imported)),
+        ("Skipped", Value::from(report.skipped))]) //return
+}
+
+impl ::cli::CliCommand for ImportArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> ImportArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<ImportArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<ImportArgs>) -> &'static str { USAGE }
+}
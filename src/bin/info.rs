@@ -20,39 +20,116 @@
 
 // author: Ignacio Corderi
 
+use serde_json::Value;
 use kinetic::KineticResult;
+use output::Format;
 
 
-#[derive(RustcDecodable, Debug)]
+#[derive(Deserialize, Debug)]
 pub struct InfoArgs {
     flag_verbose: bool,
     flag_detailed: bool,
-    arg_target: String,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
 }
 
 static USAGE: &'static str = "
 Get info from kinetic device
 
-Usage: kinetic-rust info [options] <target>
+Usage: kinetic-rust info [options] (<target> | --targets LIST | --group GROUP)
        kinetic-rust info (-h | --help)
 
 Options:
   -h, --help            Print this message
   -d, --detailed        Shows more detailed information
+  -t, --targets LIST    Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP     Every drive of GROUP in the inventory
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
   -v, --verbose         Use verbose output
 ";
 
 fn to_utf8(s: &[u8]) -> String {
-    String::from_utf8(s.to_vec()).unwrap()
+    String::from_utf8_lossy(s).into_owned()
 }
 
-fn execute(cmd: &InfoArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+/// Prints every detail, one field each, for the JSON and CSV formats
+fn print_structured(c: &::kinetic::AsyncClient, format: Format, shell: &mut ::shell::MultiShell)
+        -> KineticResult<()> {
+    let config = c.get_config();
+    let headers = ["name", "mac", "ipv4", "ipv6"];
+    let interfaces = config.get_interface().iter().map(|i| {
+        let cells = [Value::from(i.get_name()), Value::from(to_utf8(i.get_MAC())),
+                     Value::from(to_utf8(i.get_ipv4Address())), Value::from(to_utf8(i.get_ipv6Address()))];
+        match format {
+            Format::Json => ::output::json_object(&headers, &cells),
+            _ => Value::Array(cells.to_vec()),
+        }
+    }).collect();
+
+    ::output::print_record(format, shell, &[
+        ("vendor", Value::from(config.get_vendor())),
+        ("model", Value::from(config.get_model())),
+        ("serial_number", Value::from(to_utf8(config.get_serialNumber()))),
+        ("wwn", Value::from(to_utf8(config.get_worldWideName()))),
+        ("firmware_version", Value::from(config.get_version())),
+        ("firmware_date", Value::from(config.get_compilationDate())),
+        ("firmware_hash", Value::from(config.get_sourceHash())),
+        ("protocol_version", Value::from(config.get_protocolVersion())),
+        ("protocol_date", Value::from(config.get_protocolCompilationDate())),
+        ("protocol_hash", Value::from(config.get_protocolSourceHash())),
+        ("port", Value::from(config.get_port())),
+        ("tls_port", Value::from(config.get_tlsPort())),
+        ("interfaces", Value::Array(interfaces))]) // return
+}
+
+/// Prints a row per drive
+fn print_many(drives: &[::inventory::Drive], globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    let connection = globals.connection.clone();
+    let results = ::cli::fan_out(drives, move |d| {
+        let c = try!(connection.connect(d));
+        let config = c.get_config();
+        Ok(vec![Value::from(config.get_vendor()),
+                Value::from(config.get_model()),
+                Value::from(to_utf8(config.get_serialNumber())),
+                Value::from(to_utf8(config.get_worldWideName())),
+                Value::from(config.get_version()),
+                Value::from(config.get_protocolVersion())])
+    });
+
+    let rows: Vec<Vec<Value>> = drives.iter().zip(results.iter()).map(|(d, r)| {
+        let mut row = vec![Value::from(d.label())];
+        match *r {
+            Ok(ref cells) => row.extend(cells.iter().cloned()),
+            Err(_) => row.extend((0..6).map(|_| Value::Null)),
+        }
+        row.push(::cli::error_cell(r));
+        row
+    }).collect();
+
+    ::output::print_table(globals.format, &["Drive", "Vendor", "Model", "SN", "WWN", "Firmware", "Protocol", "Error"], &rows);
+    Ok(()) //return
+}
+
+fn execute(cmd: &InfoArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
     //debug!("executing; cmd=kinetic-rust-info; args={}", ::std::env::args());
     shell.set_verbose(cmd.flag_verbose);
 
-    try!(shell.status("Connecting", format!("device at {}:8123", cmd.arg_target)));
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
+    if drives.len() > 1 {
+        return print_many(&drives, globals);
+    }
+
+    let target = try!(globals.connection.address(&drives[0]));
+    if globals.format != Format::Text {
+        let c = try!(globals.connection.connect(&drives[0]));
+        return print_structured(&c, globals.format, shell);
+    }
+
+    try!(shell.status("Connecting", format!("device at {}", target)));
 
-    let c = try!(::kinetic::Client::new(format!("{}:8123", cmd.arg_target).as_str()));
+    let c = try!(globals.connection.connect(&drives[0]));
 
     if cmd.flag_detailed {
         try!(shell.header("Device"));
@@ -134,13 +211,13 @@ fn execute(cmd: &InfoArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()>
 impl ::cli::CliCommand for InfoArgs {
     fn from_argv(argv: ::std::vec::Vec<String>) -> InfoArgs {
         ::docopt::Docopt::new(::cli::CliCommand::usage(None::<InfoArgs>))
-            .and_then(|d| d.argv(argv.clone().into_iter()).decode() )
+            .and_then(|d| d.argv(argv).deserialize() )
             .unwrap_or_else(|e| e.exit())
     }
 
     #[inline]
-    fn execute(&self, shell: &mut ::shell::MultiShell) -> ::kinetic::KineticResult<()> {
-        execute(self, shell)
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
     }
 
     #[inline]
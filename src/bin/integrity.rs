@@ -0,0 +1,106 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{io, vec};
+use std::io::Read;
+use crypto::sha1::Sha1;
+use crypto::sha2::Sha256;
+use crypto::digest::Digest;
+use kinetic::commands::common::Integrity;
+use kinetic::digest::{self, Crc};
+use kinetic::proto::command::Algorithm;
+
+
+/// Integrity algorithms the CLI can compute
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum IntegrityArg {
+    Sha1,
+    Sha2,
+    Crc32,
+    Crc32c,
+}
+
+/// Computes a tag over a stream, without holding it in memory
+enum Hasher {
+    Digest(Box<Digest>),
+    Crc(Box<Crc>),
+}
+
+impl Hasher {
+
+    fn update(&mut self, data: &[u8]) {
+        match *self {
+            Hasher::Digest(ref mut d) => d.input(data),
+            Hasher::Crc(ref mut c) => c.update(data),
+        }
+    }
+
+    fn finish(&mut self) -> vec::Vec<u8> {
+        match *self {
+            Hasher::Digest(ref mut d) => {
+                let mut tag = vec![0u8; d.output_bytes()];
+                d.result(&mut tag);
+                tag
+            },
+            Hasher::Crc(ref c) => {
+                let crc = c.finish();
+                vec![(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8]
+            },
+        }
+    }
+
+}
+
+impl IntegrityArg {
+
+    fn hasher(&self) -> (Hasher, Algorithm) {
+        match *self {
+            IntegrityArg::Sha1 => (Hasher::Digest(Box::new(Sha1::new())), Algorithm::SHA1),
+            IntegrityArg::Sha2 => (Hasher::Digest(Box::new(Sha256::new())), Algorithm::SHA2),
+            IntegrityArg::Crc32 => (Hasher::Crc(Box::new(Crc::new(digest::CRC32))), Algorithm::CRC32),
+            IntegrityArg::Crc32c => (Hasher::Crc(Box::new(Crc::new(digest::CRC32C))), Algorithm::CRC32C),
+        }
+    }
+
+    /// Computes the integrity tag of `data`
+    pub fn integrity(&self, data: &[u8]) -> Integrity {
+        let (mut h, algorithm) = self.hasher();
+        h.update(data);
+        Integrity { tag: h.finish(), algorithm: algorithm }
+    }
+
+    /// Computes the integrity tag of everything read from `reader`
+    pub fn integrity_of(&self, reader: &mut Read) -> io::Result<Integrity> {
+        let (mut h, algorithm) = self.hasher();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => h.update(&buf[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Integrity { tag: h.finish(), algorithm: algorithm })
+    }
+
+}
@@ -0,0 +1,210 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Drive inventories
+//!
+//! An inventory is a TOML file listing drives, by default at
+//! `~/.config/kinetic/inventory.toml`:
+//!
+//! ```toml
+//! [[drive]]
+//! name = "rack3-01"          # optional, defaults to host:port
+//! host = "10.0.3.1"
+//! port = 8123                # optional
+//! tls_port = 8443            # optional
+//! credentials = "ops"        # optional, name of the profile holding the credentials
+//! groups = ["rack3", "ssd"]  # optional
+//! ```
+
+use std::{env, fs, vec};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use kinetic::{KineticResult, KineticError};
+
+
+/// A drive of the inventory
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Drive {
+    pub name: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub tls_port: Option<u16>,
+    pub credentials: Option<String>,
+    pub groups: Option<vec::Vec<String>>,
+}
+
+impl Drive {
+
+    /// Gets a drive from a `host`, `host:port`, `[address]` or `[address]:port` target
+    ///
+    /// A bare address with more than one `:` is an IPv6 address without a port.
+    pub fn from_target(target: &str) -> Drive {
+        let (host, port) = if target.starts_with('[') {
+            match target.find(']') {
+                Some(i) => {
+                    let port = &target[i + 1..];
+                    if port.is_empty() {
+                        (&target[1..i], None)
+                    } else if port.starts_with(':') && port[1..].parse::<u16>().is_ok() {
+                        (&target[1..i], port[1..].parse().ok())
+                    } else {
+                        (target, None)
+                    }
+                },
+                None => (target, None),
+            }
+        } else if target.matches(':').count() == 1 {
+            let i = target.find(':').unwrap();
+            match target[i + 1..].parse() {
+                Ok(port) => (&target[..i], Some(port)),
+                Err(_) => (target, None),
+            }
+        } else {
+            (target, None)
+        };
+
+        Drive { name: None, host: host.to_string(), port: port, tls_port: None, credentials: None, groups: None }
+    }
+
+    /// Gets the `host:port` address of the drive, `[address]:port` for IPv6
+    #[inline]
+    pub fn address(&self) -> String {
+        let port = self.port.unwrap_or(::cli::DEFAULT_PORT);
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, port)
+        } else {
+            format!("{}:{}", self.host, port)
+        }
+    }
+
+    /// Gets the name of the drive, its address if it has none
+    #[inline]
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or(self.address())
+    }
+
+    #[inline]
+    pub fn in_group(&self, group: &str) -> bool {
+        self.groups.as_ref().map_or(false, |gs| gs.iter().any(|g| g == group))
+    }
+
+}
+
+/// A set of drives
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Inventory {
+    #[serde(default)]
+    pub drive: vec::Vec<Drive>,
+}
+
+impl Inventory {
+
+    /// Gets the inventory path, `$KINETIC_INVENTORY` or `~/.config/kinetic/inventory.toml`
+    pub fn default_path() -> PathBuf {
+        match env::var("KINETIC_INVENTORY") {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => env::home_dir().unwrap_or(PathBuf::from("."))
+                          .join(".config").join("kinetic").join("inventory.toml"),
+        }
+    }
+
+    /// Loads an inventory, a missing file is an empty inventory
+    pub fn load(path: &Path) -> KineticResult<Inventory> {
+        let mut s = String::new();
+        match fs::File::open(path) {
+            Ok(mut f) => { try!(f.read_to_string(&mut s)); },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+            Err(e) => return Err(KineticError::IoError(e)),
+        }
+
+        ::toml::from_str(&s).map_err(|_| KineticError::Usage("malformed inventory file".to_string()))
+    }
+
+    /// Saves the inventory, creating its directory if needed
+    pub fn save(&self, path: &Path) -> KineticResult<()> {
+        if let Some(dir) = path.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+        let mut f = try!(fs::File::create(path));
+        let s = try!(::toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
+        try!(f.write_all(s.as_bytes()));
+        Ok(())
+    }
+
+    /// Finds a drive by name or address
+    pub fn find(&self, target: &str) -> Option<&Drive> {
+        self.drive.iter().find(|d| d.name.as_ref().map_or(false, |n| n == target) || d.address() == target)
+    }
+
+}
+
+/// Gets the drives selected by a command
+///
+/// Either a single `target`, a comma separated list of `targets` or a `group`. Targets
+/// are looked up in the inventory by name or address, unknown ones are taken as `host[:port]`.
+pub fn select(target: &Option<String>, targets: &Option<String>, group: &Option<String>,
+              inventory: &Option<String>) -> KineticResult<vec::Vec<Drive>> {
+    let path = inventory.as_ref().map_or(Inventory::default_path(), PathBuf::from);
+    let inventory = try!(Inventory::load(&path));
+
+    let lookup = |t: &str| inventory.find(t).cloned().unwrap_or(Drive::from_target(t));
+
+    let drives: vec::Vec<Drive> = match (target, targets, group) {
+        (&Some(ref t), _, _) => vec![lookup(t)],
+        (_, &Some(ref ts), _) => ts.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()).map(lookup).collect(),
+        (_, _, &Some(ref g)) => inventory.drive.iter().filter(|d| d.in_group(g)).cloned().collect(),
+        _ => vec![],
+    };
+
+    if drives.is_empty() {
+        return Err(KineticError::Usage("no drives selected".to_string()));
+    }
+    Ok(drives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Drive;
+
+    fn target(t: &str) -> (String, Option<u16>) {
+        let d = Drive::from_target(t);
+        (d.host, d.port)
+    }
+
+    #[test]
+    fn targets() {
+        assert_eq!(target("10.0.3.1"), ("10.0.3.1".to_string(), None));
+        assert_eq!(target("10.0.3.1:9123"), ("10.0.3.1".to_string(), Some(9123)));
+        assert_eq!(target("rack3-01:x"), ("rack3-01:x".to_string(), None));
+        assert_eq!(target("::1"), ("::1".to_string(), None));
+        assert_eq!(target("fe80::1"), ("fe80::1".to_string(), None));
+        assert_eq!(target("[fe80::1]"), ("fe80::1".to_string(), None));
+        assert_eq!(target("[::1]:9123"), ("::1".to_string(), Some(9123)));
+    }
+
+    #[test]
+    fn addresses() {
+        assert_eq!(Drive::from_target("10.0.3.1").address(), "10.0.3.1:8123");
+        assert_eq!(Drive::from_target("::1").address(), "[::1]:8123");
+        assert_eq!(Drive::from_target("[::1]:9123").address(), "[::1]:9123");
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::vec;
+use kinetic::{KineticResult, KineticError};
+
+
+/// Shows a key as hex, or as text with `\\` and `\xNN` escapes for anything not printable
+pub fn display(key: &[u8], hex: bool) -> String {
+    if hex {
+        return key.iter().map(|b| format!("{:02x}", b)).collect();
+    }
+
+    let mut s = String::new();
+    for &b in key {
+        match b {
+            b'\\' => s.push_str("\\\\"),
+            0x20...0x7e => s.push(b as char),
+            _ => s.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    s
+}
+
+fn hex_digit(c: u8) -> KineticResult<u8> {
+    match c {
+        b'0'...b'9' => Ok(c - b'0'),
+        b'a'...b'f' => Ok(c - b'a' + 10),
+        b'A'...b'F' => Ok(c - b'A' + 10),
+        _ => Err(KineticError::Usage("invalid hex digit in key".to_string())),
+    }
+}
+
+/// Parses a key shown by `display`
+pub fn parse(key: &str, hex: bool) -> KineticResult<vec::Vec<u8>> {
+    let bytes = key.as_bytes();
+    let mut k = vec::Vec::new();
+
+    if hex {
+        if bytes.len() % 2 != 0 {
+            return Err(KineticError::Usage("hex key has an odd number of digits".to_string()));
+        }
+        for pair in bytes.chunks(2) {
+            k.push(try!(hex_digit(pair[0])) << 4 | try!(hex_digit(pair[1])));
+        }
+        return Ok(k);
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'\\', Some(&b'\\')) => { k.push(b'\\'); i += 2; },
+            (b'\\', Some(&b'x')) if i + 3 < bytes.len() => {
+                k.push(try!(hex_digit(bytes[i + 2])) << 4 | try!(hex_digit(bytes[i + 3])));
+                i += 4;
+            },
+            (b'\\', _) => return Err(KineticError::Usage("invalid escape in key".to_string())),
+            (b, _) => { k.push(b); i += 1; },
+        }
+    }
+    Ok(k)
+}
+
+/// Parses a `--version` flag shown by `display`, missing it's the empty version
+#[inline]
+pub fn parse_version(version: &Option<String>, hex: bool) -> KineticResult<vec::Vec<u8>> {
+    match *version {
+        Some(ref v) => parse(v, hex),
+        None => Ok(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{display, parse, parse_version};
+
+    #[test]
+    fn roundtrip() {
+        let all: Vec<u8> = (0..256).map(|b| b as u8).collect();
+        for key in [vec![], b"hello".to_vec(), b"a\\b\\\\x41".to_vec(), vec![0x00, 0xff, b'\\', b'x'], all].iter() {
+            assert_eq!(&parse(&display(key, false), false).unwrap(), key);
+            assert_eq!(&parse(&display(key, true), true).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn display_escapes_what_is_not_printable() {
+        assert_eq!(display(b"users/42", false), "users/42");
+        assert_eq!(display(&[b'a', 0x00, b'\\', 0x7f], false), "a\\x00\\\\\\x7f");
+        assert_eq!(display(&[0x00, 0xab], true), "00ab");
+    }
+
+    #[test]
+    fn parses_escapes_and_hex() {
+        assert_eq!(parse("a\\x00b", false).unwrap(), vec![b'a', 0x00, b'b']);
+        assert_eq!(parse("\\xFF\\\\", false).unwrap(), vec![0xff, b'\\']);
+        assert_eq!(parse("00AbfF", true).unwrap(), vec![0x00, 0xab, 0xff]);
+        assert_eq!(parse_version(&Some("\\x01v".to_string()), false).unwrap(), vec![0x01, b'v']);
+        assert!(parse_version(&None, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(parse("a\\", false).is_err());
+        assert!(parse("\\n", false).is_err());
+        assert!(parse("\\x4", false).is_err());
+        assert!(parse("\\xzz", false).is_err());
+        assert!(parse("abc", true).is_err());
+        assert!(parse("zz", true).is_err());
+    }
+}
@@ -22,37 +22,64 @@
 
 #![crate_name = "kinetic_rust"]
 
-// Skip entire crate
-#![cfg(not(test))]
+// the code base keeps the idioms of the 2015 edition: try!, bare trait objects, ...
+#![allow(deprecated, bare_trait_objects, anonymous_parameters, ellipsis_inclusive_range_patterns)]
+#![allow(clippy::redundant_field_names, clippy::redundant_static_lifetimes, clippy::needless_lifetimes)]
+#![allow(clippy::question_mark, clippy::legacy_numeric_constants, clippy::needless_borrowed_reference, clippy::manual_range_contains)]
+#![allow(clippy::unnecessary_map_or, clippy::manual_is_multiple_of, clippy::manual_div_ceil, clippy::io_other_error)]
+
+// tests only reach the helpers they test
+#![cfg_attr(test, allow(dead_code))]
+// the main module holds the command dispatch, not an entry point
+#![allow(special_module_name)]
 
-#![feature(std_misc)]
-#![feature(convert)]
 
 extern crate libc;
-extern crate rustc_serialize;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+extern crate serde_json;
 extern crate docopt;
 extern crate kinetic;
+extern crate crypto;
+extern crate rand;
+extern crate toml;
 extern crate term;
 extern crate shell;
-#[macro_use] extern crate log;
-
-use std::env;
+extern crate rustyline;
+extern crate log;
 
 mod cli;
 mod main;
 mod help;
 mod info;
 mod write;
+mod integrity;
 mod bench;
 mod get_log;
+mod get;
+mod put;
+mod delete;
+mod ls;
+mod keys;
+mod output;
+mod inventory;
+mod config;
+mod discover;
+mod repl;
+mod export;
+mod import;
+mod unlock;
 
 #[cfg(not(test))]
 fn main() {
     let mut shell = ::shell::MultiShell::new_stdio(false);
-    let args: Vec<_> = env::args().collect();
+    let args: Vec<_> = ::std::env::args().collect();
     let r = main::main_with_args(args.as_ref(), &mut shell);
     match r {
         Ok(_) => (),
-        Err(e) => shell.error_full(&e, true).unwrap(),
+        Err(e) => {
+            ::cli::print_error(&mut shell, &e).unwrap();
+            ::std::process::exit(1);
+        },
     }
 }
@@ -0,0 +1,219 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{cmp, vec};
+use serde_json::Value;
+use kinetic::KineticResult;
+use kinetic::commands::GetKeyRange;
+use output::Format;
+
+
+#[derive(Deserialize, Debug)]
+pub struct LsArgs {
+    flag_verbose: bool,
+    flag_reverse: bool,
+    flag_limit: Option<usize>,
+    flag_hex: bool,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+    arg_start: Option<String>,
+    arg_end: Option<String>,
+}
+
+static USAGE: &'static str = "
+List the keys of a kinetic device
+
+Lists the keys from <start> to <end>, both included, or every key when missing.
+Keys are shown with \\\\ and \\xNN escapes, or in hex with --hex, and <start>
+and <end> are given the same way.
+
+Usage: kinetic-rust ls [options] (<target> | --targets LIST | --group GROUP) [<start>] [<end>]
+       kinetic-rust ls (-h | --help)
+
+Options:
+  -h, --help            Print this message
+  -r, --reverse         List from <end> to <start>
+  -l, --limit N         List at most N keys
+  -x, --hex             Show and read keys in hex
+  -t, --targets LIST    Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP     Every drive of GROUP in the inventory
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+/// Default for devices not advertising a `maxKeyRangeCount`
+static DEFAULT_PAGE_SIZE: usize = 200;
+
+/// Lists the keys of a range, a page of at most `maxKeyRangeCount` keys at a time
+///
+/// `on_key` gets the keys in order, up to `limit` of them.
+pub fn list<F>(c: &::kinetic::AsyncClient, start: vec::Vec<u8>, end: vec::Vec<u8>,
+               reverse: bool, limit: Option<usize>, mut on_key: F) -> KineticResult<usize>
+    where F: FnMut(&[u8]) -> KineticResult<()> {
+
+    let page = match c.get_limits().get_maxKeyRangeCount() as usize {
+        0 => DEFAULT_PAGE_SIZE,
+        n => n,
+    };
+
+    let (mut start, mut end) = (start, end);
+    let (mut start_inclusive, mut end_inclusive) = (true, true);
+    let mut listed = 0;
+
+    loop {
+        let want = limit.map_or(page, |l| cmp::min(page, l - listed));
+        if want == 0 { break; }
+
+        let r = try!(c.send(GetKeyRange { start: start.clone(),
+                                          end: end.clone(),
+                                          start_inclusive: start_inclusive,
+                                          end_inclusive: end_inclusive,
+                                          max_returned: want as i32,
+                                          reverse: reverse }));
+
+        for k in r.keys.iter() {
+            try!(on_key(k));
+        }
+        listed += r.keys.len();
+
+        if r.keys.len() < want { break; }
+
+        // next page starts right after the last key
+        let last = r.keys.into_iter().last().unwrap();
+        if reverse { end = last; end_inclusive = false; }
+        else { start = last; start_inclusive = false; }
+    }
+
+    Ok(listed)
+}
+
+/// Gets the largest key the device can hold, the end of a range over every key
+#[inline]
+pub fn last_key(c: &::kinetic::AsyncClient) -> vec::Vec<u8> {
+    ::kinetic::namespace::prefix_end(&[], c.get_limits().get_maxKeySize() as usize).0
+}
+
+/// Gets the last key of a range, `end` or the end of the device
+#[inline]
+fn range_end(c: &::kinetic::AsyncClient, end: &Option<vec::Vec<u8>>) -> vec::Vec<u8> {
+    match *end {
+        Some(ref end) => end.clone(),
+        None => last_key(c),
+    }
+}
+
+/// Gets the `<start>` and `<end>` of the command
+fn range(cmd: &LsArgs) -> KineticResult<(vec::Vec<u8>, Option<vec::Vec<u8>>)> {
+    let start = match cmd.arg_start {
+        Some(ref k) => try!(::keys::parse(k, cmd.flag_hex)),
+        None => vec![],
+    };
+    let end = match cmd.arg_end {
+        Some(ref k) => Some(try!(::keys::parse(k, cmd.flag_hex))),
+        None => None,
+    };
+    Ok((start, end))
+}
+
+fn execute(cmd: &LsArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-ls; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let (start, end) = try!(range(cmd));
+
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
+    let (hex, reverse, limit) = (cmd.flag_hex, cmd.flag_reverse, cmd.flag_limit);
+
+    if drives.len() > 1 {
+        let connection = globals.connection.clone();
+        let results = ::cli::fan_out(&drives, move |d| {
+            let c = try!(connection.connect(d));
+            let mut keys = vec::Vec::new();
+            try!(list(&c, start.clone(), range_end(&c, &end), reverse, limit, |k| {
+                keys.push(::keys::display(k, hex));
+                Ok(())
+            }));
+            Ok(keys)
+        });
+
+        let mut rows = vec::Vec::new();
+        for (d, r) in drives.iter().zip(results.iter()) {
+            match *r {
+                Ok(ref keys) => rows.extend(keys.iter().map(|k| vec![Value::from(d.label()), Value::from(k.clone()), Value::Null])),
+                Err(_) => rows.push(vec![Value::from(d.label()), Value::Null, ::cli::error_cell(r)]),
+            }
+        }
+        ::output::print_table(globals.format, &["Drive", "Key", "Error"], &rows);
+        return Ok(());
+    }
+
+    let c = try!(globals.connection.connect(&drives[0]));
+    print_keys(cmd, &c, start, &end, globals)
+}
+
+/// Runs the command on an open connection
+pub fn run(cmd: &LsArgs, c: &::kinetic::AsyncClient, _: &mut ::shell::MultiShell,
+           globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    let (start, end) = try!(range(cmd));
+    print_keys(cmd, c, start, &end, globals)
+}
+
+/// Lists the keys of a single drive
+fn print_keys(cmd: &LsArgs, c: &::kinetic::AsyncClient, start: vec::Vec<u8>, end: &Option<vec::Vec<u8>>,
+              globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    let end = range_end(c, end);
+    let (hex, reverse, limit) = (cmd.flag_hex, cmd.flag_reverse, cmd.flag_limit);
+
+    if globals.format == Format::Text {
+        try!(list(c, start, end, reverse, limit, |k| {
+            println!("{}", ::keys::display(k, hex));
+            Ok(())
+        }));
+    } else {
+        let mut keys = vec::Vec::new();
+        try!(list(c, start, end, reverse, limit, |k| {
+            keys.push(::keys::display(k, hex));
+            Ok(())
+        }));
+        ::output::print_list(globals.format, "key", &keys);
+    }
+
+    Ok(()) //return
+}
+
+impl ::cli::CliCommand for LsArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> LsArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<LsArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<LsArgs>) -> &'static str { USAGE }
+}
@@ -22,56 +22,94 @@
 
 use docopt::Docopt;
 use std::vec;
-use kinetic::KineticResult;
+use kinetic::{KineticResult, KineticError};
 //use std::ascii::OwnedAsciiExt;
-use cli::{CliDispatcher, CliCommand};
+use cli::{CliDispatcher, CliCommand, GlobalArgs};
+use output::Format;
 
 
-#[derive(RustcDecodable, Debug)]
+#[derive(Deserialize, Debug)]
 pub struct Args {
     arg_command: Option<Command>,
     arg_args: Vec<String>,
     flag_list: bool,
-    flag_help: bool,
-    flag_version: bool,
     flag_verbose: bool,
+    flag_format: Format,
+    flag_profile: Option<String>,
+    flag_identity: Option<i64>,
+    flag_key_file: Option<String>,
 }
 
-#[derive(RustcDecodable, Debug)]
+#[derive(Deserialize, Debug)]
 pub enum Command {
     Help,
     Write,
     Info,
     Bench,
     Log,
+    Get,
+    Put,
+    Delete,
+    Ls,
+    Discover,
+    Shell,
+    Export,
+    Import,
+    Unlock,
 }
 
 impl CliDispatcher for Command {
 
-    fn dispatch(&self, mut argv: vec::Vec<String>, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
+    fn dispatch(&self, mut argv: vec::Vec<String>, shell: &mut ::shell::MultiShell, globals: &GlobalArgs) -> KineticResult<()> {
         argv.insert(0, format!("{:?}", self).to_lowercase());
         argv.insert(0, "kinetic-rust".to_string());
 
-        let result =
-            match *self {
-                Command::Write => {
-                    let x: ::write::WriteArgs = CliCommand::from_argv(argv); try!(x.execute(shell))
-                },
-                Command::Info  => {
-                    let x: ::info::InfoArgs = CliCommand::from_argv(argv); try!(x.execute(shell))
-                },
-                Command::Bench  => {
-                    let x: ::bench::BenchArgs = CliCommand::from_argv(argv); try!(x.execute(shell))
-                },
-                Command::Log  => {
-                    let x: ::get_log::LogArgs = CliCommand::from_argv(argv); try!(x.execute(shell))
-                }
-                Command::Help => {
-                    let x: ::help::HelpArgs = CliCommand::from_argv(argv); try!(x.execute(shell))
-                }
-            };
-
-        Ok(result) // return
+        match *self {
+            Command::Write => {
+                let x: ::write::WriteArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Info  => {
+                let x: ::info::InfoArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Bench  => {
+                let x: ::bench::BenchArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Log  => {
+                let x: ::get_log::LogArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            }
+            Command::Get  => {
+                let x: ::get::GetArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Put  => {
+                let x: ::put::PutArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Delete  => {
+                let x: ::delete::DeleteArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Ls  => {
+                let x: ::ls::LsArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Discover  => {
+                let x: ::discover::DiscoverArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Shell  => {
+                let x: ::repl::ShellArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Export  => {
+                let x: ::export::ExportArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Import  => {
+                let x: ::import::ImportArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Unlock  => {
+                let x: ::unlock::UnlockArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            },
+            Command::Help => {
+                let x: ::help::HelpArgs = CliCommand::from_argv(argv); try!(x.execute(shell, globals))
+            }
+        };
+
+        Ok(()) // return
     }
 
 }
@@ -86,7 +124,7 @@ fn version() -> String {
 static USAGE: &'static str = "
 Kinetic from Rust!
 
-Usage: kinetic-rust <command> [<args>...]
+Usage: kinetic-rust [options] <command> [<args>...]
        kinetic-rust [options]
 
 Options:
@@ -94,10 +132,23 @@ Options:
   --version        Show the version of kinetic-rust.
   --list           List installed commands
   -v, --verbose    Use verbose output
+  --format FORMAT  Output format: text, json or csv [default: text]
+  --profile NAME   Connection profile from ~/.config/kinetic/config.toml
+  --identity ID    HMAC identity, over the profile
+  --key-file PATH  File holding the HMAC key, over the profile
 
 Some common kinetic-rust commands are:
     write          Write objects to a kinetic device
     info           Show information for a kinetic device
+    get            Get a value from a kinetic device
+    put            Put a value on a kinetic device
+    delete         Delete keys from a kinetic device
+    ls             List the keys of a kinetic device
+    discover       Discover kinetic devices on the network
+    shell          Interactive shell for a kinetic device
+    export         Export the keys of a kinetic device to an archive
+    import         Import an archive into a kinetic device
+    unlock         Unlock kinetic devices with the PIN of their profile
 
 See 'kinetic-rust help <command>' for more information on a specific command.
 ";
@@ -108,7 +159,7 @@ pub fn main_with_args(args : &[String], shell: &mut ::shell::MultiShell) -> Kine
                             .help(true)
                             .version(Some(version()));
 
-    let args: Args = docopt.decode().unwrap_or_else(|e| e.exit());
+    let args: Args = docopt.deserialize().unwrap_or_else(|e| e.exit());
     shell.set_verbose(args.flag_verbose);
 
     // FIXME: figure how to make generic...
@@ -118,15 +169,71 @@ pub fn main_with_args(args : &[String], shell: &mut ::shell::MultiShell) -> Kine
         println!("    info");
         println!("    bench");
         println!("    log");
+        println!("    get");
+        println!("    put");
+        println!("    delete");
+        println!("    ls");
+        println!("    discover");
+        println!("    shell");
+        println!("    export");
+        println!("    import");
+        println!("    unlock");
         println!("    help");
         return Ok(());
     }
 
-    match args.arg_command {
-        Some(cmd) => cmd.dispatch(args.arg_args, shell),
+    // a default key would silently go with an explicit identity
+    if args.flag_identity.is_some() && args.flag_key_file.is_none() {
+        return Err(KineticError::Usage("--identity needs --key-file".to_string()));
+    }
+
+    let connection = ::config::Connection::new(args.flag_profile, args.flag_identity, args.flag_key_file);
+    let globals = GlobalArgs { format: args.flag_format, connection: connection };
+
+    let r = match args.arg_command {
+        Some(cmd) => cmd.dispatch(args.arg_args, shell, &globals),
         None => {
             println!("{}", USAGE);
             Ok(())
         },
+    };
+
+    // structured errors go to stdout, along with the rest of the output
+    match r {
+        Err(ref e) if globals.format != Format::Text => {
+            ::output::print_error(globals.format, e);
+            ::std::process::exit(1);
+        },
+        r => r,
     } // return
 }
+
+#[cfg(test)]
+mod tests {
+    use docopt::Docopt;
+    use cli::CliCommand;
+
+    #[test]
+    fn usages_parse() {
+        let usages = [super::USAGE,
+                      CliCommand::usage(None::<::help::HelpArgs>),
+                      CliCommand::usage(None::<::write::WriteArgs>),
+                      CliCommand::usage(None::<::info::InfoArgs>),
+                      CliCommand::usage(None::<::bench::BenchArgs>),
+                      CliCommand::usage(None::<::get_log::LogArgs>),
+                      CliCommand::usage(None::<::get::GetArgs>),
+                      CliCommand::usage(None::<::put::PutArgs>),
+                      CliCommand::usage(None::<::delete::DeleteArgs>),
+                      CliCommand::usage(None::<::ls::LsArgs>),
+                      CliCommand::usage(None::<::discover::DiscoverArgs>),
+                      CliCommand::usage(None::<::repl::ShellArgs>),
+                      CliCommand::usage(None::<::export::ExportArgs>),
+                      CliCommand::usage(None::<::import::ImportArgs>),
+                      CliCommand::usage(None::<::unlock::UnlockArgs>)];
+        for usage in usages.iter() {
+            if let Err(e) = Docopt::new(*usage) {
+                panic!("{}\n{}", e, usage);
+            }
+        }
+    }
+}
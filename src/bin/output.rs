@@ -0,0 +1,219 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{cmp, vec};
+use serde_json::{self, Map, Value};
+use kinetic::{KineticResult, KineticError};
+
+
+/// Output formats, picked with the global `--format` option
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace("\"", "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn csv_line<S: AsRef<str>>(cells: &[S]) {
+    let s: vec::Vec<String> = cells.iter().map(|c| csv_field(c.as_ref())).collect();
+    println!("{}", s.join(","));
+}
+
+/// Gets cells as an object keyed by the headers
+pub fn json_object(headers: &[&str], cells: &[Value]) -> Value {
+    let mut o = Map::new();
+    for (h, c) in headers.iter().zip(cells.iter()) {
+        o.insert(h.to_string(), c.clone());
+    }
+    Value::Object(o)
+}
+
+/// Gets a cell as text, for the text and CSV formats
+///
+/// Arrays are joined with spaces, nested ones with `; `, and errors show their message.
+fn text(cell: &Value) -> String {
+    match *cell {
+        Value::Null => String::new(),
+        Value::String(ref s) => s.clone(),
+        Value::Array(ref items) => {
+            let nested = items.iter().any(|i| i.is_object() || i.is_array());
+            items.iter().map(text).collect::<vec::Vec<_>>().join(if nested { "; " } else { " " })
+        },
+        Value::Object(ref o) => o.get("message").map_or(String::new(), text),
+        ref v => v.to_string(),
+    }
+}
+
+/// Rounds a number to `decimals` places for display
+#[inline]
+pub fn round(x: f64, decimals: i32) -> Value {
+    let scale = 10f64.powi(decimals);
+    Value::from((x * scale).round() / scale)
+}
+
+/// Gets an error as a status code and message
+///
+/// Errors from the device carry their status code, usage errors are `USAGE_ERROR`
+/// and other errors are `CLIENT_ERROR`.
+pub fn error_json(err: &KineticError) -> Value {
+    let (code, message) = match *err {
+        KineticError::RemoteError(ref s) => (format!("{:?}", s.get_code()), s.get_statusMessage().to_string()),
+        KineticError::Usage(ref msg) => ("USAGE_ERROR".to_string(), msg.clone()),
+        ref e => ("CLIENT_ERROR".to_string(), format!("{}", e)),
+    };
+    json_object(&["code", "message"], &[Value::String(code), Value::String(message)])
+}
+
+#[inline]
+fn pretty(json: &Value) -> String {
+    serde_json::to_string_pretty(json).unwrap()
+}
+
+/// Prints rows as a table
+///
+/// As text the columns are aligned, the first one to the left and the rest to the right.
+/// As JSON it is an array of objects keyed by the headers, with the cells as they are.
+pub fn print_table(format: Format, headers: &[&str], rows: &[vec::Vec<Value>]) {
+    match format {
+        Format::Text => {
+            let rows: vec::Vec<vec::Vec<String>> = rows.iter().map(|r| r.iter().map(text).collect()).collect();
+            let mut widths: vec::Vec<usize> = headers.iter().map(|h| h.len()).collect();
+            for row in rows.iter() {
+                for (i, cell) in row.iter().enumerate() {
+                    widths[i] = cmp::max(widths[i], cell.len());
+                }
+            }
+
+            let line = |cells: &[String]| {
+                let s: vec::Vec<String> = cells.iter().enumerate().map(|(i, c)| {
+                    if i == 0 { format!("{:<1$}", c, widths[i]) } else { format!("{:>1$}", c, widths[i]) }
+                }).collect();
+                println!("{}", s.join("  "));
+            };
+
+            let headers: vec::Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+            line(&headers);
+            line(&widths.iter().map(|&w| (0..w).map(|_| '-').collect()).collect::<vec::Vec<String>>());
+            for row in rows.iter() {
+                line(row);
+            }
+        },
+        Format::Csv => {
+            csv_line(headers);
+            for row in rows.iter() { csv_line(&row.iter().map(text).collect::<vec::Vec<_>>()); }
+        },
+        Format::Json => {
+            let rows = rows.iter().map(|r| json_object(headers, r)).collect();
+            println!("{}", pretty(&Value::Array(rows)));
+        },
+    }
+}
+
+/// Prints named fields, as `shell` tags for text
+pub fn print_record(format: Format, shell: &mut ::shell::MultiShell, fields: &[(&str, Value)])
+        -> KineticResult<()> {
+    match format {
+        Format::Text => {
+            for &(name, ref value) in fields.iter() {
+                try!(shell.tag(name, text(value)));
+            }
+        },
+        Format::Csv => {
+            csv_line(&fields.iter().map(|&(n, _)| n).collect::<vec::Vec<_>>());
+            csv_line(&fields.iter().map(|&(_, ref v)| text(v)).collect::<vec::Vec<_>>());
+        },
+        Format::Json => {
+            let names: vec::Vec<&str> = fields.iter().map(|&(n, _)| n).collect();
+            let values: vec::Vec<Value> = fields.iter().map(|&(_, ref v)| v.clone()).collect();
+            println!("{}", pretty(&json_object(&names, &values)));
+        },
+    }
+    Ok(())
+}
+
+/// Prints a list of values, one per line for text
+pub fn print_list(format: Format, name: &str, items: &[String]) {
+    match format {
+        Format::Text => for i in items.iter() { println!("{}", i); },
+        Format::Csv => {
+            csv_line(&[name]);
+            for i in items.iter() { csv_line(&[i]); }
+        },
+        Format::Json => {
+            let items = items.iter().map(|i| Value::String(i.clone())).collect();
+            println!("{}", pretty(&Value::Array(items)));
+        },
+    }
+}
+
+/// Prints an error as a status code and message, for JSON and CSV
+///
+/// Text errors go through the shell instead.
+pub fn print_error(format: Format, err: &KineticError) {
+    let error = error_json(err);
+
+    match format {
+        Format::Text => unreachable!(),
+        Format::Csv => {
+            csv_line(&["code", "message"]);
+            csv_line(&[text(&error["code"]), text(&error["message"])]);
+        },
+        Format::Json => {
+            let mut o = Map::new();
+            o.insert("error".to_string(), error);
+            println!("{}", pretty(&Value::Object(o)));
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use kinetic::KineticError;
+    use super::{text, round, error_json};
+
+    #[test]
+    fn errors_have_a_code_and_message() {
+        let e = error_json(&KineticError::QuorumNotReached { required: 2, reached: 1 });
+        assert_eq!(e["code"], "CLIENT_ERROR");
+        assert_eq!(e["message"], "Only 1 of the 2 drives required acknowledged.");
+        assert_eq!(text(&e), "Only 1 of the 2 drives required acknowledged.");
+    }
+
+    #[test]
+    fn cells_as_text() {
+        assert_eq!(text(&Value::Null), "");
+        assert_eq!(text(&Value::from(8123)), "8123");
+        assert_eq!(text(&round(36.2999, 1)), "36.3");
+        assert_eq!(text(&Value::from(vec!["eth0", "eth1"])), "eth0 eth1");
+        let nested = Value::Array(vec![Value::from(vec!["eth0", "10.0.0.1"]), Value::from(vec!["eth1", "10.0.0.2"])]);
+        assert_eq!(text(&nested), "eth0 10.0.0.1; eth1 10.0.0.2");
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::io;
+use std::io::Read;
+use std::default::Default;
+use serde_json::Value;
+use kinetic::KineticResult;
+use kinetic::commands::Put;
+use kinetic::commands::common::Versioning;
+
+
+#[derive(Deserialize, Debug)]
+pub struct PutArgs {
+    flag_verbose: bool,
+    flag_version: Option<String>,
+    flag_new_version: Option<String>,
+    flag_force: bool,
+    flag_sync: ::write::SyncArg,
+    flag_hex: bool,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+    arg_key: String,
+    arg_value: Option<String>,
+}
+
+static USAGE: &'static str = "
+Put a value on a kinetic device
+
+Stores <value>, or stdin if missing, under <key>. The key must not exist
+yet, unless --force or --version is given. See 'write' for files and
+directories.
+
+Usage: kinetic-rust put [options] (<target> | --targets LIST | --group GROUP) <key> [<value>]
+       kinetic-rust put (-h | --help)
+
+Options:
+  -h, --help               Print this message
+  -f, --force              Overwrite regardless of the current version
+  --version VERSION        Only overwrite the entry at version VERSION
+  -n, --new-version VERSION  Version of the entry
+  --sync MODE              writethrough, writeback or flush [default: writeback]
+  -x, --hex                The key and versions are given in hex
+  -t, --targets LIST       Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP        Every drive of GROUP in the inventory
+  --inventory PATH         Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose            Use verbose output
+";
+
+impl PutArgs {
+
+    /// Whether the value is given, rather than read from stdin
+    #[inline]
+    pub fn has_value(&self) -> bool {
+        self.arg_value.is_some()
+    }
+
+}
+
+/// Gets the key and value of the command, the value is read from stdin when not given
+fn key_value(cmd: &PutArgs) -> KineticResult<(::std::vec::Vec<u8>, ::std::vec::Vec<u8>)> {
+    let key = try!(::keys::parse(&cmd.arg_key, cmd.flag_hex));
+    let value = match cmd.arg_value {
+        Some(ref v) => v.as_bytes().to_vec(),
+        None => {
+            let mut v = ::std::vec::Vec::new();
+            try!(io::stdin().read_to_end(&mut v));
+            v
+        },
+    };
+    Ok((key, value))
+}
+
+#[inline]
+fn versioning(force: bool, version: &[u8]) -> Versioning {
+    if force { Versioning::Force }
+    else { Versioning::Match(version.to_vec()) }
+}
+
+fn execute(cmd: &PutArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-put; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
+    if drives.len() == 1 {
+        let c = try!(globals.connection.connect(&drives[0]));
+        return run(cmd, &c, shell, globals);
+    }
+
+    let (key, value) = try!(key_value(cmd));
+    let force = cmd.flag_force;
+    let version = try!(::keys::parse_version(&cmd.flag_version, cmd.flag_hex));
+    let new_version = try!(::keys::parse_version(&cmd.flag_new_version, cmd.flag_hex));
+    let synchronization = cmd.flag_sync.to_proto();
+
+    let connection = globals.connection.clone();
+    let results = ::cli::fan_out(&drives, move |d| {
+        let c = try!(connection.connect(d));
+        c.send(Put { key: key.clone(),
+                     value: value.clone(),
+                     new_version: new_version.clone(),
+                     current_version: versioning(force, &version),
+                     synchronization: synchronization,
+                     ..Default::default() })
+    });
+
+    let rows: ::std::vec::Vec<_> = drives.iter().zip(results.iter())
+        .map(|(d, r)| vec![Value::from(d.label()), ::cli::error_cell(r)])
+        .collect();
+    ::output::print_table(globals.format, &["Drive", "Error"], &rows);
+
+    Ok(()) //return
+}
+
+/// Runs the command on an open connection
+pub fn run(cmd: &PutArgs, c: &::kinetic::AsyncClient, _: &mut ::shell::MultiShell,
+           _: &::cli::GlobalArgs) -> KineticResult<()> {
+    let (key, value) = try!(key_value(cmd));
+    let version = try!(::keys::parse_version(&cmd.flag_version, cmd.flag_hex));
+    try!(c.send(Put { key: key,
+                      value: value,
+                      new_version: try!(::keys::parse_version(&cmd.flag_new_version, cmd.flag_hex)),
+                      current_version: versioning(cmd.flag_force, &version),
+                      synchronization: cmd.flag_sync.to_proto(),
+                      ..Default::default() }));
+    Ok(()) //return
+}
+
+impl ::cli::CliCommand for PutArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> PutArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<PutArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<PutArgs>) -> &'static str { USAGE }
+}
@@ -0,0 +1,505 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Interactive shell over a single connection
+//!
+//! Commands that also exist as one-shot commands are parsed with the same usage,
+//! the target is filled in by the shell.
+
+use std::{env, io, vec};
+use std::io::Write;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::time::Instant;
+use serde_json::Value;
+use serde::de::DeserializeOwned;
+use rustyline::Editor;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use kinetic::{KineticResult, KineticError, AsyncClient};
+use kinetic::commands::{GetKeyRange, GetNext, GetPrevious, GetVersion, Noop, Flush};
+use kinetic::proto::command::Priority;
+use cli::{CliCommand, GlobalArgs};
+use output::Format;
+use term::color::BLACK;
+
+
+#[derive(Deserialize, Debug)]
+pub struct ShellArgs {
+    flag_verbose: bool,
+    flag_history: Option<String>,
+    flag_inventory: Option<String>,
+    arg_target: String,
+}
+
+static USAGE: &'static str = "
+Interactive shell for a kinetic device
+
+Keeps a connection to <target> open and reads commands from the terminal, with
+history and tab-completion of keys. Type 'help' once in for the commands.
+
+Usage: kinetic-rust shell [options] <target>
+       kinetic-rust shell (-h | --help)
+
+Options:
+  -h, --help            Print this message
+  --history FILE        History file (default: ~/.config/kinetic/history)
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+static HELP: &'static str = "
+Commands:
+    get [options] <key>            Get a value
+    put [options] <key> <value>    Put a value
+    del [options] <key>...         Delete keys
+    ls [options] [<start>] [<end>] List keys
+    next [options] <key>           Get the entry after a key
+    prev [options] <key>           Get the entry before a key
+    version [options] <key>        Get the version of a key
+    log [options] <type> [<name>]  Get a log
+    noop                           Ping the device
+    flush                          Flush the device write cache
+    set [<option> <value>]         Show or set the header options
+    help [<command>]               Show this message or the usage of a command
+    exit                           Leave the shell
+
+get, put, del, ls and log take the same options as their one-shot commands.
+";
+
+static COMMANDS: &'static [&'static str] = &["get", "put", "del", "ls", "next", "prev", "version",
+                                             "log", "noop", "flush", "set", "help", "exit"];
+
+static NEXT_USAGE: &'static str = "
+Get the entry after <key>
+
+Usage: next [options] <key>
+
+Options:
+  -m, --meta   Show the version and integrity tag instead of the value
+  -x, --hex    Show and read keys in hex
+";
+
+static PREV_USAGE: &'static str = "
+Get the entry before <key>
+
+Usage: prev [options] <key>
+
+Options:
+  -m, --meta   Show the version and integrity tag instead of the value
+  -x, --hex    Show and read keys in hex
+";
+
+static VERSION_USAGE: &'static str = "
+Get the version of <key>
+
+Usage: version [options] <key>
+
+Options:
+  -x, --hex    Show and read keys in hex
+";
+
+static NOOP_USAGE: &'static str = "
+Ping the device and show the round trip time
+
+Usage: noop
+";
+
+static FLUSH_USAGE: &'static str = "
+Flush the write cache of the device
+
+Usage: flush
+";
+
+static SET_USAGE: &'static str = "
+Show or set the header options sent on every request
+
+<option> is one of cluster-version, timeout or priority. The timeout is in
+milliseconds and the priority one of lowest, lower, normal, higher or highest,
+both go back to the device default with 'default'.
+
+Usage: set [<option> <value>]
+";
+
+#[derive(Deserialize, Debug)]
+struct EntryArgs {
+    flag_meta: bool,
+    flag_hex: bool,
+    arg_key: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionArgs {
+    flag_hex: bool,
+    arg_key: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SetArgs {
+    arg_option: Option<String>,
+    arg_value: Option<String>,
+}
+
+/// Splits a line in words, quotes group words and are left out
+///
+/// Backslashes are kept, they are key escapes.
+fn split(line: &str) -> vec::Vec<String> {
+    let mut words = vec::Vec::new();
+    let mut word = String::new();
+    let (mut in_word, mut quote) = (false, None);
+
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '"' || c == '\'' => { quote = Some(c); in_word = true; },
+            None if c.is_whitespace() => if in_word {
+                words.push(word.clone());
+                word.clear();
+                in_word = false;
+            },
+            None => { word.push(c); in_word = true; },
+        }
+    }
+    if in_word { words.push(word); }
+    words
+}
+
+/// Parses the words of a command with its usage
+fn parse<T: DeserializeOwned>(usage: &str, argv: vec::Vec<String>) -> Result<T, ::docopt::Error> {
+    ::docopt::Docopt::new(usage).and_then(|d| d.argv(argv).deserialize())
+}
+
+/// Parses the words of a one-shot command, targeting the device of the shell
+fn parse_command<T: CliCommand + DeserializeOwned>(name: &str, words: &[String], target: &str)
+        -> Result<T, ::docopt::Error> {
+    let mut argv = vec!["kinetic-rust".to_string(), name.to_string()];
+    argv.extend(words.iter().cloned());
+    argv.push("--targets".to_string());
+    argv.push(target.to_string());
+    parse(CliCommand::usage(None::<T>), argv)
+}
+
+/// Parses the words of a shell only command
+#[inline]
+fn parse_local<T: DeserializeOwned>(usage: &str, name: &str, words: &[String]) -> Result<T, ::docopt::Error> {
+    let mut argv = vec![name.to_string()];
+    argv.extend(words.iter().cloned());
+    parse(usage, argv)
+}
+
+/// Checks the words of a shell only command without arguments
+#[inline]
+fn check_local(usage: &str, name: &str, words: &[String]) -> Result<(), ::docopt::Error> {
+    let mut argv = vec![name.to_string()];
+    argv.extend(words.iter().cloned());
+    ::docopt::Docopt::new(usage).and_then(|d| d.argv(argv).parse()).map(|_| ())
+}
+
+/// Completes command names and keys, the keys are read with `GetKeyRange`
+struct KeyCompleter<'a> {
+    client: &'a RefCell<AsyncClient>,
+}
+
+/// Most keys offered on a completion
+static MAX_COMPLETIONS: i32 = 100;
+
+impl<'a> Completer for KeyCompleter<'a> {
+    fn complete(&self, line: &str, pos: usize) -> ::rustyline::Result<(usize, vec::Vec<String>)> {
+        let start = line[..pos].rfind(|c: char| c.is_whitespace()).map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+
+        if start == 0 {
+            let names = COMMANDS.iter().filter(|c| c.starts_with(word)).map(|c| c.to_string()).collect();
+            return Ok((start, names));
+        }
+
+        let hex = split(&line[..start]).iter().any(|w| w == "-x" || w == "--hex");
+        let prefix = match ::keys::parse(word, hex) {
+            Ok(prefix) => prefix,
+            Err(_) => return Ok((start, vec![])),
+        };
+
+        let c = self.client.borrow();
+        let (end, end_inclusive) = ::kinetic::namespace::prefix_end(&prefix, c.get_limits().get_maxKeySize() as usize);
+        let keys = match c.send(GetKeyRange { start: prefix, end: end,
+                                              start_inclusive: true, end_inclusive: end_inclusive,
+                                              max_returned: MAX_COMPLETIONS, reverse: false }) {
+            Ok(r) => r.keys,
+            Err(_) => vec![],
+        };
+
+        let candidates = keys.iter().map(|k| {
+            let k = ::keys::display(k, hex);
+            if k.contains(' ') { format!("\"{}\"", k) } else { k }
+        }).collect();
+        Ok((start, candidates))
+    }
+}
+
+#[inline]
+fn priority(value: &str) -> KineticResult<Option<Priority>> {
+    match value {
+        "lowest" => Ok(Some(Priority::LOWEST)),
+        "lower" => Ok(Some(Priority::LOWER)),
+        "normal" => Ok(Some(Priority::NORMAL)),
+        "higher" => Ok(Some(Priority::HIGHER)),
+        "highest" => Ok(Some(Priority::HIGHEST)),
+        "default" => Ok(None),
+        _ => Err(KineticError::Usage("unknown priority".to_string())),
+    }
+}
+
+fn set(cmd: &SetArgs, c: &mut AsyncClient, shell: &mut ::shell::MultiShell, globals: &GlobalArgs) -> KineticResult<()> {
+    if let (&Some(ref option), &Some(ref value)) = (&cmd.arg_option, &cmd.arg_value) {
+        match &option[..] {
+            "cluster-version" => {
+                let v = try!(value.parse().map_err(|_| KineticError::Usage("invalid cluster version".to_string())));
+                c.set_cluster_version(v);
+            },
+            "timeout" if value == "default" => c.set_timeout(None),
+            "timeout" => {
+                let v = try!(value.parse().map_err(|_| KineticError::Usage("invalid timeout".to_string())));
+                c.set_timeout(Some(v));
+            },
+            "priority" => c.set_priority(try!(priority(value))),
+            _ => return Err(KineticError::Usage("unknown header option".to_string())),
+        }
+    }
+
+    ::output::print_record(globals.format, shell, &[
+        ("cluster-version", Value::from(c.get_cluster_version())),
+        ("timeout", c.get_timeout().map_or(Value::from("default"), Value::from)),
+        ("priority", Value::from(c.get_priority().map_or("default".to_string(), |p| format!("{:?}", p).to_lowercase())))])
+}
+
+/// Prints an entry found by `next` or `prev`
+fn print_entry(cmd: &EntryArgs, key: &[u8], value: &[u8], version: &[u8], integrity: &::kinetic::commands::common::Integrity,
+               shell: &mut ::shell::MultiShell, globals: &GlobalArgs) -> KineticResult<()> {
+    if cmd.flag_meta {
+        return ::output::print_record(globals.format, shell, &[
+            ("Key", Value::from(::keys::display(key, cmd.flag_hex))),
+            ("Size", Value::from(value.len())),
+            ("Version", Value::from(::keys::display(version, cmd.flag_hex))),
+            ("Algorithm", Value::from(format!("{:?}", integrity.algorithm))),
+            ("Tag", Value::from(::keys::display(&integrity.tag, true)))]);
+    }
+
+    if globals.format == Format::Text {
+        try!(shell.status("Key", ::keys::display(key, cmd.flag_hex)));
+        try!(io::stdout().write_all(value));
+        println!();
+        Ok(())
+    } else {
+        ::output::print_record(globals.format, shell, &[
+            ("Key", Value::from(::keys::display(key, cmd.flag_hex))),
+            ("Value", Value::from(String::from_utf8_lossy(value).into_owned()))])
+    }
+}
+
+/// Prints a usage, on stderr when the output is structured
+fn print_usage(usage: &str, shell: &mut ::shell::MultiShell, globals: &GlobalArgs) -> io::Result<()> {
+    if globals.format == Format::Text { shell.say(usage, BLACK) }
+    else { shell.err().say(usage, BLACK) }
+}
+
+/// Runs a line of the shell, `Ok(false)` when leaving
+fn run_line(words: &[String], client: &RefCell<AsyncClient>, target: &str,
+            shell: &mut ::shell::MultiShell, globals: &GlobalArgs) -> KineticResult<bool> {
+    let (name, args) = match words.split_first() {
+        Some((name, args)) => (&name[..], args),
+        None => return Ok(true),
+    };
+
+    macro_rules! parse_or_usage {
+        ($e:expr) => (match $e {
+            Ok(x) => x,
+            Err(ref e) if !e.fatal() => { try!(print_usage(&e.to_string(), shell, globals)); return Ok(true); },
+            Err(e) => { try!(shell.error(e)); return Ok(true); },
+        })
+    }
+
+    match name {
+        "get" => {
+            let x: ::get::GetArgs = parse_or_usage!(parse_command("get", args, target));
+            try!(::get::run(&x, &client.borrow(), shell, globals));
+            // the value is written as is
+            if globals.format == Format::Text && !args.iter().any(|a| a == "-o" || a.starts_with("--output")) {
+                println!();
+            }
+        },
+        "put" => {
+            let x: ::put::PutArgs = parse_or_usage!(parse_command("put", args, target));
+            // reading stdin would take over the terminal, and its end would leave the shell
+            if !x.has_value() {
+                try!(shell.error("put needs a <value> in the shell"));
+                return Ok(true);
+            }
+            try!(::put::run(&x, &client.borrow(), shell, globals));
+        },
+        "del" | "delete" => {
+            let x: ::delete::DeleteArgs = parse_or_usage!(parse_command("delete", args, target));
+            try!(::delete::run(&x, &client.borrow(), shell, globals));
+        },
+        "ls" => {
+            let x: ::ls::LsArgs = parse_or_usage!(parse_command("ls", args, target));
+            try!(::ls::run(&x, &client.borrow(), shell, globals));
+        },
+        "log" => {
+            let x: ::get_log::LogArgs = parse_or_usage!(parse_command("log", args, target));
+            // ^C only drops the line, so nothing would stop the polling
+            if x.watches() {
+                try!(shell.error("--watch is not available in the shell"));
+                return Ok(true);
+            }
+            try!(::get_log::run(&x, &client.borrow(), shell, globals));
+        },
+        "next" => {
+            let x: EntryArgs = parse_or_usage!(parse_local(NEXT_USAGE, name, args));
+            let r = try!(client.borrow().send(GetNext { key: try!(::keys::parse(&x.arg_key, x.flag_hex)) }));
+            try!(print_entry(&x, &r.key, &r.value, &r.version, &r.integrity, shell, globals));
+        },
+        "prev" => {
+            let x: EntryArgs = parse_or_usage!(parse_local(PREV_USAGE, name, args));
+            let r = try!(client.borrow().send(GetPrevious { key: try!(::keys::parse(&x.arg_key, x.flag_hex)) }));
+            try!(print_entry(&x, &r.key, &r.value, &r.version, &r.integrity, shell, globals));
+        },
+        "version" => {
+            let x: VersionArgs = parse_or_usage!(parse_local(VERSION_USAGE, name, args));
+            let r = try!(client.borrow().send(GetVersion { key: try!(::keys::parse(&x.arg_key, x.flag_hex)) }));
+            try!(::output::print_record(globals.format, shell, &[("Version", Value::from(::keys::display(&r.version, x.flag_hex)))]));
+        },
+        "noop" => {
+            parse_or_usage!(check_local(NOOP_USAGE, name, args));
+            let started = Instant::now();
+            try!(client.borrow().send(Noop));
+            let elapsed = started.elapsed();
+            let us = elapsed.as_secs() * 1_000_000 + elapsed.subsec_nanos() as u64 / 1_000;
+            try!(::output::print_record(globals.format, shell, &[("Latency (us)", Value::from(us))]));
+        },
+        "flush" => {
+            parse_or_usage!(check_local(FLUSH_USAGE, name, args));
+            try!(client.borrow().send(Flush));
+        },
+        "set" => {
+            let x: SetArgs = parse_or_usage!(parse_local(SET_USAGE, name, args));
+            try!(set(&x, &mut client.borrow_mut(), shell, globals));
+        },
+        "help" => {
+            let usage = match args.first().map(|a| &a[..]) {
+                Some("get") => CliCommand::usage(None::<::get::GetArgs>),
+                Some("put") => CliCommand::usage(None::<::put::PutArgs>),
+                Some("del") | Some("delete") => CliCommand::usage(None::<::delete::DeleteArgs>),
+                Some("ls") => CliCommand::usage(None::<::ls::LsArgs>),
+                Some("log") => CliCommand::usage(None::<::get_log::LogArgs>),
+                Some("next") => NEXT_USAGE,
+                Some("prev") => PREV_USAGE,
+                Some("version") => VERSION_USAGE,
+                Some("noop") => NOOP_USAGE,
+                Some("flush") => FLUSH_USAGE,
+                Some("set") => SET_USAGE,
+                _ => HELP,
+            };
+            try!(print_usage(usage, shell, globals));
+        },
+        "exit" | "quit" => return Ok(false),
+        _ => try!(shell.error(format!("Unknown command '{}', type 'help' for the commands", name))),
+    }
+
+    Ok(true) //return
+}
+
+/// Gets the history path, `~/.config/kinetic/history`
+fn history_path() -> PathBuf {
+    env::home_dir().unwrap_or(PathBuf::from(".")).join(".config").join("kinetic").join("history")
+}
+
+fn execute(cmd: &ShellArgs, shell: &mut ::shell::MultiShell, globals: &GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-shell; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let drives = try!(::inventory::select(&Some(cmd.arg_target.clone()), &None, &None, &cmd.flag_inventory));
+    let drive = &drives[0];
+    let target = try!(globals.connection.address(drive));
+
+    if globals.format == Format::Text {
+        try!(shell.status("Connecting", format!("device at {}", target)));
+    }
+    let client = RefCell::new(try!(globals.connection.connect(drive)));
+
+    let completer = KeyCompleter { client: &client };
+    let mut editor = Editor::new();
+    editor.set_completer(Some(&completer));
+
+    let history = cmd.flag_history.as_ref().map_or(history_path(), PathBuf::from);
+    let _ = editor.load_history(&history);
+
+    let prompt = format!("{}> ", drive.label());
+    // the history is saved however the loop ends
+    let r = loop {
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            // ^C drops the line, ^D leaves
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break Ok(()),
+            Err(ReadlineError::Io(e)) => break Err(KineticError::IoError(e)),
+            Err(_) => break Err(KineticError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                                      "failed to read from the terminal"))),
+        };
+
+        let words = split(&line);
+        if words.is_empty() { continue; }
+        editor.add_history_entry(&line);
+
+        match run_line(&words, &client, &target, shell, globals) {
+            Ok(true) => (),
+            Ok(false) => break Ok(()),
+            Err(ref e) if globals.format == Format::Text => {
+                if let Err(e) = ::cli::print_error(shell, e) { break Err(KineticError::IoError(e)); }
+            },
+            Err(ref e) => ::output::print_error(globals.format, e),
+        }
+    };
+
+    if let Some(dir) = history.parent() {
+        let _ = ::std::fs::create_dir_all(dir);
+    }
+    let _ = editor.save_history(&history);
+
+    r //return
+}
+
+impl ::cli::CliCommand for ShellArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> ShellArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<ShellArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<ShellArgs>) -> &'static str { USAGE }
+}
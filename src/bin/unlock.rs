@@ -0,0 +1,100 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+// author: Ignacio Corderi
+
+use kinetic::{KineticResult, KineticError};
+use kinetic::commands::pin::Unlock;
+use serde_json::Value;
+
+
+#[derive(Deserialize, Debug)]
+pub struct UnlockArgs {
+    flag_verbose: bool,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+}
+
+static USAGE: &'static str = "
+Unlock kinetic devices with the PIN of their profile
+
+Usage: kinetic-rust unlock [options] (<target> | --targets LIST | --group GROUP)
+       kinetic-rust unlock (-h | --help)
+
+The PIN comes from the pin_env, pin_file or pin_prompt setting of the profile.
+
+Options:
+  -h, --help            Print this message
+  -t, --targets LIST    Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP     Every drive of GROUP in the inventory
+  --inventory PATH      Inventory file (default: ~/.config/kinetic/inventory.toml)
+  -v, --verbose         Use verbose output
+";
+
+/// Unlocks a drive, with the PIN of its profile
+fn unlock(d: &::inventory::Drive, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    let pin = match try!(globals.connection.pin(d)) {
+        Some(pin) => pin,
+        None => return Err(KineticError::Usage("the profile has no PIN source".to_string())),
+    };
+    let c = try!(globals.connection.connect(d));
+    c.send_with_pin(Unlock, pin) // return
+}
+
+fn execute(cmd: &UnlockArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-unlock; args={}", ::std::env::args());
+    shell.set_verbose(cmd.flag_verbose);
+
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
+
+    if drives.len() == 1 {
+        try!(unlock(&drives[0], globals));
+        try!(shell.status("Unlocked", drives[0].label()));
+        return Ok(());
+    }
+
+    // one drive at a time, so PIN prompts do not interleave
+    let rows: ::std::vec::Vec<_> = drives.iter()
+        .map(|d| {
+            let r = unlock(d, globals);
+            vec![Value::from(d.label()), ::cli::error_cell(&r)]
+        })
+        .collect();
+    ::output::print_table(globals.format, &["Drive", "Error"], &rows);
+
+    Ok(()) //return
+}
+
+impl ::cli::CliCommand for UnlockArgs {
+    fn from_argv(argv: ::std::vec::Vec<String>) -> UnlockArgs {
+        ::docopt::Docopt::new(::cli::CliCommand::usage(None::<UnlockArgs>))
+            .and_then(|d| d.argv(argv).deserialize() )
+            .unwrap_or_else(|e| e.exit())
+    }
+
+    #[inline]
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
+    }
+
+    #[inline]
+    fn usage(_: Option<UnlockArgs>) -> &'static str { USAGE }
+}
@@ -20,88 +20,247 @@
 
 // author: Ignacio Corderi
 
-use std::time::Duration;
-use std::vec;
+use std::{io, fs, vec};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::time::Instant;
 use std::default::Default;
-use kinetic::commands::{Put, Get};
-use kinetic::KineticResult;
+use serde_json::Value;
+use kinetic::commands::Put;
+use kinetic::commands::common::Versioning;
+use kinetic::proto::command::Synchronization;
+use kinetic::{KineticResult, KineticError};
+use integrity::IntegrityArg;
 
 
-#[derive(RustcDecodable, Debug)]
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum SyncArg {
+    Writethrough,
+    Writeback,
+    Flush,
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct WriteArgs {
     flag_verbose: bool,
-    flag_count: Option<usize>,
-    flag_size: Option<usize>,
-    arg_target: String,
+    flag_version: Option<String>,
+    flag_new_version: Option<String>,
+    flag_force: bool,
+    flag_sync: SyncArg,
+    flag_integrity: Option<IntegrityArg>,
+    flag_recursive: bool,
+    flag_hex: bool,
+    arg_target: Option<String>,
+    flag_targets: Option<String>,
+    flag_group: Option<String>,
+    flag_inventory: Option<String>,
+    arg_key: String,
+    arg_path: Option<String>,
 }
 
 static USAGE: &'static str = "
-Write an object to a kinetic device
+Write objects to a kinetic device
+
+Writes <path> (or stdin) under <key>. With --recursive, writes every file
+under the directory <path>, using <key> as a prefix for their relative paths.
 
-Usage: kinetic-rust write [options] <target>
+Without --force or --version the keys must not exist yet.
+
+Usage: kinetic-rust write [options] (<target> | --targets LIST | --group GROUP) <key> [<path>]
        kinetic-rust write (-h | --help)
 
 Options:
   -h, --help               Print this message
-  -s, --size BYTES         Size of the value in bytes (default: 1 MB)
-  -c, --count COUNT        Number of key/value pairs to send (default: 10)
+  -r, --recursive          Write a whole directory
+  -f, --force              Overwrite regardless of the current version
+  --version VERSION        Only overwrite entries at version VERSION
+  -n, --new-version VERSION  Version of the written entries
+  --sync MODE              writethrough, writeback or flush [default: writeback]
+  -i, --integrity ALG      Send an integrity tag: sha1, sha2, crc32 or crc32c
+  -x, --hex                The versions are given in hex
+  -t, --targets LIST       Comma separated drives, by inventory name or host[:port]
+  -g, --group GROUP        Every drive of GROUP in the inventory
+  --inventory PATH         Inventory file (default: ~/.config/kinetic/inventory.toml)
   -v, --verbose            Use verbose output
 ";
 
-fn execute(cmd: &WriteArgs, shell: &mut ::shell::MultiShell) -> KineticResult<()> {
-    //debugı!("executing; cmd=kinetic-rust-write; args={}", ::std::env::args());
+impl SyncArg {
+    pub fn to_proto(self) -> Synchronization {
+        match self {
+            SyncArg::Writethrough => Synchronization::WRITETHROUGH,
+            SyncArg::Writeback => Synchronization::WRITEBACK,
+            SyncArg::Flush => Synchronization::FLUSH,
+        }
+    }
+}
+
+/// Lists the files under `dir` with their keys, `prefix` followed by the relative path
+///
+/// Symbolic links are followed, a directory reached twice is only listed once.
+fn walk(dir: &Path, prefix: &str, visited: &mut HashSet<PathBuf>,
+        files: &mut vec::Vec<(String, Option<PathBuf>)>) -> io::Result<()> {
+    if !visited.insert(try!(fs::canonicalize(dir))) { return Ok(()); }
+
+    for entry in try!(fs::read_dir(dir)) {
+        let entry = try!(entry);
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        // unlike `entry.file_type()` this follows symbolic links
+        if try!(fs::metadata(&path)).is_dir() {
+            try!(walk(&path, &format!("{}{}/", prefix, name), visited, files));
+        } else {
+            files.push((format!("{}{}", prefix, name), Some(path)));
+        }
+    }
+    Ok(())
+}
+
+/// Gets the size of every item, items without a path hold the `stdin` value
+fn sizes(items: &[(String, Option<PathBuf>)], stdin: &[u8]) -> io::Result<vec::Vec<u64>> {
+    items.iter().map(|&(_, ref path)| match *path {
+        Some(ref path) => fs::metadata(path).map(|m| m.len()),
+        None => Ok(stdin.len() as u64),
+    }).collect()
+}
+
+/// Gets the keys of the items larger than the device `maxValueSize`
+fn oversized<'a>(c: &::kinetic::AsyncClient, items: &'a [(String, Option<PathBuf>)], sizes: &[u64])
+        -> vec::Vec<&'a str> {
+    let max = c.get_limits().get_maxValueSize() as u64;
+    items.iter().zip(sizes.iter())
+        .filter(|&(_, &len)| max > 0 && len > max)
+        .map(|(&(ref key, _), _)| &key[..])
+        .collect()
+}
+
+fn put(cmd: &WriteArgs, c: &::kinetic::AsyncClient, key: &str, path: &Option<PathBuf>, stdin: &[u8], len: u64)
+        -> KineticResult<()> {
+    let versioning = if cmd.flag_force { Versioning::Force }
+                     else { Versioning::Match(try!(::keys::parse_version(&cmd.flag_version, cmd.flag_hex))) };
+
+    let integrity = match (cmd.flag_integrity, path) {
+        (None, _) => None,
+        (Some(alg), &Some(ref path)) => Some(try!(alg.integrity_of(&mut try!(fs::File::open(path))))),
+        (Some(alg), &None) => Some(alg.integrity(stdin)),
+    };
+
+    let put = Put { key: key.as_bytes().to_vec(),
+                    integrity: integrity,
+                    new_version: try!(::keys::parse_version(&cmd.flag_new_version, cmd.flag_hex)),
+                    current_version: versioning,
+                    synchronization: cmd.flag_sync.to_proto(),
+                    ..Default::default() };
+
+    // files are streamed, never held in memory whole
+    let r = match *path {
+        Some(ref path) => c.put_from_reader(put, &mut try!(fs::File::open(path)), len),
+        None => c.put_from_reader(put, &mut &stdin[..], len),
+    };
+    r.map(|_| ())
+}
+
+/// Writes every item to a drive, `progress` is told about each one before it is written
+///
+/// Nothing is written if any item is larger than the device `maxValueSize`.
+/// Items without a path hold the `stdin` value.
+fn upload<F>(cmd: &WriteArgs, c: &::kinetic::AsyncClient, items: &[(String, Option<PathBuf>)], sizes: &[u64],
+             stdin: &[u8], mut progress: F) -> KineticResult<(usize, u64)>
+    where F: FnMut(&str, u64) -> KineticResult<()> {
+
+    if !oversized(c, items, sizes).is_empty() {
+        return Err(KineticError::Usage("value exceeds the device maxValueSize, nothing was written".to_string()));
+    }
+
+    let mut bytes = 0;
+    for (&(ref key, ref path), &len) in items.iter().zip(sizes.iter()) {
+        try!(progress(key, len));
+        try!(put(cmd, c, key, path, stdin, len));
+        bytes += len;
+    }
+
+    Ok((items.len(), bytes))
+}
+
+fn execute(cmd: &WriteArgs, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> KineticResult<()> {
+    //debug!("executing; cmd=kinetic-rust-write; args={}", ::std::env::args());
     shell.set_verbose(cmd.flag_verbose);
-    unimplemented!();
 
-    // try!(shell.status("Connecting", format!("device at {}:8123", cmd.arg_target)));
+    let mut items = vec::Vec::new();
+    let mut stdin = vec::Vec::new();
+    if cmd.flag_recursive {
+        let dir = match cmd.arg_path {
+            Some(ref dir) => dir,
+            None => return Err(KineticError::Usage("--recursive needs a directory".to_string())),
+        };
+
+        try!(walk(Path::new(dir), &cmd.arg_key, &mut HashSet::new(), &mut items));
+    } else {
+        match cmd.arg_path {
+            Some(ref path) if path != "-" => items.push((cmd.arg_key.clone(), Some(PathBuf::from(path)))),
+            _ => {
+                try!(io::stdin().read_to_end(&mut stdin));
+                items.push((cmd.arg_key.clone(), None));
+            },
+        }
+    }
+
+    let sizes = try!(sizes(&items, &stdin));
 
-    // let c = try!(::kinetic::Client::new(format!("{}:8123", cmd.arg_target).as_str()));
+    let drives = try!(::inventory::select(&cmd.arg_target, &cmd.flag_targets, &cmd.flag_group, &cmd.flag_inventory));
 
-    // c.send(Put { key: "rust".as_bytes().to_vec(),
-    //              value: format!("Hello from {}!", ::kinetic::version()).as_bytes().to_vec(),
-    //              ..Default::default() }).unwrap();
-    // let v = try!(c.send(Get { key: "rust".as_bytes().to_vec() }));
+    if drives.len() > 1 {
+        let args = cmd.clone();
+        let connection = globals.connection.clone();
+        let results = ::cli::fan_out(&drives, move |d| {
+            let c = try!(connection.connect(d));
+            upload(&args, &c, &items, &sizes, &stdin, |_, _| Ok(()))
+        });
 
-    // try!(shell.status("Response", format!("{}", String::from_utf8(v.value).unwrap())));
+        let rows: vec::Vec<_> = drives.iter().zip(results.iter()).map(|(d, r)| {
+            let (count, bytes) = match *r {
+                Ok((count, bytes)) => (Value::from(count), Value::from(bytes)),
+                Err(_) => (Value::Null, Value::Null),
+            };
+            vec![Value::from(d.label()), count, bytes, ::cli::error_cell(r)]
+        }).collect();
 
-    // let items = cmd.flag_count.unwrap_or(10);
-    //     // benchmark
-    // let size = cmd.flag_size.unwrap_or(1024*1024);
-    // let d = Duration::span(|| {
-    //     let mut responses = vec::Vec::with_capacity(items);
+        ::output::print_table(globals.format, &["Drive", "Keys", "Bytes", "Error"], &rows);
+        return Ok(());
+    }
+
+    let target = try!(globals.connection.address(&drives[0]));
+    try!(shell.status("Connecting", format!("device at {}", target)));
 
-    //     for i in (0.. items) {
-    //         let data = vec![0u8; size];
-    //         let r = c.send_future(Put { key: format!("opt-bench.{}", i).as_bytes().to_vec(),
-    //                                     value: data,
-    //                                     ..Default::default()});
-    //         responses.push(r);
-    //     }
+    let c = try!(globals.connection.connect(&drives[0]));
+
+    for key in oversized(&c, &items, &sizes).iter() {
+        try!(shell.error(format!("{} exceeds the device maxValueSize", key)));
+    }
 
-    //     // wait on all
-    //     for r in responses.into_iter() {
-    //         r.into_inner().unwrap();
-    //     }
-    // });
-    // let ops = items as f64  / (d.num_milliseconds() as f64 / 1000.0);
-    // let transfered = (items as f64 * size as f64) / (1024.0 * 1024.0);
-    // let bw = transfered / (d.num_milliseconds() as f64 / 1000.0);
+    let start = Instant::now();
+    let (count, bytes) = try!(upload(cmd, &c, &items, &sizes, &stdin, |key, len| {
+        shell.status("Writing", format!("{} ({} bytes)", key, len)).map_err(From::from)
+    }));
 
-    // try!(shell.status("Done", format!("benchmark took {}ms ({:.2} MB/s, {:.2} op/s)", d.num_milliseconds(), bw, ops)));
+    let elapsed = start.elapsed();
+    let ms = elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64;
+    try!(shell.status("Done", format!("wrote {} keys, {} bytes in {}ms", count, bytes, ms)));
 
-    // Ok(()) //return
+    Ok(()) //return
 }
 
 impl ::cli::CliCommand for WriteArgs {
     fn from_argv(argv: ::std::vec::Vec<String>) -> WriteArgs {
         ::docopt::Docopt::new(::cli::CliCommand::usage(None::<WriteArgs>))
-            .and_then(|d| d.argv(argv.clone().into_iter()).decode() )
+            .and_then(|d| d.argv(argv).deserialize() )
             .unwrap_or_else(|e| e.exit())
     }
 
     #[inline]
-    fn execute(&self, shell: &mut ::shell::MultiShell) -> ::kinetic::KineticResult<()> {
-        execute(self, shell)
+    fn execute(&self, shell: &mut ::shell::MultiShell, globals: &::cli::GlobalArgs) -> ::kinetic::KineticResult<()> {
+        execute(self, shell, globals)
     }
 
     #[inline]
@@ -0,0 +1,485 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Portable archives of a device keyspace
+//!
+//! An archive holds entries, each a key with its value, version and integrity tag,
+//! so a keyspace can be backed up, or moved to another device, without P2P.
+//!
+//! # Format
+//!
+//! All integers are big endian.
+//!
+//! ```text
+//! archive := header record* end
+//! header  := "KNTCARCH" version:u32          the format version is 1
+//! record  := length:u32 kind:u8 payload crc:u32
+//! ```
+//!
+//! `length` counts the `kind` and the `payload`, `crc` is the CRC-32 (IEEE) of both.
+//! There are two kinds of records:
+//!
+//! * `1`, an entry: the key, version, integrity tag and value, each as a `u32` length
+//!   followed by the bytes, then the integrity algorithm as a `u32` holding its protocol value.
+//! * `2`, the end: the number of entries in the archive as a `u64`. An archive
+//!   without it is truncated.
+
+use std::{vec, io};
+use std::io::{Read, Write};
+use byteorder::{ByteOrder, BigEndian};
+use protobuf::ProtobufEnum;
+use client::Client;
+use channel::KineticChannel;
+use commands::{Get, GetKeyRange, Put};
+use commands::common::{Integrity, Versioning};
+use proto::command::Algorithm;
+use result::KineticResult;
+use error::KineticError;
+use digest::crc32;
+
+static MAGIC: &'static [u8] = b"KNTCARCH";
+static FORMAT_VERSION: u32 = 1;
+
+static ENTRY: u8 = 1;
+static END: u8 = 2;
+
+/// Largest record accepted, guards against allocating for a corrupt length
+static MAX_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default for devices not advertising a `maxKeyRangeCount`
+static DEFAULT_PAGE_SIZE: usize = 200;
+
+#[inline]
+fn push_u32(buf: &mut vec::Vec<u8>, v: u32) {
+    let mut b = [0u8; 4];
+    <BigEndian as ByteOrder>::write_u32(&mut b, v);
+    buf.extend(b.iter().cloned());
+}
+
+#[inline]
+fn push_bytes(buf: &mut vec::Vec<u8>, v: &[u8]) {
+    push_u32(buf, v.len() as u32);
+    buf.extend(v.iter().cloned());
+}
+
+#[inline]
+fn read_exact<R: Read>(r: &mut R, buf: &mut [u8]) -> KineticResult<()> {
+    r.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => KineticError::InvalidData("truncated archive"),
+        _ => KineticError::IoError(e),
+    })
+}
+
+/// Reads the fields of a record payload in order
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn u32(&mut self) -> KineticResult<u32> {
+        if self.data.len() < 4 { return Err(KineticError::InvalidData("malformed archive record")); }
+        let v = <BigEndian as ByteOrder>::read_u32(&self.data[..4]);
+        self.data = &self.data[4..];
+        Ok(v)
+    }
+
+    fn bytes(&mut self) -> KineticResult<vec::Vec<u8>> {
+        let len = try!(self.u32()) as usize;
+        if self.data.len() < len { return Err(KineticError::InvalidData("malformed archive record")); }
+        let v = self.data[..len].to_vec();
+        self.data = &self.data[len..];
+        Ok(v)
+    }
+}
+
+/// An archived entry
+#[derive(Debug)]
+pub struct Entry {
+    pub key: vec::Vec<u8>,
+    pub value: vec::Vec<u8>,
+    pub version: vec::Vec<u8>,
+    pub integrity: Integrity,
+}
+
+/// Writes an archive
+///
+/// The archive is only complete after `finish`.
+pub struct ArchiveWriter<W: Write> {
+    sink: W,
+    count: u64,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+
+    /// Starts an archive, writing its header
+    pub fn new(mut sink: W) -> KineticResult<ArchiveWriter<W>> {
+        let mut header = MAGIC.to_vec();
+        push_u32(&mut header, FORMAT_VERSION);
+        try!(sink.write_all(&header));
+        Ok(ArchiveWriter { sink: sink, count: 0 })
+    }
+
+    fn write_record(&mut self, kind: u8, payload: &[u8]) -> KineticResult<()> {
+        let mut record = vec::Vec::with_capacity(payload.len() + 9);
+        push_u32(&mut record, payload.len() as u32 + 1);
+        record.push(kind);
+        record.extend(payload.iter().cloned());
+        let crc = crc32(&record[4..]);
+        push_u32(&mut record, crc);
+        try!(self.sink.write_all(&record));
+        Ok(())
+    }
+
+    /// Appends an entry
+    pub fn write_entry(&mut self, entry: &Entry) -> KineticResult<()> {
+        let mut payload = vec::Vec::with_capacity(entry.key.len() + entry.value.len() + 64);
+        push_bytes(&mut payload, &entry.key);
+        push_bytes(&mut payload, &entry.version);
+        push_bytes(&mut payload, &entry.integrity.tag);
+        push_bytes(&mut payload, &entry.value);
+        push_u32(&mut payload, entry.integrity.algorithm.value() as u32);
+
+        try!(self.write_record(ENTRY, &payload));
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Gets the number of entries written so far
+    #[inline]
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    /// Ends the archive and gives back the sink
+    pub fn finish(mut self) -> KineticResult<W> {
+        let mut payload = [0u8; 8];
+        <BigEndian as ByteOrder>::write_u64(&mut payload, self.count);
+        try!(self.write_record(END, &payload));
+        try!(self.sink.flush());
+        Ok(self.sink)
+    }
+
+}
+
+/// Reads the entries of an archive
+///
+/// Every record is checked against its checksum, and the archive against its end record.
+pub struct ArchiveReader<R: Read> {
+    source: R,
+    count: u64,
+    done: bool,
+}
+
+impl<R: Read> ArchiveReader<R> {
+
+    /// Opens an archive, checking its header
+    pub fn new(mut source: R) -> KineticResult<ArchiveReader<R>> {
+        let mut header = [0u8; 12];
+        try!(read_exact(&mut source, &mut header));
+        if &header[..8] != MAGIC {
+            return Err(KineticError::InvalidData("not a kinetic archive"));
+        }
+        if <BigEndian as ByteOrder>::read_u32(&header[8..]) != FORMAT_VERSION {
+            return Err(KineticError::InvalidData("unsupported archive format version"));
+        }
+        Ok(ArchiveReader { source: source, count: 0, done: false })
+    }
+
+    fn read_entry(&mut self) -> KineticResult<Option<Entry>> {
+        let mut len = [0u8; 4];
+        try!(read_exact(&mut self.source, &mut len));
+        let len = <BigEndian as ByteOrder>::read_u32(&len) as usize;
+        if len == 0 || len > MAX_RECORD_SIZE {
+            return Err(KineticError::InvalidData("malformed archive record"));
+        }
+
+        let mut record = vec![0u8; len + 4];
+        try!(read_exact(&mut self.source, &mut record));
+        if crc32(&record[..len]) != <BigEndian as ByteOrder>::read_u32(&record[len..]) {
+            return Err(KineticError::InvalidData("archive record checksum mismatch"));
+        }
+
+        let (kind, payload) = (record[0], &record[1..len]);
+        if kind == END {
+            if payload.len() != 8 || <BigEndian as ByteOrder>::read_u64(payload) != self.count {
+                return Err(KineticError::InvalidData("archive entry count mismatch"));
+            }
+            return Ok(None);
+        }
+        if kind != ENTRY {
+            return Err(KineticError::InvalidData("unknown archive record"));
+        }
+
+        let mut fields = Fields { data: payload };
+        let key = try!(fields.bytes());
+        let version = try!(fields.bytes());
+        let tag = try!(fields.bytes());
+        let value = try!(fields.bytes());
+        let algorithm = try!(Algorithm::from_i32(try!(fields.u32()) as i32)
+                                 .ok_or(KineticError::InvalidData("unknown integrity algorithm")));
+
+        self.count += 1;
+        Ok(Some(Entry { key: key, value: value, version: version,
+                        integrity: Integrity { tag: tag, algorithm: algorithm } }))
+    }
+
+}
+
+impl<R: Read> Iterator for ArchiveReader<R> {
+    type Item = KineticResult<Entry>;
+
+    fn next(&mut self) -> Option<KineticResult<Entry>> {
+        if self.done { return None; }
+
+        match self.read_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => { self.done = true; None },
+            Err(e) => { self.done = true; Some(Err(e)) },
+        }
+    }
+}
+
+/// How an import treats the versions of the entries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Keep the archived versions, a key already on the device is an error
+    Preserve,
+    /// Keep the archived versions, overwriting keys already on the device
+    Force,
+    /// Keep the archived versions, leaving keys already on the device alone
+    SkipExisting,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportReport {
+    /// Entries written to the device
+    pub imported: u64,
+    /// Entries left alone because the key was already on the device
+    pub skipped: u64,
+}
+
+/// Exports the entries from `start` to `end`, both included, into an archive
+///
+/// Keys are listed a page of at most `maxKeyRangeCount` at a time, and their entries
+/// written as they are read. Keys deleted while exporting are left out.
+///
+/// # Returns
+/// Returns the number of entries exported.
+///
+/// # Example
+/// ```no_run
+/// use std::fs::File;
+/// use kinetic::Client;
+///
+/// let c = Client::new("127.0.0.1:8123").unwrap();
+/// let f = File::create("backup.kar").unwrap();
+/// kinetic::archive::export(&c, vec![], vec![0xff; 4096], f).unwrap();
+/// ```
+pub fn export<Ch, T, W>(c: &Client<Ch, T>, start: vec::Vec<u8>, end: vec::Vec<u8>, sink: W) -> KineticResult<u64>
+    where Ch: KineticChannel<T>, W: Write {
+
+    let page = match c.get_limits().get_maxKeyRangeCount() as usize {
+        0 => DEFAULT_PAGE_SIZE,
+        n => n,
+    };
+
+    let mut archive = try!(ArchiveWriter::new(sink));
+    let (mut start, mut start_inclusive) = (start, true);
+
+    loop {
+        let r = try!(c.send(GetKeyRange { start: start.clone(),
+                                          end: end.clone(),
+                                          start_inclusive: start_inclusive,
+                                          end_inclusive: true,
+                                          max_returned: page as i32,
+                                          reverse: false }));
+        let listed = r.keys.len();
+
+        for key in r.keys.iter() {
+            match c.send(Get { key: key.clone() }) {
+                Ok(x) => try!(archive.write_entry(&Entry { key: key.clone(),
+                                                           value: x.value,
+                                                           version: x.version,
+                                                           integrity: x.integrity })),
+                Err(KineticError::RemoteError(ref s))
+                    if s.get_code() == ::proto::StatusCode::NOT_FOUND => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if listed < page { break; }
+
+        // next page starts right after the last key
+        start = r.keys.into_iter().last().unwrap();
+        start_inclusive = false;
+    }
+
+    let count = archive.get_count();
+    try!(archive.finish());
+    Ok(count)
+}
+
+/// Imports the entries of an archive
+///
+/// The archive is checked as it is read, entries before a corrupt record are imported.
+///
+/// # Example
+/// ```no_run
+/// use std::fs::File;
+/// use kinetic::Client;
+/// use kinetic::archive::ImportMode;
+///
+/// let c = Client::new("127.0.0.1:8123").unwrap();
+/// let f = File::open("backup.kar").unwrap();
+/// let report = kinetic::archive::import(&c, f, ImportMode::SkipExisting).unwrap();
+/// println!("{} imported, {} skipped", report.imported, report.skipped);
+/// ```
+pub fn import<Ch, T, R>(c: &Client<Ch, T>, source: R, mode: ImportMode) -> KineticResult<ImportReport>
+    where Ch: KineticChannel<T>, R: Read {
+
+    let mut report: ImportReport = Default::default();
+
+    for entry in try!(ArchiveReader::new(source)) {
+        let entry = try!(entry);
+        let versioning = match mode {
+            ImportMode::Force => Versioning::Force,
+            ImportMode::Preserve | ImportMode::SkipExisting => Versioning::Match(vec![]),
+        };
+        // an empty tag means the entry had none
+        let integrity = if entry.integrity.tag.is_empty() { None } else { Some(entry.integrity) };
+
+        let r = c.send(Put { key: entry.key,
+                             value: entry.value,
+                             new_version: entry.version,
+                             current_version: versioning,
+                             integrity: integrity,
+                             ..Default::default() });
+
+        match r {
+            Ok(_) => report.imported += 1,
+            Err(KineticError::RemoteError(ref s))
+                if mode == ImportMode::SkipExisting
+                   && s.get_code() == ::proto::StatusCode::VERSION_MISMATCH => report.skipped += 1,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchiveWriter, ArchiveReader, Entry};
+    use commands::common::Integrity;
+    use proto::command::Algorithm;
+    use error::KineticError;
+    use std::vec;
+
+    fn entry(key: &[u8], value: &[u8], version: &[u8], tag: &[u8], algorithm: Algorithm) -> Entry {
+        Entry { key: key.to_vec(), value: value.to_vec(), version: version.to_vec(),
+                integrity: Integrity { tag: tag.to_vec(), algorithm: algorithm } }
+    }
+
+    fn archive(entries: &[Entry]) -> vec::Vec<u8> {
+        let mut w = ArchiveWriter::new(vec![]).unwrap();
+        for e in entries.iter() {
+            w.write_entry(e).unwrap();
+        }
+        assert_eq!(w.get_count(), entries.len() as u64);
+        w.finish().unwrap()
+    }
+
+    fn read(data: &[u8]) -> Result<vec::Vec<Entry>, KineticError> {
+        try!(ArchiveReader::new(data)).collect()
+    }
+
+    fn invalid(r: Result<vec::Vec<Entry>, KineticError>) -> &'static str {
+        match r {
+            Err(KineticError::InvalidData(msg)) => msg,
+            r => panic!("expected invalid data, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let entries = vec![entry(b"", b"", b"", b"", Algorithm::SHA1),
+                           entry(b"k1", b"hello", b"v1", b"\x01\x02\x03\x04", Algorithm::CRC32),
+                           entry(&[0xff; 300], &vec![7u8; 100 * 1024], b"\x00", b"tag", Algorithm::SHA3)];
+        let read = read(&archive(&entries)).unwrap();
+
+        assert_eq!(read.len(), entries.len());
+        for (a, b) in read.iter().zip(entries.iter()) {
+            assert_eq!(a.key, b.key);
+            assert_eq!(a.value, b.value);
+            assert_eq!(a.version, b.version);
+            assert_eq!(a.integrity.tag, b.integrity.tag);
+            assert_eq!(a.integrity.algorithm, b.integrity.algorithm);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        assert!(read(&archive(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn corrupt() {
+        let data = archive(&[entry(b"k1", b"hello", b"v1", b"", Algorithm::SHA1)]);
+
+        // every byte of the entry record is covered by its length or checksum
+        for i in 12..data.len() - 17 {
+            let mut bad = data.clone();
+            bad[i] ^= 0x40;
+            assert!(read(&bad).is_err(), "flipped byte {} went unnoticed", i);
+        }
+
+        let mut bad = data.clone();
+        bad[20] ^= 0x40;
+        assert_eq!(invalid(read(&bad)), "archive record checksum mismatch");
+
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert_eq!(invalid(read(&bad)), "not a kinetic archive");
+    }
+
+    #[test]
+    fn truncated() {
+        let data = archive(&[entry(b"k1", b"hello", b"v1", b"", Algorithm::SHA1)]);
+
+        // without its end record
+        assert_eq!(invalid(read(&data[..data.len() - 17])), "truncated archive");
+        assert_eq!(invalid(read(&data[..data.len() - 1])), "truncated archive");
+        assert_eq!(invalid(read(&data[..6])), "truncated archive");
+    }
+
+    #[test]
+    fn count_mismatch() {
+        let mut data = archive(&[entry(b"k1", b"hello", b"v1", b"", Algorithm::SHA1)]);
+        let end = data.len() - 17;
+        let mut w = ArchiveWriter { sink: vec![], count: 0 };
+        w.write_record(super::END, &[0, 0, 0, 0, 0, 0, 0, 2]).unwrap();
+        data.truncate(end);
+        data.extend(w.sink.iter().cloned());
+        assert_eq!(invalid(read(&data)), "archive entry count mismatch");
+    }
+}
@@ -25,7 +25,6 @@
 use std::vec;
 use proto::message::AuthType;
 use crypto::{hmac, sha1};
-use crypto::digest::Digest;
 use crypto::mac::Mac;
 
 /// Kinetic authentication credentials
@@ -45,7 +44,7 @@ impl Credentials {
     fn calculate_hmac(key: &vec::Vec<u8>, data: &[u8]) -> vec::Vec<u8> {
         let mut hmac = hmac::Hmac::new(sha1::Sha1::new(), key.as_ref());
 
-        let buffer: [u8;4] = unsafe { ::std::mem::transmute((data.len() as u32).to_be()) };
+        let buffer: [u8;4] = (data.len() as u32).to_be_bytes();
 
         hmac.input(&buffer);
         hmac.input(data);
@@ -88,7 +87,7 @@ impl Credentials {
                 let received_hmac = msg.get_hmacAuth().get_hmac();
                 let calculated_hmac_vec = Credentials::calculate_hmac(key, msg.get_commandBytes());
                 let calculated_hmac: &[u8] = calculated_hmac_vec.as_ref();
-                (received_hmac == calculated_hmac)
+                received_hmac == calculated_hmac
             },
             Credentials::Pin { .. } => msg.get_authType() == AuthType::PINAUTH
         }
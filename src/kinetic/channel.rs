@@ -23,16 +23,64 @@
 //! Module representing raw communication channels with a kinetic device
 
 use protobuf::Message;
-use std::{vec, collections, net};
+use std::{vec, collections, net, io, thread};
+use std::io::Read;
 use std::net::ToSocketAddrs;
-use std::sync::{Mutex, Arc};
+use std::sync::{Mutex, Condvar, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
 use result::KineticResult;
-use std::sync::mpsc::{Sender, Receiver, SyncSender, channel, sync_channel};
+use error::KineticError;
+use proto::command::MessageType;
+use events::DeviceEvent;
+use std::sync::mpsc::{Sender, Receiver, channel};
 
-pub type Operation= (::authentication::Credentials, ::proto::Command, Option<::std::vec::Vec<u8>>);
+/// A request ready to be sent, the value is shared so retrying an operation doesn't copy it
+pub type Operation= (::authentication::Credentials, ::proto::Command, Option<Arc<::std::vec::Vec<u8>>>);
 
 pub type Result = (::proto::Message, ::proto::Command, ::std::vec::Vec<u8>);
 
+/// Invoked exactly once with the outcome of a request sent with `AsyncChannel::send_with`
+pub type Completion = Box<FnOnce(KineticResult<Result>) + Send>;
+
+/// A response whose value, `usize` bytes long, is still on the socket
+type Frame = (::proto::Message, ::proto::Command, usize);
+
+/// Hands the socket back and forth between a caller streaming a value and the channel threads
+struct StreamHandoff {
+    // writer -> caller: the message is on the wire, write the value now
+    value_ready: Sender<()>,
+    // caller -> writer: whether the whole value was written
+    value_written: Receiver<bool>,
+    // reader -> caller: the response, read its value now
+    response: Sender<KineticResult<Frame>>,
+    // caller -> reader: the response value was read
+    response_read: Receiver<()>,
+}
+
+/// A request queued for the writer thread
+enum Request {
+    Buffered(Operation, Completion),
+    /// The caller streams the value, of the given length, see `AsyncChannel::send_streaming`
+    Streamed(Operation, u64, StreamHandoff),
+}
+
+/// What the reader thread does with a response
+enum Handler {
+    Buffered(Completion),
+    Streamed(Sender<KineticResult<Frame>>, Receiver<()>),
+}
+
+impl Handler {
+
+    fn fail(self, err: KineticError) {
+        match self {
+            Handler::Buffered(callback) => callback(Err(err)),
+            Handler::Streamed(response, _) => { let _ = response.send(Err(err)); },
+        }
+    }
+
+}
+
 pub trait KineticChannel<T> {
 
     fn is_closed(&self) -> bool;
@@ -41,28 +89,202 @@ pub trait KineticChannel<T> {
 
     fn get_limits<'r>(&'r self) -> &'r ::proto::command::log::Limits;
 
-    fn send(&self, op: Operation) -> T;
+    fn send(&self, op: Operation) -> KineticResult<T>;
+
+    fn receive(T) -> KineticResult<Result>;
+
+    fn get_event_receiver<'r>(&'r self) -> &'r Receiver<DeviceEvent>;
+
+    /// Queues an event detected by the client on the event stream
+    fn notify(&self, event: DeviceEvent);
+
+}
+
+/// A channel that can hand the outcome of a request to a `Completion`
+pub trait CompletionChannel {
+
+    /// Sends an operation, `completion` is invoked exactly once with the outcome
+    /// unless an `Err(...)` is returned
+    fn send_with<F>(&self, op: Operation, completion: F) -> KineticResult<()>
+        where F: FnOnce(KineticResult<Result>) + Send + 'static;
+
+}
+
+/// The kind of request, as far as the device in-flight limits are concerned
+///
+/// The device advertises separate limits for outstanding reads and writes
+/// (`maxOutstandingReadRequests` and `maxOutstandingWriteRequests`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestKind {
+    Read,
+    Write,
+}
+
+impl RequestKind {
+
+    /// Classifies a request by its `MessageType`
+    pub fn of(message_type: MessageType) -> RequestKind {
+        match message_type {
+            MessageType::GET | MessageType::GETNEXT | MessageType::GETPREVIOUS |
+            MessageType::GETKEYRANGE | MessageType::GETVERSION | MessageType::GETLOG |
+            MessageType::NOOP => RequestKind::Read,
+            _ => RequestKind::Write,
+        }
+    }
+
+}
+
+/// What to do when sending a request while its in-flight window is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowPolicy {
+    /// Block the caller until a response frees a slot
+    Block,
+    /// Return `KineticError::WindowFull` right away
+    FailFast,
+}
+
+/// Bounds the number of requests of one `RequestKind` in flight
+struct Window {
+    size: usize,
+    available: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl Window {
+
+    fn new(size: usize) -> Window {
+        Window { size: size, available: Mutex::new(size), freed: Condvar::new() }
+    }
+
+    fn acquire(&self, kind: RequestKind, policy: WindowPolicy) -> KineticResult<()> {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            if policy == WindowPolicy::FailFast {
+                return Err(KineticError::WindowFull(kind));
+            }
+            available = self.freed.wait(available).unwrap();
+        }
+        *available -= 1;
+        Ok(())
+    }
+
+    fn release(&self) {
+        let mut available = self.available.lock().unwrap();
+        *available += 1;
+        self.freed.notify_one();
+    }
+
+}
+
+/// The read and write in-flight windows of a connection
+struct Windows {
+    read: Window,
+    write: Window,
+}
 
-    fn receive(T) -> Result;
+impl Windows {
 
-    fn get_unsolicited_receiver<'r>(&'r self) -> &'r Receiver<Result>;
+    #[inline]
+    fn get(&self, kind: RequestKind) -> &Window {
+        match kind {
+            RequestKind::Read => &self.read,
+            RequestKind::Write => &self.write,
+        }
+    }
 
 }
 
+/// Picks the window size for a device advertised limit, `fallback` is used
+/// when the device does not advertise one.
+#[inline]
+fn window_size(advertised: u32, fallback: usize) -> usize {
+    if advertised > 0 { advertised as usize } else { fallback }
+}
+
+/// Requests sent and waiting for a response, by sequence number
+type Pending = Mutex<collections::HashMap<i64, (Handler, RequestKind)>>;
+
+/// Marks the connection closed and fails every request still waiting for a response
+///
+/// The flag is set under the lock so the writer can't sneak in a new request.
+/// Both channel threads call it, whichever notices the connection is gone first
+/// fails the requests.
+fn close(pending: &Pending, windows: &Windows, closed: &AtomicBool) {
+    let drained: vec::Vec<(i64, (Handler, RequestKind))> = {
+        let mut pending = pending.lock().unwrap();
+        closed.store(true, Ordering::SeqCst);
+        pending.drain().collect()
+    };
+    for (_, (handler, kind)) in drained.into_iter() {
+        windows.get(kind).release();
+        handler.fail(KineticError::ConnectionClosed);
+    }
+}
+
 pub struct AsyncChannel {
     stream: net::TcpStream,
-    writer_tx: SyncSender<(Operation,Sender<Result>)>,
-    unsol_rx: Receiver<Result>,
-    closed: bool,
+    sender: ChannelSender,
+    unsol_tx: Sender<DeviceEvent>,
+    unsol_rx: Receiver<DeviceEvent>,
     configuration: ::proto::command::log::Configuration,
     limits: ::proto::command::log::Limits,
 }
 
+/// The sending side of an `AsyncChannel`
+///
+/// Unlike the channel it can be cloned and shared between threads, so a sender blocked on
+/// a full window doesn't need to hold on to the channel. Once the channel is dropped every
+/// send fails with `KineticError::ConnectionClosed`.
+#[derive(Clone)]
+pub struct ChannelSender {
+    writer_tx: Sender<Request>,
+    windows: Arc<Windows>,
+    policy: WindowPolicy,
+    reader: thread::ThreadId,
+    closed: Arc<AtomicBool>,
+}
+
+impl ChannelSender {
+
+    /// Gets the `WindowPolicy` for a send from the calling thread
+    ///
+    /// The reader thread is the only one freeing slots, a completion sending
+    /// more requests would wait for itself.
+    #[inline]
+    fn policy(&self) -> WindowPolicy {
+        if thread::current().id() == self.reader { WindowPolicy::FailFast } else { self.policy }
+    }
+
+    #[inline]
+    pub fn is_closed(&self) -> bool { self.closed.load(Ordering::SeqCst) }
+
+    /// Sends an operation, see `AsyncChannel::send_with`
+    pub fn send_with<F>(&self, op: Operation, completion: F) -> KineticResult<()>
+        where F: FnOnce(KineticResult<Result>) + Send + 'static {
+
+        if self.is_closed() { return Err(KineticError::ConnectionClosed); }
+
+        let kind = RequestKind::of(op.1.get_header().get_messageType());
+        try!(self.windows.get(kind).acquire(kind, self.policy()));
+
+        match self.writer_tx.send(Request::Buffered(op, Box::new(completion))) {
+            Ok(()) => Ok(()),
+            Err(_) => {
+                self.windows.get(kind).release();
+                Err(KineticError::ConnectionClosed)
+            }
+        }
+    }
+
+}
+
 impl Drop for AsyncChannel {
 
     #[inline]
     fn drop(&mut self) {
-        self.closed = true;
+        self.sender.closed.store(true, Ordering::SeqCst);
+        // wakes up the reader, which fails anything still in flight
+        let _ = self.stream.shutdown(net::Shutdown::Both);
     }
 
 }
@@ -70,12 +292,23 @@ impl Drop for AsyncChannel {
 
 impl AsyncChannel {
 
+    /// Connects to the device, blocking senders when an in-flight window is full
+    ///
+    /// The read and write windows are sized from the `Limits` advertised by the device
+    /// during the _handshake_, `max_pending` is used for any limit it does not advertise.
+    #[inline]
     pub fn new<A: ToSocketAddrs>(addr: A, max_pending: usize) -> KineticResult<AsyncChannel> {
+        AsyncChannel::new_with_policy(addr, max_pending, WindowPolicy::Block)
+    }
+
+    /// Connects to the device using the given `WindowPolicy`
+    pub fn new_with_policy<A: ToSocketAddrs>(addr: A, max_pending: usize, policy: WindowPolicy)
+            -> KineticResult<AsyncChannel> {
         let mut s = try!(net::TcpStream::connect(addr));
         try!(s.set_nodelay(true));
 
-        // Handshake
-        let (_, mut cmd, _) = ::network::recv(&mut s).expect("boom 1");
+        // Handshake, a device dropping the connection here is an error like any other
+        let (_, mut cmd, _) = try!(::network::recv(&mut s));
         if cmd.get_status().get_code() != ::proto::StatusCode::SUCCESS {
             return Err(::error::KineticError::RemoteError(cmd.take_status()));
         }
@@ -85,28 +318,49 @@ impl AsyncChannel {
         let configuration = the_log.take_configuration();
         let limits = the_log.take_limits();
 
+        // In-flight windows
+        let windows = Arc::new(Windows {
+            read: Window::new(window_size(limits.get_maxOutstandingReadRequests(), max_pending)),
+            write: Window::new(window_size(limits.get_maxOutstandingWriteRequests(), max_pending)),
+        });
+
         // Other state like pending requests...
-        let pending_mutex = Arc::new(Mutex::new(collections::HashMap::with_capacity(max_pending)));
+        let pending_mutex: Arc<Pending> = Arc::new(Mutex::new(collections::HashMap::with_capacity(max_pending)));
+
+        let closed = Arc::new(AtomicBool::new(false));
 
         // reader
         let mut reader = try!(s.try_clone());
         let pending_mutex_reader = pending_mutex.clone();
+        let windows_reader = windows.clone();
+        let closed_reader = closed.clone();
         // for unsolicited status
         let (unsol_tx, unsol_rx) = channel();
-        ::std::thread::spawn(move|| {
+        let unsol_tx_reader = unsol_tx.clone();
+        let reader_thread = thread::spawn(move|| {
             let pending_mutex = pending_mutex_reader;
+            let windows = windows_reader;
+            let unsol_tx = unsol_tx_reader;
             loop {
-                let r = ::network::recv(&mut reader);
+                // the value is left on the socket, it may be streamed by the caller
+                let r = ::network::recv_frame(&mut reader);
                 if r.is_err() { break; } // FIXME: this is only ok if *we* closed it
 
-                let (msg, cmd, value) = r.expect("boom2");
+                let (msg, cmd, value_len) = r.expect("boom2");
 
                 match  msg.get_authType() {
-                    ::proto::message::AuthType::UNSOLICITEDSTATUS => unsol_tx.send((msg, cmd, value)).expect("boom3"), //TODO: try!(...)
-                    ::proto::message::AuthType::HMACAUTH => {
+                    ::proto::message::AuthType::UNSOLICITEDSTATUS => {
+                        let value = match ::network::recv_value(&mut reader, value_len) {
+                            Ok(value) => value,
+                            Err(_) => break,
+                        };
+                        // nobody may be listening for events, that's fine
+                        let _ = unsol_tx.send(DeviceEvent::from_proto(msg, cmd, value));
+                    },
+                    ::proto::message::AuthType::HMACAUTH | ::proto::message::AuthType::PINAUTH => {
                         // FIXME: verify HMAC integrity
                         let ack = cmd.get_header().get_ackSequence();
-                        let req: Option<Sender<Result>>;
+                        let req: Option<(Handler, RequestKind)>;
                         // lock the pendings and grab the request that matches the ACK
                         {
                             let mut pending = pending_mutex.lock().expect("boom4"); // TODO: try!(...)
@@ -115,48 +369,67 @@ impl AsyncChannel {
                         }
 
                         match req {
-                            // FIXME: What should we do if we get a result for an operation
-                            //        we did not send?
-                            None => println!("No match for ack: {} found.", ack),
-                            Some(callback) => callback.send((msg, cmd, value)).expect("boom5") // TODO: try!(...)
+                            // a response to nothing we sent, report it and skip its value
+                            None => {
+                                let _ = unsol_tx.send(DeviceEvent::UnexpectedResponse { ack: ack });
+                                if ::network::recv_value(&mut reader, value_len).is_err() { break; }
+                            },
+                            Some((Handler::Buffered(callback), kind)) => {
+                                windows.get(kind).release();
+                                match ::network::recv_value(&mut reader, value_len) {
+                                    Ok(value) => callback(Ok((msg, cmd, value))),
+                                    Err(_) => {
+                                        callback(Err(KineticError::ConnectionClosed));
+                                        break;
+                                    },
+                                }
+                            },
+                            Some((Handler::Streamed(response, response_read), kind)) => {
+                                windows.get(kind).release();
+                                if response.send(Ok((msg, cmd, value_len))).is_ok() {
+                                    // the caller reads the value straight from the socket, wait for it.
+                                    // If it gives up half way there is no telling where the next message starts.
+                                    if response_read.recv().is_err() { break; }
+                                } else if ::network::recv_value(&mut reader, value_len).is_err() {
+                                    break;
+                                }
+                            },
                         }
                     },
-                    ::proto::message::AuthType::PINAUTH => {
-                        // FIXME: duplicate code with HMACAUTH except no need to veriy HMAC, refactor.
-                        let ack = cmd.get_header().get_ackSequence();
-                        let req: Option<Sender<Result>>;
-                        // lock the pendings and grab the request that matches the ACK
-                        {
-                            let mut pending = pending_mutex.lock().unwrap(); // TODO: try!(...);
-                            // *remove* returns the value if it was there
-                            req = pending.remove(&ack);
-                        }
-
-                        match req {
-                            // FIXME: What shjould we do if we get a result for an operation
-                            //        we did not send?
-                            None => println!("No match for ack: {} found.", ack),
-                            Some(callback) => callback.send((msg, cmd, value)).expect("boom6") // TODO: try!(...)
-                        }
+                    ::proto::message::AuthType::INVALID_AUTH_TYPE => {
+                        let _ = unsol_tx.send(DeviceEvent::InvalidMessage);
+                        if ::network::recv_value(&mut reader, value_len).is_err() { break; }
                     },
-                    ::proto::message::AuthType::INVALID_AUTH_TYPE =>
-                        // FIXME: where do we send errors?
-                        println!("Invalid authentication type."),
                 }
             }
+
+            // The connection is gone, fail everything still in flight
+            close(&pending_mutex, &windows, &closed_reader);
+            let _ = unsol_tx.send(DeviceEvent::Disconnected);
         });
 
-        // writer
-        let (w_tx, w_rx): (_, Receiver<(Operation,Sender<Result>)>) = sync_channel(max_pending);
+        // writer, the in-flight windows bound how much can be queued here
+        let (w_tx, w_rx): (_, Receiver<Request>) = channel();
         let mut writer = try!(s.try_clone());
         let pending_mutex_writer = pending_mutex.clone();
+        let windows_writer = windows.clone();
+        let closed_writer = closed.clone();
         ::std::thread::spawn(move|| {
             let pending_mutex = pending_mutex_writer;
+            let windows = windows_writer;
             let mut seq = 0;
 
-            for ((auth, mut cmd, value), callback) in w_rx.iter(){
+            for request in w_rx.iter() {
+                let ((auth, mut cmd, value), handler, streamed) = match request {
+                    Request::Buffered(op, callback) => (op, Handler::Buffered(callback), None),
+                    Request::Streamed(op, len, handoff) =>
+                        (op, Handler::Streamed(handoff.response, handoff.response_read),
+                         Some((len, handoff.value_ready, handoff.value_written))),
+                };
+
                 cmd.mut_header().set_sequence(seq);
                 cmd.mut_header().set_connectionID(connection_id);
+                let kind = RequestKind::of(cmd.get_header().get_messageType());
 
                 let cmd_bytes = cmd.write_to_bytes().expect("boom7");
 
@@ -165,21 +438,153 @@ impl AsyncChannel {
 
                 {
                     let mut pending = pending_mutex.lock().unwrap(); // TODO: try!(...)
-                    pending.insert(seq, callback);
+                    if closed_writer.load(Ordering::SeqCst) {
+                        drop(pending);
+                        windows.get(kind).release();
+                        handler.fail(KineticError::ConnectionClosed);
+                        continue;
+                    }
+                    pending.insert(seq, (handler, kind));
                 }
-
-                let value = value.unwrap_or(vec::Vec::new());
-                ::network::send(&mut writer, &msg, value.as_ref()).expect("boom9");
                 seq += 1;
+
+                let sent = match streamed {
+                    None => {
+                        let value = value.as_ref().map_or(&[][..], |v| &v[..]);
+                        ::network::send(&mut writer, &msg, value).is_ok()
+                    },
+                    Some((len, value_ready, value_written)) => {
+                        ::network::send_header(&mut writer, &msg, len as usize).is_ok() && {
+                            // the caller writes the value straight to the socket, wait for it
+                            let _ = value_ready.send(());
+                            // a partial value can't be taken back, the connection is unusable
+                            value_written.recv() == Ok(true)
+                        }
+                    },
+                };
+
+                // Past a failed send the connection is unusable. Requests still queued
+                // are failed by the check above, until every sender is gone.
+                if !sent {
+                    let _ = writer.shutdown(net::Shutdown::Both);
+                    close(&pending_mutex, &windows, &closed_writer);
+                }
             }
         });
 
+        let sender = ChannelSender { writer_tx: w_tx,
+                                     windows: windows,
+                                     policy: policy,
+                                     reader: reader_thread.thread().id(),
+                                     closed: closed };
+
         Ok(AsyncChannel { stream: s,
-                          writer_tx: w_tx,
+                          sender: sender,
+                          unsol_tx: unsol_tx,
                           unsol_rx: unsol_rx,
                           configuration: configuration,
-                          limits: limits,
-                          closed: false, })
+                          limits: limits })
+    }
+
+    /// Gets the size of the in-flight window for the given `RequestKind`
+    #[inline]
+    pub fn get_window_size(&self, kind: RequestKind) -> usize {
+        self.sender.windows.get(kind).size
+    }
+
+    /// Gets a `ChannelSender` for this channel
+    #[inline]
+    pub fn get_sender(&self) -> ChannelSender {
+        self.sender.clone()
+    }
+
+    /// Sends an operation, `completion` will be invoked by the reader thread with the outcome
+    ///
+    /// Waits (or fails, depending on the `WindowPolicy`) for a free slot on the matching
+    /// in-flight window before queuing the operation. Sends from a completion never wait,
+    /// they fail with `KineticError::WindowFull` instead.
+    /// If the connection is lost before the response arrives `completion` receives
+    /// `KineticError::ConnectionClosed`.
+    #[inline]
+    pub fn send_with<F>(&self, op: Operation, completion: F) -> KineticResult<()>
+        where F: FnOnce(KineticResult<Result>) + Send + 'static {
+        self.sender.send_with(op, completion)
+    }
+
+    /// Sends an operation streaming its value from `source` and the response value into `sink`
+    ///
+    /// `source` must provide `len` bytes, the value of `op` is ignored. Both values are copied
+    /// straight between the socket and the caller, they are never held in memory whole.
+    /// The channel threads wait while the caller copies, so other requests are held back meanwhile.
+    ///
+    /// If `source` runs out early the connection is closed, since the device expects the rest
+    /// of the value. If `sink` fails the response value is still read off the connection.
+    ///
+    /// # Returns
+    /// Returns the response message and command, the response value having been written to `sink`.
+    pub fn send_streaming(&self, op: Operation, source: &mut io::Read, len: u64, sink: &mut io::Write)
+            -> KineticResult<(::proto::Message, ::proto::Command)> {
+
+        if self.is_closed() { return Err(KineticError::ConnectionClosed); }
+
+        let kind = RequestKind::of(op.1.get_header().get_messageType());
+        try!(self.sender.windows.get(kind).acquire(kind, self.sender.policy()));
+
+        let (value_ready_tx, value_ready_rx) = channel();
+        let (value_written_tx, value_written_rx) = channel();
+        let (response_tx, response_rx) = channel();
+        let (response_read_tx, response_read_rx) = channel();
+        let handoff = StreamHandoff { value_ready: value_ready_tx,
+                                      value_written: value_written_rx,
+                                      response: response_tx,
+                                      response_read: response_read_rx };
+
+        if self.sender.writer_tx.send(Request::Streamed(op, len, handoff)).is_err() {
+            self.sender.windows.get(kind).release();
+            return Err(KineticError::ConnectionClosed);
+        }
+
+        // wait for our turn on the socket, the writer drops the handoff if the request failed
+        if value_ready_rx.recv().is_err() { return Err(KineticError::ConnectionClosed); }
+
+        match io::copy(&mut source.take(len), &mut &self.stream) {
+            Ok(n) if n == len => { let _ = value_written_tx.send(true); },
+            Ok(_) => {
+                let _ = value_written_tx.send(false);
+                return Err(KineticError::IoError(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                                "value source ended early")));
+            },
+            Err(e) => {
+                let _ = value_written_tx.send(false);
+                return Err(KineticError::IoError(e));
+            },
+        }
+
+        let (msg, cmd, value_len) = match response_rx.recv() {
+            Ok(r) => try!(r),
+            Err(_) => return Err(KineticError::ConnectionClosed),
+        };
+
+        // the reader thread waits for us to read the value
+        match ::network::recv_value_into(&mut &self.stream, value_len, sink) {
+            Ok(sink_result) => {
+                let _ = response_read_tx.send(());
+                try!(sink_result);
+                Ok((msg, cmd))
+            },
+            // dropping the handoff tells the reader the connection is lost
+            Err(e) => Err(KineticError::IoError(e)),
+        }
+    }
+
+}
+
+impl CompletionChannel for AsyncChannel {
+
+    #[inline]
+    fn send_with<F>(&self, op: Operation, completion: F) -> KineticResult<()>
+        where F: FnOnce(KineticResult<Result>) + Send + 'static {
+        AsyncChannel::send_with(self, op, completion)
     }
 
 }
@@ -187,13 +592,18 @@ impl AsyncChannel {
 impl KineticChannel<Receiver<Result>> for AsyncChannel {
 
     #[inline]
-    fn is_closed(&self) -> bool { self.closed }
+    fn is_closed(&self) -> bool { self.sender.is_closed() }
 
     #[inline]
-    fn get_unsolicited_receiver<'r>(&'r self) -> &'r Receiver<Result> {
+    fn get_event_receiver<'r>(&'r self) -> &'r Receiver<DeviceEvent> {
         &self.unsol_rx
     }
 
+    #[inline]
+    fn notify(&self, event: DeviceEvent) {
+        let _ = self.unsol_tx.send(event);
+    }
+
     #[inline]
     fn get_configuration<'r>(&'r self) -> &'r ::proto::command::log::Configuration {
         &self.configuration
@@ -205,15 +615,127 @@ impl KineticChannel<Receiver<Result>> for AsyncChannel {
     }
 
     #[inline]
-    fn send(&self, op: Operation) -> Receiver<Result> {
+    fn send(&self, op: Operation) -> KineticResult<Receiver<Result>> {
         let (tx,rx) = channel();
-        self.writer_tx.send((op,tx)).unwrap();
-        rx //return rx
+        // on failure the sender is dropped, which `receive` reports as a closed connection
+        try!(self.send_with(op, move |r: KineticResult<Result>| {
+            if let Ok(r) = r { let _ = tx.send(r); }
+        }));
+        Ok(rx) //return rx
     }
 
     #[inline]
-    fn receive(rx: Receiver<Result>) -> Result {
-        rx.recv().unwrap() // TODO: try and return KineticResult
+    fn receive(rx: Receiver<Result>) -> KineticResult<Result> {
+        rx.recv().map_err(|_| KineticError::ConnectionClosed)
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use super::{AsyncChannel, KineticChannel, Operation, RequestKind, WindowPolicy};
+    use error::KineticError;
+    use proto::command::MessageType;
+    use testing::{Drive, Mode};
+
+    fn noop() -> Operation {
+        let mut cmd = ::proto::Command::new();
+        cmd.mut_header().set_messageType(MessageType::NOOP);
+        (Default::default(), cmd, None)
+    }
+
+    #[test]
+    fn window_fail_fast() {
+        let drive = Drive::with_window(2);
+        drive.set_mode(Mode::Silent);
+        let c = AsyncChannel::new_with_policy(drive.address(), 10, WindowPolicy::FailFast).unwrap();
+        assert_eq!(c.get_window_size(RequestKind::Read), 2);
+
+        assert!(c.send_with(noop(), |_| ()).is_ok());
+        assert!(c.send_with(noop(), |_| ()).is_ok());
+        match c.send_with(noop(), |_| ()) {
+            Err(KineticError::WindowFull(RequestKind::Read)) => (),
+            r => panic!("unexpected {:?}", r.err()),
+        }
+    }
+
+    #[test]
+    fn window_block() {
+        let drive = Drive::with_window(1);
+        drive.set_mode(Mode::Silent);
+        let c = AsyncChannel::new(drive.address(), 10).unwrap();
+        c.send_with(noop(), |_| ()).unwrap();
+
+        // the second send waits for the first response
+        let sender = c.get_sender();
+        let (sent_tx, sent_rx) = channel();
+        let (done_tx, done_rx) = channel();
+        thread::spawn(move || {
+            let r = sender.send_with(noop(), move |r| { let _ = done_tx.send(r.is_ok()); });
+            let _ = sent_tx.send(r.is_ok());
+        });
+        assert!(sent_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        // losing the connection frees the slot, the request then fails
+        drop(c);
+        assert_eq!(sent_rx.recv_timeout(Duration::from_secs(5)), Ok(true));
+        assert_eq!(done_rx.recv_timeout(Duration::from_secs(5)), Ok(false));
+    }
+
+    #[test]
+    fn window_block_serves_everything() {
+        let drive = Drive::with_window(1);
+        let c = AsyncChannel::new(drive.address(), 10).unwrap();
+        let (tx, rx) = channel();
+        for _ in 0..20 {
+            let tx = tx.clone();
+            c.send_with(noop(), move |r| { let _ = tx.send(r.is_ok()); }).unwrap();
+        }
+        for _ in 0..20 {
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(true));
+        }
+    }
+
+    #[test]
+    fn completions_run_once_on_disconnect() {
+        let drive = Drive::start();
+        drive.set_mode(Mode::Silent);
+        let c = AsyncChannel::new(drive.address(), 10).unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = channel();
+        let send = |c: &AsyncChannel| {
+            let calls = calls.clone();
+            let tx = tx.clone();
+            c.send_with(noop(), move |r| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let _ = tx.send(r);
+            }).unwrap();
+        };
+        send(&c);
+        send(&c);
+        send(&c);
+        drive.set_mode(Mode::Hangup);
+        send(&c);
+
+        for _ in 0..4 {
+            match rx.recv_timeout(Duration::from_secs(5)) {
+                Ok(Err(KineticError::ConnectionClosed)) => (),
+                r => panic!("unexpected {:?}", r.map(|r| r.err())),
+            }
+        }
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        assert!(c.is_closed());
+        match c.send_with(noop(), |_| panic!("never sent")) {
+            Err(KineticError::ConnectionClosed) => (),
+            r => panic!("unexpected {:?}", r.err()),
+        }
+    }
+}
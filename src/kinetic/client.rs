@@ -21,7 +21,6 @@
 // author: Ignacio Corderi
 
 use std::net::ToSocketAddrs;
-//use std::sync::Future;
 use core::{Command, Response};
 use result::KineticResult;
 use channel::Result;
@@ -29,9 +28,19 @@ use authentication::Credentials::Pin;
 use commands::pin::PinCommand;
 use std::sync::mpsc::{Receiver};
 use std::marker::PhantomData;
+use std::cell::Cell;
+use std::sync::Arc;
+use future::ResponseFuture;
+use pipeline::Pipeline;
+use commands::{Put, Get, Delete};
+use responses::{PutResponse, GetResponse, DeleteResponse, GetVersionResponse};
+use std::io;
 
 static DEFAULT_MAX_PENDING: usize = 10;
 
+/// Number of times `Client::update` retries on a conflicting write
+static DEFAULT_UPDATE_RETRIES: usize = 10;
+
 /// The Kinetic device client
 ///
 /// The Kinetic device client represents the main point of access for this library
@@ -50,7 +59,10 @@ static DEFAULT_MAX_PENDING: usize = 10;
 ///
 pub struct Client<Ch: ::channel::KineticChannel<T>,T> {
     channel: Ch,
-    cluster_version: i64,
+    cluster_version: Cell<i64>,
+    refresh_cluster_version: bool,
+    timeout: Option<i64>,
+    priority: Option<::proto::command::Priority>,
     default_credentials: ::authentication::Credentials,
     async_return_type: PhantomData<T>,
 }
@@ -70,7 +82,10 @@ impl Client<::channel::AsyncChannel, Receiver<Result>> {
         let c = try!(::channel::AsyncChannel::new(addr, DEFAULT_MAX_PENDING));
 
         Ok( Client { channel: c,
-                     cluster_version: 0,
+                     cluster_version: Cell::new(0),
+                     refresh_cluster_version: false,
+                     timeout: None,
+                     priority: None,
                      default_credentials: ::std::default::Default::default(),
                      async_return_type: PhantomData})
     }
@@ -82,14 +97,59 @@ impl<Ch: ::channel::KineticChannel<T>, T> Client<Ch,T> {
     #[inline]
     pub fn new_with_channel<A: ToSocketAddrs>(channel: Ch) -> KineticResult<Client<Ch, T>> {
         Ok( Client { channel: channel,
-                     cluster_version: 0,
+                     cluster_version: Cell::new(0),
+                     refresh_cluster_version: false,
+                     timeout: None,
+                     priority: None,
                      default_credentials: ::std::default::Default::default(),
                      async_return_type: PhantomData})
     }
 
     #[inline]
     pub fn set_cluster_version(&mut self, value: i64) {
-        self.cluster_version = value;
+        self.cluster_version.set(value);
+    }
+
+    /// Gets the cluster version sent on every request
+    #[inline]
+    pub fn get_cluster_version(&self) -> i64 {
+        self.cluster_version.get()
+    }
+
+    /// Enables or disables refreshing the cluster version when the device rejects it
+    ///
+    /// When enabled, a `send` rejected with `VERSION_FAILURE` because the cluster version changed
+    /// adopts the device cluster version and re-issues the command once.
+    /// The device cluster version is read from the rejected response or, if it's not there,
+    /// from a `GetLog`. A `DeviceEvent::ClusterVersionChanged` is emitted on every refresh.
+    ///
+    /// Only the blocking `send` and `send_with_pin` refresh the cluster version.
+    /// Disabled by default.
+    #[inline]
+    pub fn set_cluster_version_refresh(&mut self, enabled: bool) {
+        self.refresh_cluster_version = enabled;
+    }
+
+    /// Sets the milliseconds the device may take to process a request, `None` for the device default
+    #[inline]
+    pub fn set_timeout(&mut self, value: Option<i64>) {
+        self.timeout = value;
+    }
+
+    #[inline]
+    pub fn get_timeout(&self) -> Option<i64> {
+        self.timeout
+    }
+
+    /// Sets the priority of the requests relative to other clients, `None` for the device default
+    #[inline]
+    pub fn set_priority(&mut self, value: Option<::proto::command::Priority>) {
+        self.priority = value;
+    }
+
+    #[inline]
+    pub fn get_priority(&self) -> Option<::proto::command::Priority> {
+        self.priority
     }
 
     /// Gets the device `Configuration` received during _handshake_
@@ -104,25 +164,111 @@ impl<Ch: ::channel::KineticChannel<T>, T> Client<Ch,T> {
         self.channel.get_limits()
     }
 
+    /// Gets the stream of `DeviceEvent`s reported by the device
+    ///
+    /// Events are queued until received, use `recv()` or `iter()` to wait for them
+    /// and `try_recv()` to poll. The last event of a connection is `DeviceEvent::Disconnected`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kinetic::Client;
+    /// use kinetic::events::DeviceEvent;
+    ///
+    /// let c = Client::new("127.0.0.1:8123").unwrap();
+    /// for e in c.events().iter() {
+    ///     match e {
+    ///         DeviceEvent::Disconnected => break,
+    ///         e => println!("{:?}", e),
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn events<'r>(&'r self) -> &'r Receiver<::events::DeviceEvent> {
+        self.channel.get_event_receiver()
+    }
+
     #[inline]
-    fn send_raw<R : Response, C: Command<R>> (&self, auth: ::authentication::Credentials, cmd: C) -> T {
+    fn build_operation<R : Response, C: Command<R>> (&self, auth: ::authentication::Credentials, cmd: C)
+            -> ::channel::Operation {
         // build specific command
         let (mut cmd, value) = cmd.build_proto();
 
         // set extra client specific fields on the header
         {
-            let mut h = cmd.mut_header();
-            h.set_clusterVersion(self.cluster_version);
+            let h = cmd.mut_header();
+            h.set_clusterVersion(self.cluster_version.get());
+            if let Some(timeout) = self.timeout { h.set_timeout(timeout); }
+            if let Some(priority) = self.priority { h.set_priority(priority); }
+        }
+
+        (auth, cmd, value.map(Arc::new)) // return
+    }
+
+    /// Sends the operation and waits for the response, refreshing the cluster version if enabled
+    fn send_operation<R : Response> (&self, op: ::channel::Operation) -> KineticResult<R> {
+        if !self.refresh_cluster_version {
+            let token = try!(self.channel.send(op));
+            return Self::receive_raw(token);
+        }
+
+        // cheap, the value is shared
+        let retry = op.clone();
+        let token = try!(self.channel.send(op));
+        let (msg, cmd, value) = try!(Ch::receive(token));
+
+        if cmd.get_status().get_code() != ::proto::StatusCode::VERSION_FAILURE {
+            return Response::from_proto(msg, cmd, value);
+        }
+
+        let cluster_version = match try!(self.device_cluster_version(&cmd)) {
+            Some(v) if v != self.cluster_version.get() => v,
+            // not a cluster version problem after all
+            _ => return Response::from_proto(msg, cmd, value),
+        };
+
+        self.cluster_version.set(cluster_version);
+        self.channel.notify(::events::DeviceEvent::ClusterVersionChanged {
+            cluster_version: cluster_version,
+            status: cmd.get_status().clone() });
+
+        // re-issue the command, only once
+        let (auth, mut cmd, value) = retry;
+        cmd.mut_header().set_clusterVersion(cluster_version);
+        let token = try!(self.channel.send((auth, cmd, value)));
+        Self::receive_raw(token) // return
+    }
+
+    /// Reads the device cluster version from a response rejected with `VERSION_FAILURE`,
+    /// asks for it with a `GetLog` if the response doesn't carry it.
+    fn device_cluster_version(&self, rejected: &::proto::Command) -> KineticResult<Option<i64>> {
+        if rejected.get_header().has_clusterVersion() {
+            return Ok(Some(rejected.get_header().get_clusterVersion()));
+        }
+
+        // without our stale cluster version, or it gets rejected too
+        let get_log = ::commands::GetLog { log_types: vec![::proto::command::LogType::CONFIGURATION] };
+        let mut op = self.build_operation::<::responses::GetLogResponse, _>(self.default_credentials.clone(), get_log);
+        op.1.mut_header().clear_clusterVersion();
+        let token = try!(self.channel.send(op));
+        let (_, mut cmd, _) = try!(Ch::receive(token));
+
+        // a rejection still tells the cluster version
+        let code = cmd.get_status().get_code();
+        if code != ::proto::StatusCode::SUCCESS && code != ::proto::StatusCode::VERSION_FAILURE {
+            return Err(::error::KineticError::RemoteError(cmd.take_status()));
         }
 
-        // Send to device
-        self.channel.send((auth, cmd, value)) //return
+        if cmd.get_header().has_clusterVersion() {
+            Ok(Some(cmd.get_header().get_clusterVersion()))
+        } else {
+            Ok(None)
+        }
     }
 
     #[inline]
     fn receive_raw<R : Response> (token: T) -> KineticResult<R> {
         // Receive response
-        let (msg, cmd, value) = Ch::receive(token);
+        let (msg, cmd, value) = try!(Ch::receive(token));
 
         let r:KineticResult<R> = Response::from_proto(msg, cmd, value);
 
@@ -135,8 +281,97 @@ impl<Ch: ::channel::KineticChannel<T>, T> Client<Ch,T> {
     /// * `cmd` - The `PinCommand` to be sent.
     #[inline]
     pub fn send<C: Command<R>, R : Response> (&self, cmd: C) -> KineticResult<R> {
-        let token = self.send_raw(self.default_credentials.clone(), cmd);
-        Self::receive_raw(token) // return
+        let op = self.build_operation(self.default_credentials.clone(), cmd);
+        self.send_operation(op) // return
+    }
+
+    /// Updates an entry with a compare-and-swap, see `update_with`
+    ///
+    /// Versions are generated with `CounterVersions` and conflicts are retried up to 10 times.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kinetic::Client;
+    ///
+    /// let c = Client::new("127.0.0.1:8123").unwrap();
+    /// // appends a byte, creating the entry if missing
+    /// c.update("hello".as_bytes().to_vec(), |v| {
+    ///     let mut v = v.map(|v| v.to_vec()).unwrap_or(vec![]);
+    ///     v.push(b'!');
+    ///     Some(v)
+    /// }).unwrap();
+    /// ```
+    #[inline]
+    pub fn update<F>(&self, key: ::std::vec::Vec<u8>, f: F) -> KineticResult<Option<::std::vec::Vec<u8>>>
+        where F: FnMut(Option<&[u8]>) -> Option<::std::vec::Vec<u8>> {
+        self.update_with(key, &::versions::CounterVersions, DEFAULT_UPDATE_RETRIES, f)
+    }
+
+    /// Updates an entry with a compare-and-swap
+    ///
+    /// Reads the entry, calls `f` with its value (`None` if missing) and writes the result
+    /// under a version picked by `versions`, as long as the entry was not changed meanwhile.
+    /// A `None` result deletes the entry. When the entry is missing and `f` returns `None`
+    /// nothing is written and `Ok(None)` is returned.
+    ///
+    /// On a conflict (`VERSION_MISMATCH`, or the entry vanished) it starts over, calling `f`
+    /// again with the new value, at most `retries` times before returning the conflict error.
+    ///
+    /// Beware of `ContentHashVersions` here, a stale read may match again once the value
+    /// is changed back (see its documentation).
+    ///
+    /// # Returns
+    /// The new version of the entry, `None` when it was deleted or left missing.
+    pub fn update_with<F>(&self, key: ::std::vec::Vec<u8>, versions: &::versions::VersionGenerator,
+                          retries: usize, mut f: F) -> KineticResult<Option<::std::vec::Vec<u8>>>
+        where F: FnMut(Option<&[u8]>) -> Option<::std::vec::Vec<u8>> {
+
+        let mut attempt = 0;
+        loop {
+            let current = match self.send(Get { key: key.clone() }) {
+                Ok(r) => Some(r),
+                Err(::error::KineticError::RemoteError(ref s))
+                    if s.get_code() == ::proto::StatusCode::NOT_FOUND => None,
+                Err(e) => return Err(e),
+            };
+
+            let value = f(current.as_ref().map(|r| &r.value[..]));
+            let r = match (value, current) {
+                (Some(value), current) => {
+                    let current_version = current.map(|r| r.version);
+                    let version = versions.next_version(current_version.as_ref().map(|v| &v[..]), &value);
+                    self.send(Put { key: key.clone(),
+                                    value: value,
+                                    new_version: version.clone(),
+                                    // an empty version only matches a missing entry
+                                    current_version: ::commands::common::Versioning::Match(
+                                        current_version.unwrap_or(vec![])),
+                                    ..::std::default::Default::default() })
+                        .map(|_| Some(version))
+                },
+                (None, Some(current)) => {
+                    self.send(Delete::Versioned { key: key.clone(), version: current.version })
+                        .map(|_| None)
+                },
+                (None, None) => Ok(None), // nothing to delete
+            };
+
+            match r {
+                Err(::error::KineticError::RemoteError(ref s))
+                    if attempt < retries
+                       && (s.get_code() == ::proto::StatusCode::VERSION_MISMATCH
+                           || s.get_code() == ::proto::StatusCode::NOT_FOUND) => attempt += 1,
+                r => return r,
+            }
+        }
+    }
+
+    /// Gets a view of this client restricted to the keys starting with `prefix`
+    ///
+    /// See `Namespace` for details.
+    #[inline]
+    pub fn namespace<'r>(&'r self, prefix: ::std::vec::Vec<u8>) -> ::namespace::Namespace<'r, Ch, T> {
+        ::namespace::Namespace::new(self, prefix)
     }
 
     /// Sends a `PinCommand` to the target device an waits for the `Response`
@@ -147,13 +382,138 @@ impl<Ch: ::channel::KineticChannel<T>, T> Client<Ch,T> {
     #[inline]
     pub fn send_with_pin<C: PinCommand<R>, R : Response> (&self, cmd: C, pin: ::std::vec::Vec<u8>) -> KineticResult<R> {
         let auth = Pin { pin: pin };
-        let token = self.send_raw(auth, cmd);
-        Self::receive_raw(token) // return
+        let op = self.build_operation(auth, cmd);
+        self.send_operation(op) // return
     }
 }
 
 pub type AsyncClient = Client<::channel::AsyncChannel, Receiver<Result>>;
 
+pub type PooledClient = Client<::pool::PoolChannel, Receiver<Result>>;
+
+/// Asynchronous sends, for the channels delivering responses to a completion
+impl<Ch: ::channel::KineticChannel<T> + ::channel::CompletionChannel, T> Client<Ch,T> {
+
+    /// Sends a `Command` to the target device and returns a `Future` for the `Response`
+    ///
+    /// The future is resolved by the channel reader thread, so no thread is blocked
+    /// while the request is in flight. It can be driven by any executor or simply `wait()`ed on.
+    ///
+    /// Sending still honors the `WindowPolicy`, use `WindowPolicy::FailFast` to make sure
+    /// an executor thread is never blocked on a full in-flight window.
+    ///
+    /// # Arguments
+    /// * `cmd` - The `Command` to be sent.
+    ///
+    /// # Example
+    /// ```no_run
+    /// extern crate futures;
+    /// extern crate kinetic;
+    ///
+    /// use futures::Future;
+    /// use kinetic::Client;
+    /// use kinetic::commands::Get;
+    ///
+    /// # fn main() {
+    /// let c = Client::new("127.0.0.1:8123").unwrap();
+    /// let v = c.send_future(Get { key: "hello".as_bytes().to_vec() })
+    ///          .map(|r| r.value)
+    ///          .wait().unwrap();
+    /// # }
+    /// ```
+    pub fn send_future<C: Command<R>, R : Response> (&self, cmd: C) -> ResponseFuture<R> {
+        let op = self.build_operation(self.default_credentials.clone(), cmd);
+
+        let (tx, rx) = ::futures::sync::oneshot::channel();
+        match self.channel.send_with(op, move |r| { let _ = tx.send(r); }) {
+            Ok(()) => ResponseFuture::new(rx),
+            Err(e) => ResponseFuture::failed(e),
+        }
+    }
+
+    /// Sends a `Command` to the target device and invokes `callback` with the `Response`
+    ///
+    /// The callback is invoked directly by the channel reader thread when the response arrives,
+    /// no channel is allocated per request. Keep it short, the reader thread can't process
+    /// any other response until the callback returns.
+    ///
+    /// The callback may send further commands with `send_with_callback`, but it must never wait
+    /// for a response: a blocking `send`, or waiting on a future, from a callback deadlocks the
+    /// connection since only the reader thread can deliver that response. For the same reason a
+    /// send from a callback never waits for a slot, it fails with `KineticError::WindowFull`
+    /// when the in-flight window is full, whatever the `WindowPolicy`. On a pooled client the
+    /// callback runs on the reader thread of the connection the command went to.
+    ///
+    /// # Arguments
+    /// * `cmd` - The `Command` to be sent.
+    /// * `callback` - Invoked once with the result of the command.
+    ///
+    /// # Returns
+    /// Returns an `Err(...)` without invoking `callback` if the command could not be sent,
+    /// for example if the connection is closed or the in-flight window is full under
+    /// `WindowPolicy::FailFast`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kinetic::Client;
+    /// use kinetic::commands::Get;
+    ///
+    /// let c = Client::new("127.0.0.1:8123").unwrap();
+    /// c.send_with_callback(Get { key: "hello".as_bytes().to_vec() }, |r| {
+    ///     match r {
+    ///         Ok(r) => println!("{} bytes", r.value.len()),
+    ///         Err(e) => println!("failed: {}", e),
+    ///     }
+    /// }).unwrap();
+    /// ```
+    pub fn send_with_callback<C, R, F> (&self, cmd: C, callback: F) -> KineticResult<()>
+        where C: Command<R>, R: Response + 'static, F: FnOnce(KineticResult<R>) + Send + 'static {
+
+        let op = self.build_operation(self.default_credentials.clone(), cmd);
+
+        self.channel.send_with(op, move |r: KineticResult<Result>| {
+            callback(r.and_then(|(msg, cmd, value)| Response::from_proto(msg, cmd, value)))
+        }) // return
+    }
+}
+
+/// `Client` backed by a `PoolChannel`
+impl Client<::pool::PoolChannel, Receiver<Result>> {
+
+    /// Creates a new `Client` spreading requests over several connections to the same device
+    ///
+    /// Opens up to `connections` connections, never more than the device `maxConnections`.
+    /// See `PoolChannel` for details.
+    ///
+    /// Besides `send` it supports `send_future` and `send_with_callback`. Pipelining with
+    /// `send_many` and streaming values are only available on an `AsyncClient`, since they
+    /// depend on the window and socket of a single connection.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kinetic::Client;
+    /// use kinetic::commands::Get;
+    ///
+    /// let c = Client::new_pooled("127.0.0.1:8123", 4).unwrap();
+    /// let v = c.send(Get { key: "hello".as_bytes().to_vec() }).unwrap();
+    /// ```
+    #[inline]
+    pub fn new_pooled<A: ToSocketAddrs>(addr: A, connections: usize)
+            -> KineticResult<Client<::pool::PoolChannel, Receiver<Result>>> {
+
+        let c = try!(::pool::PoolChannel::new(addr, connections, DEFAULT_MAX_PENDING,
+                                              ::channel::WindowPolicy::Block));
+
+        Ok( Client { channel: c,
+                     cluster_version: Cell::new(0),
+                     refresh_cluster_version: false,
+                     timeout: None,
+                     priority: None,
+                     default_credentials: ::std::default::Default::default(),
+                     async_return_type: PhantomData })
+    }
+}
+
 /// `Client` backed by an `AsyncChannel`
 impl Client<::channel::AsyncChannel, Receiver<Result>> {
 
@@ -164,8 +524,219 @@ impl Client<::channel::AsyncChannel, Receiver<Result>> {
         let c = try!(::channel::AsyncChannel::new(addr, DEFAULT_MAX_PENDING));
 
         Ok( Client { channel: c,
-                     cluster_version: 0,
+                     cluster_version: Cell::new(0),
+                     refresh_cluster_version: false,
+                     timeout: None,
+                     priority: None,
                      default_credentials: credentials,
                      async_return_type: PhantomData })
     }
+
+    /// Stores a value streamed from `source` straight to the device
+    ///
+    /// The value is never held in memory whole, which keeps near `maxValueSize` values cheap.
+    /// Other requests on this client are held back while the value is being sent.
+    ///
+    /// # Arguments
+    /// * `cmd` - The `Put` to send, its `value` is ignored.
+    /// * `source` - Where the value is read from.
+    /// * `len` - The length of the value, `source` must provide at least `len` bytes.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kinetic::Client;
+    /// use kinetic::commands::Put;
+    /// use kinetic::commands::common::Versioning;
+    /// use std::default::Default;
+    /// use std::fs::File;
+    ///
+    /// let c = Client::new("127.0.0.1:8123").unwrap();
+    /// let mut f = File::open("photo.jpg").unwrap();
+    /// let len = f.metadata().unwrap().len();
+    /// c.put_from_reader(Put { key: "photo.jpg".as_bytes().to_vec(),
+    ///                         current_version: Versioning::Force,
+    ///                         ..Default::default() }, &mut f, len).unwrap();
+    /// ```
+    pub fn put_from_reader(&self, cmd: Put, source: &mut io::Read, len: u64) -> KineticResult<PutResponse> {
+        let (auth, cmd, _) = self.build_operation(self.default_credentials.clone(), cmd);
+
+        let (msg, cmd) = try!(self.channel.send_streaming((auth, cmd, None), source, len, &mut io::sink()));
+        Response::from_proto(msg, cmd, vec![]) // return
+    }
+
+    /// Gets the value of a key streaming it straight from the device into `sink`
+    ///
+    /// The value is never held in memory whole, which keeps near `maxValueSize` values cheap.
+    /// Other requests on this client are held back while the value is being received.
+    ///
+    /// # Returns
+    /// Returns the version and integrity information of the value written to `sink`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kinetic::Client;
+    /// use std::fs::File;
+    ///
+    /// let c = Client::new("127.0.0.1:8123").unwrap();
+    /// let mut f = File::create("photo.jpg").unwrap();
+    /// let meta = c.get_into_writer("photo.jpg".as_bytes().to_vec(), &mut f).unwrap();
+    /// ```
+    pub fn get_into_writer(&self, key: ::std::vec::Vec<u8>, sink: &mut io::Write) -> KineticResult<GetVersionResponse> {
+        let op = self.build_operation(self.default_credentials.clone(), Get { key: key });
+
+        let (msg, cmd) = try!(self.channel.send_streaming(op, &mut io::empty(), 0, sink));
+        Response::from_proto(msg, cmd, vec![]) // return
+    }
+
+    /// Sends all the commands, keeping up to a full in-flight window of them pipelined
+    ///
+    /// Commands are pulled from `cmds` as the pipeline drains, results are yielded in input order.
+    /// Each item carries its own result, a failed command does not stop the others.
+    /// Use `Pipeline::unordered()` to get the results as they complete instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use kinetic::Client;
+    /// use kinetic::commands::Put;
+    /// use std::default::Default;
+    ///
+    /// let c = Client::new("127.0.0.1:8123").unwrap();
+    /// let puts = (0..1000).map(|i| Put { key: format!("key.{}", i).into_bytes(),
+    ///                                    value: vec![0u8; 1024],
+    ///                                    ..Default::default() });
+    /// for (i, r) in c.send_many(puts).enumerate() {
+    ///     if let Err(e) = r { println!("key.{} failed: {}", i, e); }
+    /// }
+    /// ```
+    #[inline]
+    pub fn send_many<I, C, R> (&self, cmds: I) -> Pipeline<'_, I::IntoIter, C, R>
+        where I: IntoIterator<Item=C>, C: Command<R>, R: Response + 'static {
+        // the pipeline doesn't know which window the commands go to, stay within both
+        let depth = ::std::cmp::min(self.channel.get_window_size(::channel::RequestKind::Read),
+                                    self.channel.get_window_size(::channel::RequestKind::Write));
+        Pipeline::new(self, cmds.into_iter(), depth)
+    }
+
+    /// Pipelines a set of `Put` commands, see `send_many`
+    #[inline]
+    pub fn put_many<I: IntoIterator<Item=Put>> (&self, puts: I) -> Pipeline<'_, I::IntoIter, Put, PutResponse> {
+        self.send_many(puts)
+    }
+
+    /// Pipelines a set of `Get` commands, see `send_many`
+    #[inline]
+    pub fn get_many<I: IntoIterator<Item=Get>> (&self, gets: I) -> Pipeline<'_, I::IntoIter, Get, GetResponse> {
+        self.send_many(gets)
+    }
+
+    /// Pipelines a set of `Delete` commands, see `send_many`
+    #[inline]
+    pub fn delete_many<I: IntoIterator<Item=Delete>> (&self, deletes: I)
+            -> Pipeline<'_, I::IntoIter, Delete, DeleteResponse> {
+        self.send_many(deletes)
+    }
+
+    /// Creates a new `Client` that handles full in-flight windows with the given `WindowPolicy`
+    ///
+    /// Reads and writes are tracked on separate windows, sized from the
+    /// `maxOutstandingReadRequests` and `maxOutstandingWriteRequests` device limits.
+    /// With `WindowPolicy::FailFast` a `send` on a full window returns `KineticError::WindowFull`
+    /// instead of blocking.
+    #[inline]
+    pub fn new_with_policy<A: ToSocketAddrs>(addr: A, policy: ::channel::WindowPolicy)
+            -> KineticResult<Client<::channel::AsyncChannel, Receiver<Result>>> {
+
+        let c = try!(::channel::AsyncChannel::new_with_policy(addr, DEFAULT_MAX_PENDING, policy));
+
+        Ok( Client { channel: c,
+                     cluster_version: Cell::new(0),
+                     refresh_cluster_version: false,
+                     timeout: None,
+                     priority: None,
+                     default_credentials: ::std::default::Default::default(),
+                     async_return_type: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+    use futures::Future;
+    use super::Client;
+    use commands::{Put, Get, Noop};
+    use commands::common::Versioning;
+    use events::DeviceEvent;
+    use error::KineticError;
+    use proto::StatusCode;
+    use testing::{Drive, Mode};
+
+    fn put(key: &str, value: &str) -> Put {
+        Put { key: key.as_bytes().to_vec(),
+              value: value.as_bytes().to_vec(),
+              current_version: Versioning::Force,
+              ..Default::default() }
+    }
+
+    #[test]
+    fn futures_and_callbacks() {
+        let drive = Drive::start();
+        let c = Client::new(drive.address()).unwrap();
+
+        c.send_future(put("a", "1")).wait().unwrap();
+        assert_eq!(c.send_future(Get { key: b"a".to_vec() }).wait().unwrap().value, b"1");
+
+        let (tx, rx) = channel();
+        c.send_with_callback(Get { key: b"b".to_vec() }, move |r| { let _ = tx.send(r); }).unwrap();
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            Err(KineticError::RemoteError(ref s)) if s.get_code() == StatusCode::NOT_FOUND => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn pipelines_keep_the_order() {
+        let drive = Drive::with_window(3);
+        let c = Client::new(drive.address()).unwrap();
+
+        let keys: Vec<String> = (0..20).map(|i| format!("k{}", i)).collect();
+        assert!(c.put_many(keys.iter().map(|k| put(k, k))).all(|r| r.is_ok()));
+
+        let values: Vec<Vec<u8>> = c.get_many(keys.iter().map(|k| Get { key: k.as_bytes().to_vec() }))
+            .map(|r| r.unwrap().value).collect();
+        assert_eq!(values, keys.iter().map(|k| k.as_bytes().to_vec()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn disconnected_event() {
+        let drive = Drive::start();
+        let c = Client::new(drive.address()).unwrap();
+        drive.set_mode(Mode::Hangup);
+
+        assert!(c.send(Noop).is_err());
+        match c.events().recv_timeout(Duration::from_secs(5)) {
+            Ok(DeviceEvent::Disconnected) => (),
+            e => panic!("unexpected {:?}", e),
+        }
+    }
+
+    #[test]
+    fn cluster_version_refresh() {
+        let drive = Drive::start();
+        drive.set_cluster_version(7);
+        let mut c = Client::new(drive.address()).unwrap();
+
+        match c.send(Noop) {
+            Err(KineticError::RemoteError(ref s)) if s.get_code() == StatusCode::VERSION_FAILURE => (),
+            r => panic!("unexpected {:?}", r),
+        }
+
+        c.set_cluster_version_refresh(true);
+        c.send(Noop).unwrap();
+        assert_eq!(c.get_cluster_version(), 7);
+        match c.events().try_recv() {
+            Ok(DeviceEvent::ClusterVersionChanged { cluster_version: 7, .. }) => (),
+            e => panic!("unexpected {:?}", e),
+        }
+    }
 }
@@ -0,0 +1,330 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Clients spanning multiple kinetic devices
+
+use std::collections::HashMap;
+use std::{cmp, vec};
+use byteorder::{ByteOrder, BigEndian};
+use core::Response;
+use commands::KeyCommand;
+use result::KineticResult;
+use error::KineticError;
+use client::AsyncClient;
+
+/// Number of points a drive gets on the ring per unit of weight
+static VIRTUAL_NODES_PER_WEIGHT: u32 = 16;
+
+/// Nominal capacity, in bytes, worth one unit of weight (100 GB)
+static BYTES_PER_WEIGHT: u64 = 100 * 1000 * 1000 * 1000;
+
+/// Hashes to a point on the ring
+#[inline]
+fn hash(data: &[u8]) -> u64 {
+    <BigEndian as ByteOrder>::read_u64(&::digest::sha1(data)[..8])
+}
+
+/// Gets the ring weight for a drive with the given nominal capacity
+#[inline]
+pub fn weight_for_capacity(bytes: u64) -> u32 {
+    cmp::max(1, bytes / BYTES_PER_WEIGHT) as u32
+}
+
+/// A consistent hash ring
+///
+/// Each node is placed on the ring on a number of _virtual nodes_ proportional to its weight.
+/// A key belongs to the first node found walking the ring clockwise from the hash of the key,
+/// so adding or removing a node only moves the keys that node gains or loses.
+#[derive(Default)]
+pub struct HashRing {
+    points: vec::Vec<(u64, String)>,
+}
+
+impl HashRing {
+
+    /// Creates an empty ring
+    pub fn new() -> HashRing {
+        HashRing { points: vec::Vec::new() }
+    }
+
+    /// Adds a node with the given weight
+    ///
+    /// # Panics
+    /// Panics if `weight` is zero, the node would own no keys.
+    pub fn add(&mut self, node: &str, weight: u32) {
+        assert!(weight > 0, "a node needs a weight of at least 1");
+        for i in 0..weight * VIRTUAL_NODES_PER_WEIGHT {
+            let point = hash(format!("{}#{}", node, i).as_bytes());
+            self.points.push((point, node.to_string()));
+        }
+        self.points.sort();
+    }
+
+    /// Removes a node, all its keys go to the nodes that follow it on the ring
+    pub fn remove(&mut self, node: &str) {
+        self.points.retain(|&(_, ref n)| n != node);
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Gets the node a key belongs to
+    #[inline]
+    pub fn node_for<'r>(&'r self, key: &[u8]) -> Option<&'r str> {
+        self.nodes_for(key, 1).pop()
+    }
+
+    /// Gets up to `n` distinct nodes for a key, in ring order
+    ///
+    /// The first node is the one the key belongs to, the rest are the nodes
+    /// that would take over the key if the previous ones were removed.
+    pub fn nodes_for<'r>(&'r self, key: &[u8], n: usize) -> vec::Vec<&'r str> {
+        let mut nodes: vec::Vec<&'r str> = vec::Vec::with_capacity(n);
+        if self.points.is_empty() { return nodes; }
+
+        let h = hash(key);
+        let start = match self.points.binary_search_by(|&(p, _)| p.cmp(&h)) {
+            Ok(i) => i,
+            Err(i) => i,
+        };
+
+        for i in 0..self.points.len() {
+            if nodes.len() == n { break; }
+
+            let (_, ref node) = self.points[(start + i) % self.points.len()];
+            if !nodes.contains(&node.as_ref()) {
+                nodes.push(node.as_ref());
+            }
+        }
+
+        nodes // return
+    }
+
+}
+
+/// A client for a set of kinetic devices
+///
+/// Keys are placed on the devices using consistent hashing, weighted by the device capacity.
+/// Single key commands (`Put`, `Get`, `Delete`, `GetVersion`) are routed to the device
+/// owning the key.
+///
+/// # Example
+/// ```no_run
+/// use kinetic::cluster::ClusterClient;
+/// use kinetic::commands::{Put, Get};
+/// use std::default::Default;
+///
+/// let mut c = ClusterClient::new();
+/// c.connect("10.0.0.1:8123").unwrap();
+/// c.connect("10.0.0.2:8123").unwrap();
+///
+/// c.send(Put { key: "hello".as_bytes().to_vec(),
+///              value: "world".as_bytes().to_vec(),
+///              ..Default::default() }).unwrap();
+/// let v = c.send(Get { key: "hello".as_bytes().to_vec() }).unwrap();
+/// ```
+#[derive(Default)]
+pub struct ClusterClient {
+    drives: HashMap<String, AsyncClient>,
+    ring: HashRing,
+}
+
+impl ClusterClient {
+
+    /// Creates a `ClusterClient` without drives
+    pub fn new() -> ClusterClient {
+        ClusterClient { drives: HashMap::new(), ring: HashRing::new() }
+    }
+
+    /// Connects to the device at `addr` and adds it, named after its address
+    pub fn connect(&mut self, addr: &str) -> KineticResult<()> {
+        let c = try!(::client::Client::new(addr));
+        self.add_drive(addr, c)
+    }
+
+    /// Adds a drive weighted by its nominal capacity
+    pub fn add_drive(&mut self, name: &str, client: AsyncClient) -> KineticResult<()> {
+        let log = try!(client.send(::commands::GetLog {
+            log_types: vec![::proto::command::LogType::CAPACITIES] }));
+        let weight = weight_for_capacity(log.get_capacity().get_nominalCapacityInBytes());
+
+        self.add_drive_with_weight(name, client, weight);
+        Ok(())
+    }
+
+    /// Adds a drive with an explicit weight
+    ///
+    /// Replaces any drive with the same `name`.
+    ///
+    /// # Panics
+    /// Panics if `weight` is zero.
+    pub fn add_drive_with_weight(&mut self, name: &str, client: AsyncClient, weight: u32) {
+        assert!(weight > 0, "a drive needs a weight of at least 1");
+        self.remove_drive(name);
+        self.ring.add(name, weight);
+        self.drives.insert(name.to_string(), client);
+    }
+
+    /// Removes a drive, its keys are now placed on the remaining drives
+    pub fn remove_drive(&mut self, name: &str) -> Option<AsyncClient> {
+        self.ring.remove(name);
+        self.drives.remove(name)
+    }
+
+    /// Gets the names of all the drives
+    pub fn drive_names(&self) -> vec::Vec<&str> {
+        self.drives.keys().map(|k| k.as_ref()).collect()
+    }
+
+    /// Gets a drive by name
+    #[inline]
+    pub fn get_drive<'r>(&'r self, name: &str) -> Option<&'r AsyncClient> {
+        self.drives.get(name)
+    }
+
+    /// Gets the name of the drive a key is placed on
+    #[inline]
+    pub fn drive_name_for<'r>(&'r self, key: &[u8]) -> Option<&'r str> {
+        self.ring.node_for(key)
+    }
+
+    /// Gets the names of up to `n` distinct drives for a key
+    ///
+    /// The first one is the drive the key is placed on, the rest are the drives that would
+    /// take over the key if the previous ones were removed.
+    #[inline]
+    pub fn drive_names_for<'r>(&'r self, key: &[u8], n: usize) -> vec::Vec<&'r str> {
+        self.ring.nodes_for(key, n)
+    }
+
+    /// Gets the drive a key is placed on
+    #[inline]
+    pub fn drive_for<'r>(&'r self, key: &[u8]) -> Option<&'r AsyncClient> {
+        self.ring.node_for(key).and_then(|name| self.drives.get(name))
+    }
+
+    /// Sends a single key `Command` to the drive owning the key and waits for the `Response`
+    pub fn send<C: KeyCommand<R>, R: Response> (&self, cmd: C) -> KineticResult<R> {
+        match self.drive_for(cmd.key()) {
+            Some(c) => c.send(cmd),
+            None => Err(KineticError::EmptyCluster),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::{HashRing, weight_for_capacity};
+
+    static KEYS: usize = 20000;
+
+    fn key(i: usize) -> String {
+        format!("key-{}", i)
+    }
+
+    /// Counts the keys each node owns
+    fn owners(ring: &HashRing) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for i in 0..KEYS {
+            *counts.entry(ring.node_for(key(i).as_bytes()).unwrap().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn keys_spread_over_the_nodes() {
+        let mut ring = HashRing::new();
+        for n in ["a", "b", "c", "d"].iter() { ring.add(n, 4); }
+
+        let counts = owners(&ring);
+        assert_eq!(counts.len(), 4);
+        for (_, &c) in counts.iter() {
+            // a quarter each, give or take
+            assert!(c > KEYS * 3 / 20 && c < KEYS * 7 / 20, "unbalanced {:?}", counts);
+        }
+    }
+
+    #[test]
+    fn adding_a_node_moves_its_share_only() {
+        let mut ring = HashRing::new();
+        for n in ["a", "b", "c"].iter() { ring.add(n, 4); }
+        let before: Vec<String> = (0..KEYS).map(|i| ring.node_for(key(i).as_bytes()).unwrap().to_string()).collect();
+
+        ring.add("d", 4);
+        let mut moved = 0;
+        for (i, owner) in before.iter().enumerate() {
+            let now = ring.node_for(key(i).as_bytes()).unwrap();
+            if now != owner {
+                // keys only move to the new node
+                assert_eq!(now, "d");
+                moved += 1;
+            }
+        }
+        // about 1/4 of the keys
+        assert!(moved > KEYS * 3 / 20 && moved < KEYS * 7 / 20, "moved {}", moved);
+
+        // and removing it moves them back
+        ring.remove("d");
+        for (i, owner) in before.iter().enumerate() {
+            assert_eq!(ring.node_for(key(i).as_bytes()).unwrap(), owner);
+        }
+    }
+
+    #[test]
+    fn keys_follow_the_weights() {
+        let mut ring = HashRing::new();
+        ring.add("small", 2);
+        ring.add("big", 6);
+
+        let counts = owners(&ring);
+        let big = counts["big"] as f64 / KEYS as f64;
+        assert!(big > 0.65 && big < 0.85, "big owns {}", big);
+    }
+
+    #[test]
+    fn replicas_are_distinct() {
+        let mut ring = HashRing::new();
+        for n in ["a", "b", "c"].iter() { ring.add(n, 1); }
+
+        let nodes = ring.nodes_for(b"hello", 5);
+        assert_eq!(nodes.len(), 3);
+        assert!(nodes.contains(&"a") && nodes.contains(&"b") && nodes.contains(&"c"));
+        assert_eq!(nodes[0], ring.node_for(b"hello").unwrap());
+        assert!(HashRing::new().nodes_for(b"hello", 2).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_weight_is_rejected() {
+        HashRing::new().add("a", 0);
+    }
+
+    #[test]
+    fn weights_from_capacity() {
+        assert_eq!(weight_for_capacity(0), 1);
+        assert_eq!(weight_for_capacity(4 * 1000 * 1000 * 1000 * 1000), 40);
+    }
+}
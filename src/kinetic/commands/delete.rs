@@ -21,6 +21,7 @@
 // author: Ignacio Corderi
 
 use core::Command;
+use commands::KeyCommand;
 use std::vec;
 
 /// Deletes the key
@@ -64,6 +65,26 @@ pub enum Delete {
     Forced { key: vec::Vec<u8>, },
 }
 
+impl KeyCommand<::responses::DeleteResponse> for Delete {
+
+    #[inline]
+    fn key<'r>(&'r self) -> &'r [u8] {
+        match *self {
+            Delete::Versioned { ref key, .. } => key.as_ref(),
+            Delete::Forced { ref key } => key.as_ref(),
+        }
+    }
+
+    #[inline]
+    fn key_mut<'r>(&'r mut self) -> &'r mut vec::Vec<u8> {
+        match *self {
+            Delete::Versioned { ref mut key, .. } => key,
+            Delete::Forced { ref mut key } => key,
+        }
+    }
+
+}
+
 impl Command<::responses::DeleteResponse> for Delete {
 
     fn build_proto(self) -> (::proto::Command, Option<vec::Vec<u8>>) {
@@ -53,3 +53,38 @@ impl Command<::responses::GetLogResponse> for GetLog {
     }
 
 }
+
+/// Get's a device specific log, by name
+///
+/// The names and contents of these logs are up to the device vendor.
+pub struct GetDeviceLog {
+    pub name: vec::Vec<u8>
+}
+
+impl Command<::responses::GetDeviceLogResponse> for GetDeviceLog {
+
+    fn build_proto(self) -> (::proto::Command, Option<vec::Vec<u8>>) {
+        let mut cmd = ::proto::Command::new();
+        let mut header = command::Header::new();
+
+        // Set command type
+        header.set_messageType(command::MessageType::GETLOG);
+        cmd.set_header(header);
+
+        // Build the actual command
+        let mut device = command::log::Device::new();
+        device.set_name(self.name);
+
+        let mut get_log = command::GetLog::new();
+        get_log.set_types(vec![command::LogType::DEVICE]);
+        get_log.set_device(device);
+
+        // Fill the body
+        let mut body = command::Body::new();
+        body.set_getLog(get_log);
+        cmd.set_body(body);
+
+        (cmd, None) // return command
+    }
+
+}
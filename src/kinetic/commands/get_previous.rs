@@ -28,7 +28,7 @@ pub struct GetPrevious {
     pub key: vec::Vec<u8>
 }
 
-impl Command<::responses::GetResponse> for GetPrevious {
+impl Command<::responses::GetPreviousResponse> for GetPrevious {
 
     fn build_proto(self) -> (::proto::Command, Option<vec::Vec<u8>>) {
         let mut cmd = ::proto::Command::new();
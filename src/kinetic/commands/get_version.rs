@@ -21,6 +21,7 @@
 // author: Ignacio Corderi
 
 use core::Command;
+use commands::KeyCommand;
 use std::vec;
 
 /// Get's the version and integrity for the given key
@@ -28,6 +29,16 @@ pub struct GetVersion {
     pub key: vec::Vec<u8>
 }
 
+impl KeyCommand<::responses::GetVersionResponse> for GetVersion {
+
+    #[inline]
+    fn key<'r>(&'r self) -> &'r [u8] { self.key.as_ref() }
+
+    #[inline]
+    fn key_mut<'r>(&'r mut self) -> &'r mut vec::Vec<u8> { &mut self.key }
+
+}
+
 impl Command<::responses::GetVersionResponse> for GetVersion {
 
     fn build_proto(self) -> (::proto::Command, Option<vec::Vec<u8>>) {
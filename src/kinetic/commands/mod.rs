@@ -24,7 +24,7 @@
 
 pub use commands::get::Get;
 pub use commands::put::Put;
-pub use commands::get_log::GetLog;
+pub use commands::get_log::{GetLog, GetDeviceLog};
 pub use commands::delete::Delete;
 pub use commands::get_key_range::GetKeyRange;
 pub use commands::get_version::GetVersion;
@@ -46,6 +46,20 @@ mod flush;
 
 pub mod pin;
 
+/// Trait representing a Kinetic command that targets a single key
+///
+/// Single key commands can be routed, or have their key rewritten, without knowing
+/// the concrete command.
+pub trait KeyCommand<R: ::core::Response>: ::core::Command<R> {
+
+    /// The key targeted by the command
+    fn key<'r>(&'r self) -> &'r [u8];
+
+    /// The key targeted by the command, for rewriting
+    fn key_mut<'r>(&'r mut self) -> &'r mut ::std::vec::Vec<u8>;
+
+}
+
 pub mod common {
 
     use std::vec;
@@ -21,6 +21,7 @@
 // author: Ignacio Corderi
 
 use core::Command;
+use commands::KeyCommand;
 use std::vec;
 use proto::command;
 use std::default::Default;
@@ -54,6 +55,16 @@ impl Default for Put {
     }
 }
 
+impl KeyCommand<::responses::PutResponse> for Put {
+
+    #[inline]
+    fn key<'r>(&'r self) -> &'r [u8] { self.key.as_ref() }
+
+    #[inline]
+    fn key_mut<'r>(&'r mut self) -> &'r mut vec::Vec<u8> { &mut self.key }
+
+}
+
 impl Command<::responses::PutResponse> for Put {
 
     fn build_proto(self) -> (::proto::Command, Option<vec::Vec<u8>>) {
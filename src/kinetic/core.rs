@@ -41,12 +41,12 @@ pub trait Response : Send {
 
 /// Returns the current version of the package
 pub fn version() -> String {
-    format!("{}", match option_env!("CFG_VERSION") {
+    match option_env!("CFG_VERSION") {
         Some(s) => s.to_string(),
         None => format!("{}.{}.{}{}",
                         env!("CARGO_PKG_VERSION_MAJOR"),
                         env!("CARGO_PKG_VERSION_MINOR"),
                         env!("CARGO_PKG_VERSION_PATCH"),
                         option_env!("CARGO_PKG_VERSION_PRE").unwrap_or(""))
-    })
+    }
 }
@@ -0,0 +1,119 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Digests used for integrity tags, versions and checksums
+
+use std::vec;
+use crypto::sha1::Sha1;
+use crypto::digest::Digest;
+
+/// Gets the SHA1 of `data`
+#[inline]
+pub fn sha1(data: &[u8]) -> vec::Vec<u8> {
+    let mut sha = Sha1::new();
+    sha.input(data);
+    let mut digest = vec![0u8; 20];
+    sha.result(&mut digest);
+    digest
+}
+
+/// CRC-32 (IEEE 802.3) polynomial, reversed
+pub const CRC32: u32 = 0xedb88320;
+
+/// CRC-32C (Castagnoli) polynomial, reversed
+pub const CRC32C: u32 = 0x82f63b78;
+
+/// A CRC computed over data given in pieces
+///
+/// # Example
+/// ```
+/// use kinetic::digest::{self, Crc};
+///
+/// let mut crc = Crc::new(digest::CRC32);
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.finish(), digest::crc32(b"123456789"));
+/// ```
+pub struct Crc {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc {
+
+    /// Starts a CRC with the given reversed polynomial, `CRC32` or `CRC32C`
+    pub fn new(poly: u32) -> Crc {
+        let mut table = [0u32; 256];
+        for (i, t) in table.iter_mut().enumerate() {
+            let mut c = i as u32;
+            for _ in 0..8 {
+                c = if c & 1 == 1 { (c >> 1) ^ poly } else { c >> 1 };
+            }
+            *t = c;
+        }
+        Crc { table: table, crc: !0u32 }
+    }
+
+    #[inline]
+    pub fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.crc = self.table[((self.crc ^ b as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    #[inline]
+    pub fn finish(&self) -> u32 {
+        !self.crc
+    }
+
+}
+
+/// Gets the CRC-32 of `data`
+#[inline]
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new(CRC32);
+    crc.update(data);
+    crc.finish()
+}
+
+/// Gets the CRC-32C of `data`
+#[inline]
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = Crc::new(CRC32C);
+    crc.update(data);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sha1, crc32, crc32c};
+
+    #[test]
+    fn check_values() {
+        // the usual "123456789" check values
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32c(b"123456789"), 0xe3069283);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(sha1(b"abc"), vec![0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e,
+                                      0x25, 0x71, 0x78, 0x50, 0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d]);
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Discovery of kinetic devices through their multicast announcements
+//!
+//! Devices periodically multicast a JSON document describing themselves. To try it
+//! without devices, send fake announcements to the group on the loopback interface
+//! and bind with `Discovery::bind(group, port, Ipv4Addr::new(127, 0, 0, 1))`.
+
+use std::{vec, io};
+use std::net::{UdpSocket, Ipv4Addr, SocketAddr};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use net2::UdpBuilder;
+use serde_json::{self, Value};
+use result::KineticResult;
+use error::KineticError;
+
+/// Multicast group devices announce themselves on
+pub static MULTICAST_GROUP: [u8; 4] = [239, 1, 2, 3];
+
+/// Port devices announce themselves on
+pub static MULTICAST_PORT: u16 = 8123;
+
+/// A network interface of a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    pub mac: String,
+    pub ipv4: String,
+    pub ipv6: String,
+}
+
+/// What a device says about itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct Announcement {
+    pub world_wide_name: String,
+    pub serial_number: String,
+    pub model: String,
+    pub manufacturer: String,
+    pub firmware_version: String,
+    pub protocol_version: String,
+    pub interfaces: vec::Vec<Interface>,
+    /// `None` when the announcement has no port
+    pub port: Option<u16>,
+    pub tls_port: Option<u16>,
+    /// Where the announcement came from
+    pub source: SocketAddr,
+}
+
+/// Most malformed announcements kept, the latest ones
+static MAX_MALFORMED: usize = 32;
+
+#[inline]
+fn string(o: &Value, field: &str) -> String {
+    o.get(field).and_then(|v| v.as_str()).unwrap_or("").to_string()
+}
+
+/// Gets a port, an error when it is not one rather than a truncated value
+#[inline]
+fn port(o: &Value, field: &str) -> KineticResult<Option<u16>> {
+    match o.get(field) {
+        None | Some(&Value::Null) => Ok(None),
+        Some(v) => match v.as_u64() {
+            Some(p) if p > 0 && p <= u16::max_value() as u64 => Ok(Some(p as u16)),
+            _ => Err(KineticError::InvalidData("announcement port is not a valid port")),
+        },
+    }
+}
+
+impl Announcement {
+
+    /// Parses an announcement received from `source`
+    pub fn from_json(data: &[u8], source: SocketAddr) -> KineticResult<Announcement> {
+        let s = try!(::std::str::from_utf8(data).map_err(|_| KineticError::InvalidData("announcement is not UTF-8")));
+        let o: Value = try!(serde_json::from_str(s).map_err(|_| KineticError::InvalidData("announcement is not JSON")));
+        if !o.is_object() {
+            return Err(KineticError::InvalidData("announcement is not a JSON object"));
+        }
+
+        let interfaces = o.get("network_interfaces").and_then(|v| v.as_array()).map_or(vec![], |is| {
+            is.iter().map(|i| Interface { name: string(i, "name"),
+                                          mac: string(i, "mac_addr"),
+                                          ipv4: string(i, "ipv4_addr"),
+                                          ipv6: string(i, "ipv6_addr") }).collect()
+        });
+
+        let a = Announcement { world_wide_name: string(&o, "world_wide_name"),
+                               serial_number: string(&o, "serial_number"),
+                               model: string(&o, "model"),
+                               manufacturer: string(&o, "manufacturer"),
+                               firmware_version: string(&o, "firmware_version"),
+                               protocol_version: string(&o, "protocol_version"),
+                               interfaces: interfaces,
+                               port: try!(port(&o, "port")),
+                               tls_port: try!(port(&o, "tlsPort")),
+                               source: source };
+
+        if a.world_wide_name.is_empty() && a.serial_number.is_empty() {
+            return Err(KineticError::InvalidData("announcement without WWN or serial number"));
+        }
+        Ok(a)
+    }
+
+    /// Identifies the device, its WWN or else its serial number
+    #[inline]
+    pub fn id(&self) -> &str {
+        if self.world_wide_name.is_empty() { &self.serial_number } else { &self.world_wide_name }
+    }
+
+    /// Gets the `host:port` address to connect to the device, `None` if it announced no port
+    #[inline]
+    pub fn address(&self) -> Option<String> {
+        self.port.map(|port| SocketAddr::new(self.source.ip(), port).to_string())
+    }
+
+}
+
+/// Also sets `SO_REUSEPORT`, which BSDs need to share a multicast port
+#[cfg(unix)]
+#[inline]
+fn reuse_port(builder: &UdpBuilder) -> io::Result<()> {
+    use net2::unix::UnixUdpBuilderExt;
+    builder.reuse_port(true).map(|_| ())
+}
+
+#[cfg(not(unix))]
+#[inline]
+fn reuse_port(_: &UdpBuilder) -> io::Result<()> {
+    Ok(())
+}
+
+/// Listens for device announcements
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+/// use kinetic::discovery::Discovery;
+///
+/// let mut d = Discovery::new().unwrap();
+/// for drive in d.listen(Duration::from_secs(10)).unwrap() {
+///     if let Some(address) = drive.address() {
+///         println!("{} at {}", drive.serial_number, address);
+///     }
+/// }
+/// ```
+pub struct Discovery {
+    socket: UdpSocket,
+    drives: HashMap<String, Announcement>,
+    malformed: vec::Vec<(SocketAddr, KineticError)>,
+    malformed_count: usize,
+}
+
+impl Discovery {
+
+    /// Joins the kinetic multicast group on every interface
+    #[inline]
+    pub fn new() -> KineticResult<Discovery> {
+        let g = MULTICAST_GROUP;
+        Discovery::bind(Ipv4Addr::new(g[0], g[1], g[2], g[3]), MULTICAST_PORT, Ipv4Addr::new(0, 0, 0, 0))
+    }
+
+    /// Joins the multicast `group` on `port`, through `interface`
+    ///
+    /// The port is shared, so other listeners on it (another discovery, a drive
+    /// simulator) do not make the bind fail.
+    pub fn bind(group: Ipv4Addr, port: u16, interface: Ipv4Addr) -> KineticResult<Discovery> {
+        let builder = try!(UdpBuilder::new_v4());
+        try!(builder.reuse_address(true));
+        try!(reuse_port(&builder));
+        let socket = try!(builder.bind(("0.0.0.0", port)));
+        try!(socket.join_multicast_v4(&group, &interface));
+        Ok(Discovery { socket: socket, drives: HashMap::new(), malformed: vec![], malformed_count: 0 })
+    }
+
+    /// Waits for the next announcement, `None` if `timeout` passes first
+    ///
+    /// Repeated announcements are returned too, malformed ones are skipped and the
+    /// latest of them kept in `malformed`.
+    pub fn next(&mut self, timeout: Option<Duration>) -> KineticResult<Option<Announcement>> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut buf = [0u8; 64 * 1024];
+
+        loop {
+            let wait = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline { return Ok(None); }
+                    Some(deadline - now)
+                },
+                None => None,
+            };
+            try!(self.socket.set_read_timeout(wait));
+
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, source)) => {
+                    match Announcement::from_json(&buf[..len], source) {
+                        Ok(a) => {
+                            self.drives.insert(a.id().to_string(), a.clone());
+                            return Ok(Some(a));
+                        },
+                        Err(e) => {
+                            if self.malformed.len() == MAX_MALFORMED {
+                                self.malformed.remove(0);
+                            }
+                            self.malformed.push((source, e));
+                            self.malformed_count += 1;
+                        },
+                    }
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock
+                           || e.kind() == io::ErrorKind::TimedOut => return Ok(None),
+                Err(e) => return Err(KineticError::IoError(e)),
+            }
+        }
+    }
+
+    /// Listens for `duration` and gets every device heard of, once each
+    pub fn listen(&mut self, duration: Duration) -> KineticResult<vec::Vec<Announcement>> {
+        let deadline = Instant::now() + duration;
+        loop {
+            let now = Instant::now();
+            if now >= deadline { break; }
+            if try!(self.next(Some(deadline - now))).is_none() { break; }
+        }
+        Ok(self.drives())
+    }
+
+    /// Gets every device heard of so far, once each, with its latest announcement
+    pub fn drives(&self) -> vec::Vec<Announcement> {
+        let mut drives: vec::Vec<Announcement> = self.drives.values().cloned().collect();
+        drives.sort_by(|a, b| a.id().cmp(b.id()));
+        drives
+    }
+
+    /// Gets the latest announcements that could not be parsed, with where they came from
+    #[inline]
+    pub fn malformed(&self) -> &[(SocketAddr, KineticError)] {
+        &self.malformed
+    }
+
+    /// Gets how many announcements could not be parsed so far
+    #[inline]
+    pub fn malformed_count(&self) -> usize {
+        self.malformed_count
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Discovery, Announcement, MAX_MALFORMED};
+    use std::net::{Ipv4Addr, UdpSocket, SocketAddr};
+    use std::time::Duration;
+    use net2::UdpSocketExt;
+
+    #[test]
+    fn shared_port_and_malformed() {
+        let group = Ipv4Addr::new(239, 1, 2, 3);
+        let mut first = Discovery::bind(group, 48123, Ipv4Addr::new(127, 0, 0, 1)).unwrap();
+        // unicast on a shared port may go to any listener, keep only the first
+        drop(Discovery::bind(group, 48123, Ipv4Addr::new(127, 0, 0, 1)).unwrap());
+
+        let s = UdpSocket::bind("127.0.0.1:0").unwrap();
+        s.send_to(b"not json", "127.0.0.1:48123").unwrap();
+        s.send_to(b"{\"serial_number\": \"S1\", \"port\": 8123}", "127.0.0.1:48123").unwrap();
+
+        let a = first.next(Some(Duration::from_secs(5))).unwrap().unwrap();
+        assert_eq!(a.serial_number, "S1");
+        assert_eq!(first.malformed().len(), 1);
+    }
+
+    #[test]
+    fn multicast_and_repeated_announcements() {
+        let group = Ipv4Addr::new(239, 1, 2, 4);
+        let mut d = Discovery::bind(group, 48124, Ipv4Addr::new(127, 0, 0, 1)).unwrap();
+
+        let s = UdpSocket::bind("127.0.0.1:0").unwrap();
+        s.set_multicast_if_v4(&Ipv4Addr::new(127, 0, 0, 1)).unwrap();
+        for announcement in [&b"{\"serial_number\": \"S1\", \"port\": 8123}"[..],
+                             &b"{\"world_wide_name\": \"W2\"}"[..],
+                             &b"{\"serial_number\": \"S1\", \"port\": 9123}"[..]].iter() {
+            s.send_to(announcement, (group, 48124)).unwrap();
+        }
+
+        let drives = d.listen(Duration::from_millis(500)).unwrap();
+        assert_eq!(drives.len(), 2);
+        assert_eq!((drives[0].id(), drives[0].port), ("S1", Some(9123)));
+        assert_eq!((drives[1].id(), drives[1].address()), ("W2", None));
+    }
+
+    #[test]
+    fn ports() {
+        let source: SocketAddr = "[fe80::1]:48123".parse().unwrap();
+        let a = Announcement::from_json(b"{\"serial_number\": \"S1\", \"port\": 8123, \"tlsPort\": 8443}", source).unwrap();
+        assert_eq!(a.address(), Some("[fe80::1]:8123".to_string()));
+        assert_eq!(a.tls_port, Some(8443));
+        assert!(Announcement::from_json(b"{\"serial_number\": \"S1\", \"port\": 73659}", source).is_err());
+        assert!(Announcement::from_json(b"{\"serial_number\": \"S1\", \"port\": \"8123\"}", source).is_err());
+    }
+
+    #[test]
+    fn malformed_is_bounded() {
+        let mut d = Discovery::bind(Ipv4Addr::new(239, 1, 2, 5), 48125, Ipv4Addr::new(127, 0, 0, 1)).unwrap();
+        let s = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..MAX_MALFORMED + 8 {
+            s.send_to(b"not json", "127.0.0.1:48125").unwrap();
+        }
+
+        d.listen(Duration::from_millis(500)).unwrap();
+        assert_eq!(d.malformed().len(), MAX_MALFORMED);
+        assert_eq!(d.malformed_count(), MAX_MALFORMED + 8);
+    }
+}
@@ -0,0 +1,712 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Erasure coded storage of values across kinetic devices
+
+use std::vec;
+use std::io::{Cursor, Read};
+use std::default::Default;
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use futures::Future;
+use cluster::ClusterClient;
+use commands::{Put, Get, Delete};
+use commands::common::{Versioning, Integrity};
+use proto::command::Algorithm;
+use proto::StatusCode;
+use result::KineticResult;
+use error::KineticError;
+use digest::sha1;
+
+/// Arithmetic on GF(2^8), using the 0x11d polynomial
+struct Galois {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Galois {
+
+    fn new() -> Galois {
+        let mut exp = [0u8; 512];
+        let mut log = [0u8; 256];
+
+        let mut x: u16 = 1;
+        for (i, e) in exp.iter_mut().take(255).enumerate() {
+            *e = x as u8;
+            log[x as usize] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 { x ^= 0x11d; }
+        }
+        for i in 255..512 {
+            exp[i] = exp[i - 255];
+        }
+
+        Galois { exp: exp, log: log }
+    }
+
+    #[inline]
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 { return 0; }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    #[inline]
+    fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 { return 0; }
+        self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+    }
+
+    #[inline]
+    fn pow(&self, a: u8, n: usize) -> u8 {
+        if n == 0 { return 1; }
+        if a == 0 { return 0; }
+        self.exp[(self.log[a as usize] as usize * n) % 255]
+    }
+
+    /// Inverts a square matrix, `None` if it's singular
+    fn invert(&self, matrix: &[vec::Vec<u8>]) -> Option<vec::Vec<vec::Vec<u8>>> {
+        let n = matrix.len();
+        let mut m: vec::Vec<vec::Vec<u8>> = matrix.to_vec();
+        let mut inv: vec::Vec<vec::Vec<u8>> = (0..n).map(|i| {
+            let mut r = vec![0u8; n];
+            r[i] = 1;
+            r
+        }).collect();
+
+        for col in 0..n {
+            let pivot = match (col..n).find(|&r| m[r][col] != 0) {
+                Some(r) => r,
+                None => return None,
+            };
+            m.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let p = m[col][col];
+            for j in 0..n {
+                m[col][j] = self.div(m[col][j], p);
+                inv[col][j] = self.div(inv[col][j], p);
+            }
+
+            for r in 0..n {
+                let f = m[r][col];
+                if r == col || f == 0 { continue; }
+                for j in 0..n {
+                    m[r][j] ^= self.mul(f, m[col][j]);
+                    inv[r][j] ^= self.mul(f, inv[col][j]);
+                }
+            }
+        }
+
+        Some(inv)
+    }
+
+    /// `out ^= c * data`, byte by byte
+    #[inline]
+    fn mul_add(&self, c: u8, data: &[u8], out: &mut [u8]) {
+        if c == 0 { return; }
+        for (o, d) in out.iter_mut().zip(data.iter()) {
+            *o ^= self.mul(c, *d);
+        }
+    }
+
+}
+
+/// A systematic Reed-Solomon `k+m` codec
+///
+/// A value is split into `k` data fragments and `m` parity fragments are computed from them.
+/// The value can be reconstructed from any `k` of the `k+m` fragments.
+pub struct ReedSolomon {
+    data: usize,
+    parity: usize,
+    gf: Galois,
+    // (k+m) x k, the top k rows are the identity
+    matrix: vec::Vec<vec::Vec<u8>>,
+}
+
+impl ReedSolomon {
+
+    /// Creates a codec with `data` data fragments and `parity` parity fragments
+    ///
+    /// # Panics
+    /// Panics if `data` is zero or above 255, or there are more than 256 fragments in total.
+    pub fn new(data: usize, parity: usize) -> ReedSolomon {
+        assert!(data > 0, "at least one data fragment is required");
+        // the metadata record stores each count in a byte
+        assert!(data <= 255, "at most 255 data fragments are supported");
+        assert!(data + parity <= 256, "at most 256 fragments are supported");
+
+        let gf = Galois::new();
+
+        // Any k rows of a Vandermonde matrix are independent, multiplying by the inverse
+        // of its top k rows keeps that property and makes the code systematic.
+        let vandermonde: vec::Vec<vec::Vec<u8>> = (0..data + parity)
+            .map(|i| (0..data).map(|j| gf.pow(i as u8, j)).collect())
+            .collect();
+        let top = gf.invert(&vandermonde[..data]).expect("vandermonde matrix is invertible");
+
+        let matrix = vandermonde.iter().map(|row| {
+            (0..data).map(|j| {
+                (0..data).fold(0u8, |acc, x| acc ^ gf.mul(row[x], top[x][j]))
+            }).collect()
+        }).collect();
+
+        ReedSolomon { data: data, parity: parity, gf: gf, matrix: matrix }
+    }
+
+    #[inline]
+    pub fn data_fragments(&self) -> usize { self.data }
+
+    #[inline]
+    pub fn parity_fragments(&self) -> usize { self.parity }
+
+    /// Gets the size of each fragment for a value of `len` bytes
+    #[inline]
+    pub fn fragment_size(&self, len: usize) -> usize {
+        ::std::cmp::max(1, (len + self.data - 1) / self.data)
+    }
+
+    /// Splits the value into `k+m` fragments of the same size, data fragments first
+    pub fn encode(&self, value: &[u8]) -> vec::Vec<vec::Vec<u8>> {
+        let size = self.fragment_size(value.len());
+
+        let mut fragments: vec::Vec<vec::Vec<u8>> = (0..self.data).map(|i| {
+            let start = ::std::cmp::min(i * size, value.len());
+            let end = ::std::cmp::min(start + size, value.len());
+            let mut f = value[start..end].to_vec();
+            f.resize(size, 0); // the last fragments are padded with zeros
+            f
+        }).collect();
+
+        for row in self.matrix[self.data..].iter() {
+            let mut parity = vec![0u8; size];
+            for (c, d) in row.iter().zip(fragments.iter()) {
+                self.gf.mul_add(*c, d, &mut parity);
+            }
+            fragments.push(parity);
+        }
+
+        fragments // return
+    }
+
+    /// Reconstructs a value of `len` bytes from the available fragments
+    ///
+    /// `fragments` holds all `k+m` fragments by index, `None` for the missing ones.
+    pub fn reconstruct(&self, fragments: &[Option<vec::Vec<u8>>], len: usize) -> KineticResult<vec::Vec<u8>> {
+        let available: vec::Vec<usize> = fragments.iter().enumerate()
+            .filter(|&(_, f)| f.is_some())
+            .map(|(i, _)| i)
+            .take(self.data)
+            .collect();
+
+        if available.len() < self.data {
+            return Err(KineticError::NotEnoughFragments { required: self.data, available: available.len() });
+        }
+
+        let size = self.fragment_size(len);
+        if available.iter().any(|&i| fragments[i].as_ref().unwrap().len() != size) {
+            return Err(KineticError::InvalidData("erasure fragment size mismatch"));
+        }
+
+        let mut value = vec::Vec::with_capacity(self.data * size);
+
+        if available.iter().enumerate().all(|(i, &f)| i == f) {
+            // all data fragments are there, nothing to decode
+            for f in fragments.iter().take(self.data) {
+                value.extend(f.as_ref().unwrap().iter().cloned());
+            }
+        } else {
+            let rows: vec::Vec<vec::Vec<u8>> = available.iter().map(|&i| self.matrix[i].clone()).collect();
+            let decode = match self.gf.invert(&rows) {
+                Some(m) => m,
+                None => return Err(KineticError::InvalidData("erasure fragments can't be decoded")),
+            };
+
+            for row in decode.iter() {
+                let mut data = vec![0u8; size];
+                for (c, &i) in row.iter().zip(available.iter()) {
+                    self.gf.mul_add(*c, fragments[i].as_ref().unwrap(), &mut data);
+                }
+                value.extend(data);
+            }
+        }
+
+        value.truncate(len);
+        Ok(value)
+    }
+
+}
+
+/// Magic number for erasure coding metadata records
+static METADATA_MAGIC: &'static [u8] = b"KEC1";
+
+/// The metadata record of an erasure coded value
+struct Metadata {
+    // the fragments of each write go under their own generation
+    generation: String,
+    len: u64,
+    data: u8,
+    parity: u8,
+    // where each fragment was stored
+    drives: vec::Vec<String>,
+}
+
+impl Metadata {
+
+    fn to_bytes(&self) -> vec::Vec<u8> {
+        let mut buf = METADATA_MAGIC.to_vec();
+        buf.write_u16::<BigEndian>(self.generation.len() as u16).unwrap();
+        buf.extend(self.generation.as_bytes().iter().cloned());
+        buf.write_u64::<BigEndian>(self.len).unwrap();
+        buf.write_u8(self.data).unwrap();
+        buf.write_u8(self.parity).unwrap();
+        for d in self.drives.iter() {
+            buf.write_u16::<BigEndian>(d.len() as u16).unwrap();
+            buf.extend(d.as_bytes().iter().cloned());
+        }
+        buf // return
+    }
+
+    fn from_bytes(bytes: &[u8]) -> KineticResult<Metadata> {
+        if bytes.len() < 16 || &bytes[..4] != METADATA_MAGIC {
+            return Err(KineticError::InvalidData("invalid erasure coding metadata"));
+        }
+
+        let mut r = Cursor::new(&bytes[4..]);
+        let generation = try!(read_string(&mut r));
+        let len = try!(r.read_u64::<BigEndian>().map_err(|_| KineticError::InvalidData("truncated erasure coding metadata")));
+        let data = try!(r.read_u8().map_err(|_| KineticError::InvalidData("truncated erasure coding metadata")));
+        let parity = try!(r.read_u8().map_err(|_| KineticError::InvalidData("truncated erasure coding metadata")));
+
+        let mut drives = vec::Vec::with_capacity(data as usize + parity as usize);
+        for _ in 0..(data as usize + parity as usize) {
+            drives.push(try!(read_string(&mut r)));
+        }
+
+        Ok(Metadata { generation: generation, len: len, data: data, parity: parity, drives: drives })
+    }
+
+}
+
+fn read_string(r: &mut Cursor<&[u8]>) -> KineticResult<String> {
+    let n = try!(r.read_u16::<BigEndian>().map_err(|_| KineticError::InvalidData("truncated erasure coding metadata")));
+    let mut s = vec![0u8; n as usize];
+    try!(r.read_exact(&mut s).map_err(|_| KineticError::InvalidData("truncated erasure coding metadata")));
+    String::from_utf8(s).map_err(|_| KineticError::InvalidData("invalid erasure coding metadata"))
+}
+
+/// Checks a fragment against its integrity tag, `None` if it doesn't match
+#[inline]
+fn verify_fragment(value: vec::Vec<u8>, integrity: &Integrity) -> Option<vec::Vec<u8>> {
+    if integrity.algorithm == Algorithm::SHA1 && integrity.tag == sha1(&value) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn is_not_found(e: &KineticError) -> bool {
+    match *e {
+        KineticError::RemoteError(ref s) => s.get_code() == StatusCode::NOT_FOUND,
+        _ => false,
+    }
+}
+
+/// A key/value store erasure coding each value across drives
+///
+/// Each value is split with a `ReedSolomon` codec into `k+m` fragments stored on distinct drives,
+/// so it survives the loss of any `m` of them for a storage overhead of `(k+m)/k`.
+///
+/// For a key `K` the fragments are stored as `K.ec.<generation>.<index>`, each one with a SHA1
+/// `Integrity` tag, and a small metadata record `K.ec.meta` listing the generation, the value length,
+/// the codec and the drive of each fragment is stored on `m+1` drives. Fragments failing their
+/// integrity check are treated as missing.
+///
+/// Every write stores its fragments under a new generation and the metadata record is written
+/// last, so readers only ever see the fragments of a single write. The previous generation is
+/// deleted once every copy of the metadata points to the new one. The metadata record is deleted
+/// first, an interrupted write or delete leaves unreferenced fragments behind but never a value
+/// that can't be read.
+///
+/// # Example
+/// ```no_run
+/// use kinetic::cluster::ClusterClient;
+/// use kinetic::erasure::ErasureStore;
+///
+/// let mut c = ClusterClient::new();
+/// for i in 1..7 {
+///     c.connect(format!("10.0.0.{}:8123", i).as_ref()).unwrap();
+/// }
+///
+/// let store = ErasureStore::new(c, 4, 2);
+/// store.put("cold".as_bytes(), &vec![0u8; 1024 * 1024]).unwrap();
+/// let v = store.get("cold".as_bytes()).unwrap();
+/// ```
+pub struct ErasureStore {
+    cluster: ClusterClient,
+    codec: ReedSolomon,
+}
+
+impl ErasureStore {
+
+    /// Creates a store splitting values in `data` fragments plus `parity` parity fragments
+    pub fn new(cluster: ClusterClient, data: usize, parity: usize) -> ErasureStore {
+        ErasureStore { cluster: cluster, codec: ReedSolomon::new(data, parity) }
+    }
+
+    /// Gets the underlying `ClusterClient`
+    #[inline]
+    pub fn get_cluster<'r>(&'r self) -> &'r ClusterClient {
+        &self.cluster
+    }
+
+    #[inline]
+    fn fragment_key(key: &[u8], generation: &str, index: usize) -> vec::Vec<u8> {
+        let mut k = key.to_vec();
+        k.extend(format!(".ec.{}.{}", generation, index).as_bytes().iter().cloned());
+        k
+    }
+
+    fn new_generation() -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        // two writers racing on the same key must never share a generation
+        format!("{:016x}{:08x}{:08x}", now.as_secs(), now.subsec_nanos(), ::rand::random::<u32>())
+    }
+
+    #[inline]
+    fn metadata_key(key: &[u8]) -> vec::Vec<u8> {
+        let mut k = key.to_vec();
+        k.extend(b".ec.meta".iter().cloned());
+        k
+    }
+
+    #[inline]
+    fn fragments(&self) -> usize {
+        self.codec.data_fragments() + self.codec.parity_fragments()
+    }
+
+    /// Stores the value as `k+m` fragments on distinct drives
+    ///
+    /// Requires at least `k+m` drives and every fragment to be written.
+    pub fn put(&self, key: &[u8], value: &[u8]) -> KineticResult<()> {
+        let drives = self.cluster.drive_names_for(key, self.fragments());
+        if drives.len() < self.fragments() {
+            return Err(KineticError::QuorumNotReached { required: self.fragments(), reached: drives.len() });
+        }
+
+        let previous = match self.get_metadata(key) {
+            Ok(m) => Some(m),
+            Err(ref e) if is_not_found(e) => None,
+            Err(e) => return Err(e),
+        };
+
+        let metadata = Metadata { generation: ErasureStore::new_generation(),
+                                  len: value.len() as u64,
+                                  data: self.codec.data_fragments() as u8,
+                                  parity: self.codec.parity_fragments() as u8,
+                                  drives: drives.iter().map(|d| d.to_string()).collect() };
+
+        let pending: vec::Vec<_> = self.codec.encode(value).into_iter().enumerate().map(|(i, f)| {
+            let c = self.cluster.get_drive(drives[i]).unwrap();
+            let tag = sha1(&f);
+            c.send_future(Put { key: ErasureStore::fragment_key(key, &metadata.generation, i),
+                                value: f,
+                                current_version: Versioning::Force,
+                                integrity: Some(Integrity { tag: tag, algorithm: Algorithm::SHA1 }),
+                                ..Default::default() })
+        }).collect();
+
+        let written = pending.into_iter().map(|f| f.wait()).filter(|r| r.is_ok()).count();
+        if written < self.fragments() {
+            self.delete_fragments(key, &metadata);
+            return Err(KineticError::QuorumNotReached { required: self.fragments(), reached: written });
+        }
+
+        // the value only becomes visible once the metadata is written
+        let copies = self.codec.parity_fragments() + 1;
+        let written = self.put_metadata(key, metadata.to_bytes());
+        if written == 0 {
+            // the writes may have landed with their responses lost, check before cleaning up
+            match self.get_metadata(key) {
+                Ok(ref m) if m.generation == metadata.generation => (),
+                _ => self.delete_fragments(key, &metadata),
+            }
+            return Err(KineticError::QuorumNotReached { required: 1, reached: 0 });
+        }
+
+        // a stale copy of the metadata may still point to the previous generation
+        if written == copies {
+            if let Some(previous) = previous {
+                self.delete_fragments(key, &previous);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the metadata record to its `m+1` drives, returns how many copies were written
+    fn put_metadata(&self, key: &[u8], metadata: vec::Vec<u8>) -> usize {
+        let meta_key = ErasureStore::metadata_key(key);
+        let copies = self.codec.parity_fragments() + 1;
+
+        let pending: vec::Vec<_> = self.cluster.drive_names_for(&meta_key, copies).iter()
+            .filter_map(|name| self.cluster.get_drive(name))
+            .map(|c| c.send_future(Put { key: meta_key.clone(),
+                                         value: metadata.clone(),
+                                         current_version: Versioning::Force,
+                                         ..Default::default() }))
+            .collect();
+
+        pending.into_iter().map(|f| f.wait()).filter(|r| r.is_ok()).count()
+    }
+
+    /// Deletes the fragments of a generation, best effort
+    fn delete_fragments(&self, key: &[u8], metadata: &Metadata) {
+        let pending: vec::Vec<_> = metadata.drives.iter().enumerate()
+            .filter_map(|(i, name)| self.cluster.get_drive(name).map(|c| (i, c)))
+            .map(|(i, c)| c.send_future(Delete::Forced { key: ErasureStore::fragment_key(key, &metadata.generation, i) }))
+            .collect();
+        for f in pending.into_iter() {
+            let _ = f.wait();
+        }
+    }
+
+    fn get_metadata(&self, key: &[u8]) -> KineticResult<Metadata> {
+        let meta_key = ErasureStore::metadata_key(key);
+        let copies = self.codec.parity_fragments() + 1;
+
+        let mut last_error = KineticError::EmptyCluster;
+        for name in self.cluster.drive_names_for(&meta_key, copies).iter() {
+            let c = self.cluster.get_drive(name).unwrap();
+            match c.send(Get { key: meta_key.clone() }) {
+                Ok(r) => return Metadata::from_bytes(&r.value),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Reconstructs the value from any `k` intact fragments
+    pub fn get(&self, key: &[u8]) -> KineticResult<vec::Vec<u8>> {
+        let metadata = try!(self.get_metadata(key));
+        if metadata.data as usize != self.codec.data_fragments()
+           || metadata.parity as usize != self.codec.parity_fragments() {
+            return Err(KineticError::InvalidData("value was erasure coded with a different codec"));
+        }
+
+        let pending: vec::Vec<_> = metadata.drives.iter().enumerate().map(|(i, name)| {
+            self.cluster.get_drive(name)
+                .map(|c| c.send_future(Get { key: ErasureStore::fragment_key(key, &metadata.generation, i) }))
+        }).collect();
+
+        let fragments: vec::Vec<Option<vec::Vec<u8>>> = pending.into_iter().map(|f| {
+            match f.map(|f| f.wait()) {
+                // a corrupt fragment is as good as a missing one
+                Some(Ok(r)) => verify_fragment(r.value, &r.integrity),
+                _ => None,
+            }
+        }).collect();
+
+        self.codec.reconstruct(&fragments, metadata.len as usize)
+    }
+
+    /// Deletes the value and all its fragments
+    ///
+    /// Fails, keeping the fragments, unless every copy of the metadata record is deleted,
+    /// so the value stays readable. Deleting again finishes the job.
+    pub fn delete(&self, key: &[u8]) -> KineticResult<()> {
+        let metadata = try!(self.get_metadata(key));
+
+        // metadata goes first, so the value is never half there
+        let meta_key = ErasureStore::metadata_key(key);
+        let copies = self.codec.parity_fragments() + 1;
+        let pending: vec::Vec<_> = self.cluster.drive_names_for(&meta_key, copies).iter()
+            .filter_map(|name| self.cluster.get_drive(name))
+            .map(|c| c.send_future(Delete::Forced { key: meta_key.clone() }))
+            .collect();
+
+        // a copy already gone is as good as deleted
+        let deleted = pending.into_iter().map(|f| f.wait())
+            .filter(|r| r.as_ref().err().map_or(true, is_not_found))
+            .count();
+
+        // a surviving copy still points to the fragments, keep them
+        if deleted < copies {
+            return Err(KineticError::QuorumNotReached { required: copies, reached: deleted });
+        }
+
+        self.delete_fragments(key, &metadata);
+        Ok(())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use super::{ReedSolomon, Metadata, ErasureStore, verify_fragment, is_not_found};
+    use commands::common::Integrity;
+    use cluster::ClusterClient;
+    use client::Client;
+    use proto::command::{Algorithm, MessageType};
+    use proto::StatusCode;
+    use digest::sha1;
+    use error::KineticError;
+    use testing::{Drive, Mode};
+
+    fn value(len: usize) -> vec::Vec<u8> {
+        (0..len).map(|i| (i * 31 + 7) as u8).collect()
+    }
+
+    /// Every way of picking `n` out of `0..total`
+    fn combinations(total: usize, n: usize) -> vec::Vec<vec::Vec<usize>> {
+        if n == 0 { return vec![vec![]]; }
+        if total < n { return vec![]; }
+        let mut with = combinations(total - 1, n - 1);
+        for c in with.iter_mut() { c.push(total - 1); }
+        with.extend(combinations(total - 1, n));
+        with
+    }
+
+    #[test]
+    fn reconstructs_after_losing_any_m_fragments() {
+        for &(k, m) in [(1, 1), (2, 1), (4, 2), (3, 3), (6, 3)].iter() {
+            let codec = ReedSolomon::new(k, m);
+            for &len in [0, 1, 5, 100, 1001].iter() {
+                let v = value(len);
+                let fragments = codec.encode(&v);
+                assert_eq!(fragments.len(), k + m);
+
+                for lost in combinations(k + m, m).iter() {
+                    let available: vec::Vec<Option<vec::Vec<u8>>> = fragments.iter().enumerate()
+                        .map(|(i, f)| if lost.contains(&i) { None } else { Some(f.clone()) })
+                        .collect();
+                    assert_eq!(codec.reconstruct(&available, len).unwrap(), v);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn needs_k_fragments() {
+        let codec = ReedSolomon::new(4, 2);
+        let v = value(64);
+        let mut fragments: vec::Vec<Option<vec::Vec<u8>>> = codec.encode(&v).into_iter().map(Some).collect();
+        fragments[0] = None;
+        fragments[3] = None;
+        fragments[5] = None;
+
+        match codec.reconstruct(&fragments, v.len()) {
+            Err(KineticError::NotEnoughFragments { required: 4, available: 3 }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn rejects_fragments_of_the_wrong_size() {
+        let codec = ReedSolomon::new(2, 1);
+        let v = value(10);
+        let mut fragments: vec::Vec<Option<vec::Vec<u8>>> = codec.encode(&v).into_iter().map(Some).collect();
+        fragments[1].as_mut().unwrap().push(0);
+        assert!(codec.reconstruct(&fragments, v.len()).is_err());
+
+        // also when decoding from parity
+        fragments[0] = None;
+        assert!(codec.reconstruct(&fragments, v.len()).is_err());
+    }
+
+    #[test]
+    fn corrupt_fragments_are_dropped() {
+        let codec = ReedSolomon::new(4, 2);
+        let v = value(333);
+        let fragments = codec.encode(&v);
+        let tags: vec::Vec<vec::Vec<u8>> = fragments.iter().map(|f| sha1(f)).collect();
+
+        let mut corrupted = fragments.clone();
+        corrupted[1][0] ^= 0x01;
+        corrupted[4][7] ^= 0x80;
+
+        let checked: vec::Vec<Option<vec::Vec<u8>>> = corrupted.into_iter().zip(tags.iter())
+            .map(|(f, t)| verify_fragment(f, &Integrity { tag: t.clone(), algorithm: Algorithm::SHA1 }))
+            .collect();
+        assert!(checked[1].is_none());
+        assert!(checked[4].is_none());
+        assert_eq!(checked.iter().filter(|f| f.is_some()).count(), 4);
+        assert_eq!(codec.reconstruct(&checked, v.len()).unwrap(), v);
+
+        // a fragment without a SHA1 tag can't be trusted either
+        let untagged = Integrity { tag: tags[0].clone(), algorithm: Algorithm::CRC32 };
+        assert!(verify_fragment(fragments[0].clone(), &untagged).is_none());
+    }
+
+    #[test]
+    fn metadata_roundtrip() {
+        let m = Metadata { generation: "000000005f5e10000000000deadbeef".to_string(),
+                           len: 1 << 40,
+                           data: 2,
+                           parity: 1,
+                           drives: vec!["a:8123".to_string(), "b:8123".to_string(), "c:8123".to_string()] };
+        let bytes = m.to_bytes();
+        let r = Metadata::from_bytes(&bytes).unwrap();
+        assert_eq!(r.generation, m.generation);
+        assert_eq!(r.len, m.len);
+        assert_eq!((r.data, r.parity), (2, 1));
+        assert_eq!(r.drives, m.drives);
+
+        assert!(Metadata::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Metadata::from_bytes(b"KOM1\0\0\0\0\0\0\0\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn data_fragments_fit_in_the_metadata() {
+        ReedSolomon::new(256, 0);
+    }
+
+    #[test]
+    fn delete_keeps_fragments_while_metadata_survives() {
+        let drives: vec::Vec<Drive> = (0..4).map(|_| Drive::start()).collect();
+        let mut c = ClusterClient::new();
+        for d in drives.iter() {
+            c.add_drive_with_weight(&d.address(), Client::new(d.address()).unwrap(), 1);
+        }
+        let store = ErasureStore::new(c, 2, 1);
+        let v = value(100);
+        store.put(b"hello", &v).unwrap();
+
+        // one of the 2 metadata copies can't be deleted
+        let meta_key = ErasureStore::metadata_key(b"hello");
+        let holder = store.get_cluster().drive_names_for(&meta_key, 2)[1].to_string();
+        let failing = drives.iter().find(|d| d.address() == holder).unwrap();
+        failing.set_mode(Mode::Fail(MessageType::DELETE, StatusCode::INTERNAL_ERROR));
+
+        match store.delete(b"hello") {
+            Err(KineticError::QuorumNotReached { required: 2, reached: 1 }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert_eq!(store.get(b"hello").unwrap(), v);
+
+        failing.set_mode(Mode::Serve);
+        store.delete(b"hello").unwrap();
+        assert!(is_not_found(&store.get(b"hello").unwrap_err()));
+        assert_eq!(drives.iter().map(|d| d.len()).sum::<usize>(), 0);
+    }
+}
@@ -26,6 +26,7 @@ use std::error::Error;
 use std::io;
 use protobuf::error::ProtobufError;
 use proto::command::Status;
+use channel::RequestKind;
 use std::fmt;
 //use core::convert::From;
 
@@ -35,44 +36,72 @@ pub enum KineticError {
     IoError(io::Error),
     ProtobufError(ProtobufError),
     InvalidMagicNumber,
-    RemoteError(Status)
+    RemoteError(Status),
+    /// The in-flight window for this kind of request is full
+    WindowFull(RequestKind),
+    /// The connection to the device was closed before the response arrived
+    ConnectionClosed,
+    /// There are no drives to place the key on
+    EmptyCluster,
+    /// Not enough drives acknowledged the operation
+    QuorumNotReached { required: usize, reached: usize },
+    /// Not enough intact fragments to reconstruct the value
+    NotEnoughFragments { required: usize, available: usize },
+    /// Data stored on the device is not in the expected format
+    InvalidData(&'static str),
+    /// A worker thread panicked before producing its result
+    Panicked,
+    /// The arguments or settings given to a tool are not valid
+    Usage(String),
 }
 
 impl fmt::Display for KineticError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        match *self {
+            KineticError::IoError(ref err) => write!(f, "{}", err),
+            KineticError::ProtobufError(ref err) => write!(f, "{}", err),
+            KineticError::RemoteError(ref status) => {
+                if status.get_statusMessage().is_empty() {
+                    write!(f, "{:?}", status.get_code())
+                } else {
+                    write!(f, "{:?}: {}", status.get_code(), status.get_statusMessage())
+                }
+            },
+            KineticError::WindowFull(kind) =>
+                write!(f, "Too many outstanding {:?} requests.", kind),
+            KineticError::QuorumNotReached { required, reached } =>
+                write!(f, "Only {} of the {} drives required acknowledged.", reached, required),
+            KineticError::NotEnoughFragments { required, available } =>
+                write!(f, "Only {} of the {} fragments required are intact.", available, required),
+            KineticError::InvalidData(msg) => write!(f, "{}", msg),
+            KineticError::Usage(ref msg) => write!(f, "{}", msg),
+            _ => write!(f, "{}", self.description()),
+        }
     }
 }
 
 impl Error for KineticError {
     fn description(&self) -> &str {
-        "Confused"
-//        match *self {
-//             KineticError::IoError(e) => e.description(),
-//             KineticError::ProtobufError(e) => e.description(),
-//             KineticError::InvalidMagicNumber => "Invalid magic number received.",
-//             KineticError::RemoteError(ref status) => {
-//                 let msg = status.get_statusMessage();
-//                 if msg.len() > 0 { msg }
-//                 else { "Kinetic remote error." }
-//             },
-//        }
+        match *self {
+            KineticError::IoError(_) => "I/O error.",
+            KineticError::ProtobufError(_) => "Invalid protobuf message.",
+            KineticError::InvalidMagicNumber => "Invalid magic number received.",
+            KineticError::RemoteError(ref status) => {
+                let msg = status.get_statusMessage();
+                if !msg.is_empty() { msg }
+                else { "Kinetic remote error." }
+            },
+            KineticError::WindowFull(_) => "Too many outstanding requests.",
+            KineticError::ConnectionClosed => "Connection to the device closed.",
+            KineticError::EmptyCluster => "There are no drives in the cluster.",
+            KineticError::QuorumNotReached { .. } => "Not enough drives acknowledged.",
+            KineticError::NotEnoughFragments { .. } => "Not enough intact fragments.",
+            KineticError::InvalidData(msg) => msg,
+            KineticError::Panicked => "A worker thread panicked.",
+            KineticError::Usage(ref msg) => msg,
+        }
     }
 
-//     fn detail(&self) -> Option<String> {
-//         match *self {
-//             KineticError::IoError(ref err) => Some(err.description().to_string()),
-//             KineticError::ProtobufError(ref err) => Some(err.description().to_string()),
-//             KineticError::RemoteError(ref status) => {
-//                 let x = format!("{}: {}", status.get_code(), status.get_statusMessage());
-//                 if status.has_detailedMessage() {
-//                     String::from_utf8(status.get_detailedMessage().to_vec()).ok() }
-//                 else { None }
-//             },
-//             _ => None,
-//         }
-//     }
-
     fn cause(&self) -> Option<&Error> {
         match *self {
             KineticError::IoError(ref err) => Some(err as &Error),
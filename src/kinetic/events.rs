@@ -0,0 +1,71 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Events reported by a kinetic device outside of a request/response exchange
+
+use proto::{Message, Command, StatusCode};
+use proto::command::Status;
+use std::vec;
+
+/// An event reported by the device, or about the connection to it
+///
+/// Most events come from _unsolicited status_ messages sent by the device,
+/// the status message is kept for reference.
+#[derive(Debug, Clone)]
+pub enum DeviceEvent {
+    /// The device is terminating the connection
+    ConnectionTerminated(Status),
+    /// The device is entering hibernation
+    Hibernate(Status),
+    /// The device is shutting down
+    Shutdown(Status),
+    /// The device cluster version is not the one the client is using,
+    /// also emitted when a `Client` refreshes its cluster version
+    ClusterVersionChanged { cluster_version: i64, status: Status },
+    /// Any other unsolicited status
+    Other(Status),
+    /// A response acknowledged a request that is not in flight, it was dropped
+    UnexpectedResponse { ack: i64 },
+    /// A message with an invalid authentication type was received, it was dropped
+    InvalidMessage,
+    /// The connection was closed, no more events will follow
+    Disconnected,
+}
+
+impl DeviceEvent {
+
+    /// Creates the event for an _unsolicited status_ message
+    pub fn from_proto(_: Message, mut cmd: Command, _: vec::Vec<u8>) -> DeviceEvent {
+        let cluster_version = cmd.get_header().get_clusterVersion();
+        let status = cmd.take_status();
+
+        match status.get_code() {
+            StatusCode::CONNECTION_TERMINATED => DeviceEvent::ConnectionTerminated(status),
+            StatusCode::HIBERNATE => DeviceEvent::Hibernate(status),
+            StatusCode::SHUTDOWN => DeviceEvent::Shutdown(status),
+            StatusCode::VERSION_FAILURE =>
+                DeviceEvent::ClusterVersionChanged { cluster_version: cluster_version, status: status },
+            _ => DeviceEvent::Other(status),
+        }
+    }
+
+}
@@ -0,0 +1,83 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+use std::{mem, marker};
+use futures::{Future, Poll, Async};
+use futures::sync::oneshot;
+use core::Response;
+use result::KineticResult;
+use error::KineticError;
+use channel::Result;
+
+enum State {
+    Waiting(oneshot::Receiver<KineticResult<Result>>),
+    Failed(KineticError),
+    Done,
+}
+
+/// A `Future` for the `Response` of a command sent with `Client::send_future`
+///
+/// It is resolved by the channel reader thread when the response arrives.
+#[must_use = "futures do nothing unless polled"]
+pub struct ResponseFuture<R> {
+    state: State,
+    response_type: marker::PhantomData<R>,
+}
+
+impl<R: Response> ResponseFuture<R> {
+
+    #[inline]
+    pub fn new(rx: oneshot::Receiver<KineticResult<Result>>) -> ResponseFuture<R> {
+        ResponseFuture { state: State::Waiting(rx), response_type: marker::PhantomData }
+    }
+
+    /// A future that fails with `err` as soon as it is polled
+    #[inline]
+    pub fn failed(err: KineticError) -> ResponseFuture<R> {
+        ResponseFuture { state: State::Failed(err), response_type: marker::PhantomData }
+    }
+
+}
+
+impl<R: Response> Future for ResponseFuture<R> {
+    type Item = R;
+    type Error = KineticError;
+
+    fn poll(&mut self) -> Poll<R, KineticError> {
+        match mem::replace(&mut self.state, State::Done) {
+            State::Waiting(mut rx) => match rx.poll() {
+                Ok(Async::NotReady) => {
+                    self.state = State::Waiting(rx);
+                    Ok(Async::NotReady)
+                },
+                Ok(Async::Ready(Ok((msg, cmd, value)))) =>
+                    Response::from_proto(msg, cmd, value).map(Async::Ready),
+                Ok(Async::Ready(Err(e))) => Err(e),
+                // the completion was dropped without being invoked
+                Err(oneshot::Canceled) => Err(KineticError::ConnectionClosed),
+            },
+            State::Failed(e) => Err(e),
+            State::Done => panic!("ResponseFuture polled after completion"),
+        }
+    }
+
+}
@@ -23,14 +23,22 @@
 #![crate_type = "lib"]
 #![crate_name = "kinetic"]
 
-#![feature(std_misc)] // needed for Futures
-#![feature(tcp)] // needed for TCP_NODELAY
+// the code base keeps the idioms of the 2015 edition: try!, bare trait objects, ...
+#![allow(deprecated, bare_trait_objects, anonymous_parameters, ellipsis_inclusive_range_patterns)]
+#![allow(clippy::redundant_field_names, clippy::redundant_static_lifetimes, clippy::needless_lifetimes)]
+#![allow(clippy::question_mark, clippy::legacy_numeric_constants, clippy::needless_borrowed_reference, clippy::manual_range_contains)]
+#![allow(clippy::unnecessary_map_or, clippy::manual_is_multiple_of, clippy::manual_div_ceil, clippy::io_other_error)]
+
 
 //! Kinetic protocol library in Rust
 
 extern crate protobuf;
 extern crate crypto;
 extern crate byteorder;
+extern crate futures;
+extern crate rand;
+extern crate serde_json;
+extern crate net2;
 
 pub use core::version;
 pub use proto::version as protocol_version;
@@ -39,6 +47,8 @@ pub use result::KineticResult;
 pub use error::KineticError;
 pub use client::Client;
 pub use client::AsyncClient;
+pub use client::PooledClient;
+pub use future::ResponseFuture;
 
 pub mod commands;
 pub mod responses;
@@ -46,8 +56,25 @@ pub mod error;
 pub mod result;
 pub mod proto;
 pub mod channel;
+pub mod pipeline;
+pub mod events;
+pub mod cluster;
+pub mod replication;
+pub mod erasure;
+pub mod objects;
+pub mod pool;
+pub mod namespace;
+pub mod tuple;
+pub mod versions;
+pub mod discovery;
+pub mod archive;
+pub mod digest;
 pub mod authentication;
 
 mod client;
+mod future;
 mod core;
 mod network;
+
+#[cfg(test)]
+mod testing;
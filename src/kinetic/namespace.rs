@@ -0,0 +1,224 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Key namespaces, sharing a device between tenants or applications
+
+use std::vec;
+use client::Client;
+use channel::KineticChannel;
+use commands::{KeyCommand, Get, GetKeyRange, GetNext, GetPrevious};
+use core::Response;
+use responses::{GetKeyRangeResponse, GetNextResponse, GetPreviousResponse};
+use result::KineticResult;
+use error::KineticError;
+
+/// `maxKeySize` of the Kinetic specification, used when the device does not advertise one
+static DEFAULT_MAX_KEY_SIZE: usize = 4096;
+
+/// Gets the end of the range of keys starting with `prefix`
+///
+/// # Returns
+/// Returns the end key and whether it is inclusive. That is the key right after all the keys
+/// starting with `prefix`, or the largest key of `max_key_size` bytes (0 when not advertised)
+/// when `prefix` is empty or all 0xff and no such key exists.
+pub fn prefix_end(prefix: &[u8], max_key_size: usize) -> (vec::Vec<u8>, bool) {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return (end, false);
+        }
+    }
+
+    let max = if max_key_size > 0 { max_key_size } else { DEFAULT_MAX_KEY_SIZE };
+    (vec![0xff; ::std::cmp::max(max, prefix.len())], true)
+}
+
+#[inline]
+fn not_found() -> KineticError {
+    let mut status = ::proto::command::Status::new();
+    status.set_code(::proto::StatusCode::NOT_FOUND);
+    status.set_statusMessage("key outside of the namespace".to_string());
+    KineticError::RemoteError(status)
+}
+
+/// A view of a `Client` restricted to the keys starting with a prefix
+///
+/// Keys are given and returned without the prefix. Range and iteration commands
+/// never go past the namespace, as if the device only contained its keys.
+///
+/// The prefix counts against the device `maxKeySize`, see `get_max_key_size`.
+///
+/// A namespace is only a convention of the client. To enforce it, connect with an
+/// identity whose ACL scopes are limited to the same prefix.
+///
+/// # Example
+/// ```no_run
+/// use kinetic::Client;
+/// use kinetic::commands::Get;
+///
+/// let c = Client::new("127.0.0.1:8123").unwrap();
+/// let ns = c.namespace("tenant-a/".as_bytes().to_vec());
+/// // reads "tenant-a/hello"
+/// let v = ns.send(Get { key: "hello".as_bytes().to_vec() }).unwrap();
+/// ```
+pub struct Namespace<'a, Ch: KineticChannel<T> + 'a, T: 'a> {
+    client: &'a Client<Ch, T>,
+    prefix: vec::Vec<u8>,
+}
+
+impl<'a, Ch: KineticChannel<T>, T> Namespace<'a, Ch, T> {
+
+    /// Creates a namespace over `client` for the keys starting with `prefix`
+    #[inline]
+    pub fn new(client: &'a Client<Ch, T>, prefix: vec::Vec<u8>) -> Namespace<'a, Ch, T> {
+        Namespace { client: client, prefix: prefix }
+    }
+
+    #[inline]
+    pub fn get_prefix<'r>(&'r self) -> &'r [u8] {
+        &self.prefix
+    }
+
+    #[inline]
+    pub fn get_client(&self) -> &'a Client<Ch, T> {
+        self.client
+    }
+
+    /// Gets the longest key allowed in this namespace, that is the device
+    /// `maxKeySize` minus the prefix length
+    ///
+    /// Keys are unlimited (`usize::MAX`) when the device does not advertise a `maxKeySize`.
+    #[inline]
+    pub fn get_max_key_size(&self) -> usize {
+        let max = self.client.get_limits().get_maxKeySize() as usize;
+        if max == 0 { ::std::usize::MAX }
+        else if max > self.prefix.len() { max - self.prefix.len() }
+        else { 0 }
+    }
+
+    #[inline]
+    fn end(&self) -> (vec::Vec<u8>, bool) {
+        prefix_end(&self.prefix, self.client.get_limits().get_maxKeySize() as usize)
+    }
+
+    #[inline]
+    fn prefixed(&self, key: &[u8]) -> KineticResult<vec::Vec<u8>> {
+        if key.len() > self.get_max_key_size() {
+            return Err(KineticError::InvalidData("key exceeds the namespace maxKeySize"));
+        }
+
+        let mut k = self.prefix.clone();
+        k.extend(key.iter().cloned());
+        Ok(k)
+    }
+
+    #[inline]
+    fn strip(&self, key: vec::Vec<u8>) -> Option<vec::Vec<u8>> {
+        if key.starts_with(&self.prefix) { Some(key[self.prefix.len()..].to_vec()) }
+        else { None }
+    }
+
+    /// Sends a keyed command (`Put`, `Get`, `GetVersion` or `Delete`) with its key prefixed
+    pub fn send<C: KeyCommand<R>, R: Response>(&self, mut cmd: C) -> KineticResult<R> {
+        let key = try!(self.prefixed(cmd.key()));
+        *cmd.key_mut() = key;
+        self.client.send(cmd)
+    }
+
+    /// Gets the keys of the namespace between `start` and `end`
+    ///
+    /// An empty `end` stands for the end of the namespace.
+    pub fn get_key_range(&self, cmd: GetKeyRange) -> KineticResult<GetKeyRangeResponse> {
+        let start = try!(self.prefixed(&cmd.start));
+        let (end, end_inclusive) = if cmd.end.is_empty() { self.end() }
+                                   else { (try!(self.prefixed(&cmd.end)), cmd.end_inclusive) };
+
+        let r = try!(self.client.send(GetKeyRange { start: start,
+                                                    end: end,
+                                                    start_inclusive: cmd.start_inclusive,
+                                                    end_inclusive: end_inclusive,
+                                                    max_returned: cmd.max_returned,
+                                                    reverse: cmd.reverse }));
+
+        Ok(GetKeyRangeResponse { keys: r.keys.into_iter().filter_map(|k| self.strip(k)).collect() })
+    }
+
+    /// Gets the key next to `key` in the namespace with a single key range
+    fn neighbour(&self, key: &[u8], reverse: bool) -> KineticResult<vec::Vec<u8>> {
+        let key = try!(self.prefixed(key));
+        let (start, start_inclusive, end, end_inclusive) =
+            if reverse { (self.prefix.clone(), true, key, false) }
+            else { let (end, inclusive) = self.end(); (key, false, end, inclusive) };
+
+        // the range never leaves the namespace, so other tenants' values are never read
+        let r = try!(self.client.send(GetKeyRange { start: start,
+                                                    end: end,
+                                                    start_inclusive: start_inclusive,
+                                                    end_inclusive: end_inclusive,
+                                                    max_returned: 1,
+                                                    reverse: reverse }));
+        r.keys.into_iter().next().ok_or_else(not_found)
+    }
+
+    /// Gets the entry after `key` in the namespace, `NOT_FOUND` past its last key
+    pub fn get_next(&self, cmd: GetNext) -> KineticResult<GetNextResponse> {
+        let key = try!(self.neighbour(&cmd.key, false));
+        let r = try!(self.client.send(Get { key: key.clone() }));
+
+        Ok(GetNextResponse { key: self.strip(key).unwrap(),
+                             value: r.value,
+                             version: r.version,
+                             integrity: r.integrity })
+    }
+
+    /// Gets the entry before `key` in the namespace, `NOT_FOUND` before its first key
+    pub fn get_previous(&self, cmd: GetPrevious) -> KineticResult<GetPreviousResponse> {
+        let key = try!(self.neighbour(&cmd.key, true));
+        let r = try!(self.client.send(Get { key: key.clone() }));
+
+        Ok(GetPreviousResponse { key: self.strip(key).unwrap(),
+                                 value: r.value,
+                                 version: r.version,
+                                 integrity: r.integrity })
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prefix_end;
+
+    #[test]
+    fn prefix_end_is_exclusive_when_it_can_be() {
+        assert_eq!(prefix_end(b"tenant-a/", 4096), (b"tenant-a0".to_vec(), false));
+        assert_eq!(prefix_end(&[0x01, 0xff, 0xff], 4096), (vec![0x02], false));
+    }
+
+    #[test]
+    fn prefix_end_covers_every_key_under_an_all_0xff_prefix() {
+        assert_eq!(prefix_end(&[0xff, 0xff], 8), (vec![0xff; 8], true));
+        assert_eq!(prefix_end(b"", 16), (vec![0xff; 16], true));
+        // maxKeySize not advertised
+        assert_eq!(prefix_end(b"", 0), (vec![0xff; 4096], true));
+    }
+}
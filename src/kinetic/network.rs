@@ -29,6 +29,20 @@ use byteorder::{ByteOrder, BigEndian, WriteBytesExt};
 
 #[inline]
 pub fn recv(stream: &mut Read) -> KineticResult<(::proto::Message, ::proto::Command, ::std::vec::Vec<u8>)> {
+    let (msg, cmd, value_length) = try!(recv_frame(stream));
+
+    let value = if value_length == 0 { vec![] }
+                else { try!(read_exact(stream, value_length)) };
+
+    Ok((msg, cmd, value))
+}
+
+/// Receives a message, leaving its value on the stream
+///
+/// # Returns
+/// Returns the message, its command and the length of the value that follows.
+#[inline]
+pub fn recv_frame(stream: &mut Read) -> KineticResult<(::proto::Message, ::proto::Command, usize)> {
     let mut header = [0u8;9];
     try!(read_exact_into(stream, 9, &mut header));
 
@@ -40,31 +54,69 @@ pub fn recv(stream: &mut Read) -> KineticResult<(::proto::Message, ::proto::Comm
 
     let proto_vec = try!(read_exact(stream, proto_length));
 
-    let value = if value_length == 0 { vec![] }
-                else { try!(read_exact(stream, value_length)) };
-
     let msg = try!(parse_from_bytes::<::proto::Message>(proto_vec.as_ref()));
     let cmd = try!(parse_from_bytes::<::proto::Command>(msg.get_commandBytes()));
 
-    Ok((msg, cmd, value))
+    Ok((msg, cmd, value_length))
+}
+
+/// Receives the `len` bytes of a value left on the stream by `recv_frame`
+#[inline]
+pub fn recv_value(stream: &mut Read, len: usize) -> io::Result<Vec<u8>> {
+    if len == 0 { Ok(vec![]) } else { read_exact(stream, len) }
+}
+
+/// Copies the `len` bytes of a value left on the stream by `recv_frame` into `sink`
+///
+/// The whole value is read from the stream even if `sink` fails, so the stream is left
+/// at the next message.
+///
+/// # Returns
+/// Returns an `Err(...)` if the stream failed, otherwise the result of writing to `sink`.
+pub fn recv_value_into(stream: &mut Read, len: usize, sink: &mut Write) -> io::Result<io::Result<()>> {
+    let mut buff = vec![0u8; ::std::cmp::min(len, 64 * 1024)];
+    let mut left = len;
+    let mut sink_result = Ok(());
+
+    while left > 0 {
+        let max = ::std::cmp::min(left, buff.len());
+        let n = try!(stream.read(buff[..max].as_mut()));
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended in the middle of a value"));
+        }
+        if sink_result.is_ok() {
+            sink_result = sink.write_all(&buff[..n]);
+        }
+        left -= n;
+    }
+
+    Ok(sink_result)
 }
 
 #[inline]
 pub fn send(stream: &mut Write, proto: &::proto::Message, value: &[u8]) -> KineticResult<()> {
+    try!(send_header(stream, proto, value.len()));
+
+    if !value.is_empty() {
+        try!(stream.write_all(value));
+        try!(stream.flush());
+    }
+
+    Ok(())
+}
+
+/// Sends a message announcing a value of `value_len` bytes, the value must be written next
+#[inline]
+pub fn send_header(stream: &mut Write, proto: &::proto::Message, value_len: usize) -> KineticResult<()> {
     let s = proto.compute_size();
 
     let mut hw = BufWriter::with_capacity(9 + s as usize, stream);
     try!(hw.write_u8(70u8)); // Magic number
     try!(hw.write_i32::<BigEndian>(s as i32));
-    try!(hw.write_i32::<BigEndian>(value.len() as i32));
+    try!(hw.write_i32::<BigEndian>(value_len as i32));
     try!(proto.write_to_writer(&mut hw));
 
-    let mut stream = try!(hw.into_inner());
-
-    if value.len() > 0 {
-        try!(stream.write(value));
-        try!(stream.flush());
-    }
+    try!(hw.into_inner());
 
     Ok(())
 }
@@ -75,6 +127,9 @@ fn read_exact_into(reader: &mut Read, nbytes: usize, buff: &mut[u8]) -> io::Resu
 
     while bytes_read < nbytes {
         let ln = try!(reader.read(buff[bytes_read..].as_mut()));
+        if ln == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended in the middle of a message"));
+        }
         bytes_read += ln;
     }
 
@@ -85,5 +140,8 @@ fn read_exact_into(reader: &mut Read, nbytes: usize, buff: &mut[u8]) -> io::Resu
 fn read_exact(reader: &mut Read, n: usize) -> io::Result<Vec<u8>> {
     let mut buf = vec![];
     try!(io::copy(&mut reader.take(n as u64), &mut buf));
+    if buf.len() < n {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended in the middle of a message"));
+    }
     Ok(buf)
 }
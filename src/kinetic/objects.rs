@@ -0,0 +1,485 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Objects larger than the device `maxValueSize`, stored as chunks plus a manifest
+
+use std::{vec, io, cmp};
+use std::io::{Read, Cursor};
+use std::collections::VecDeque;
+use std::default::Default;
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use futures::Future;
+use client::AsyncClient;
+use commands::{Put, Get, Delete, GetKeyRange};
+use commands::common::{Versioning, Integrity};
+use proto::command::Algorithm;
+use proto::StatusCode;
+use responses::GetResponse;
+use future::ResponseFuture;
+use result::KineticResult;
+use error::KineticError;
+use namespace::prefix_end;
+use digest::sha1;
+
+/// Chunk size used when the device does not advertise a `maxValueSize` (1 MiB)
+static DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Number of chunk writes kept in flight while storing an object
+static CHUNKS_IN_FLIGHT: usize = 4;
+
+/// Magic number for manifests
+static MANIFEST_MAGIC: &'static [u8] = b"KOM1";
+
+/// Size of the smallest chunk record in a manifest: key length, size and tag length
+static MIN_CHUNK_RECORD: u64 = 2 + 4 + 1;
+
+/// Length of a chunk key after the chunks prefix, `<generation:24 hex>.<index:10 digits>`
+static CHUNK_SUFFIX_LEN: usize = 24 + 1 + 10;
+
+/// A chunk of an object
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    /// Key the chunk is stored under
+    pub key: vec::Vec<u8>,
+    /// Size of the chunk in bytes
+    pub size: u32,
+    /// SHA1 of the chunk
+    pub tag: vec::Vec<u8>,
+}
+
+/// The manifest of an object, stored under the object key
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    /// Size of the object in bytes
+    pub len: u64,
+    /// Chunks of the object, in order
+    pub chunks: vec::Vec<Chunk>,
+}
+
+impl Manifest {
+
+    pub fn to_bytes(&self) -> vec::Vec<u8> {
+        let mut buf = MANIFEST_MAGIC.to_vec();
+        buf.write_u64::<BigEndian>(self.len).unwrap();
+        buf.write_u32::<BigEndian>(self.chunks.len() as u32).unwrap();
+        for c in self.chunks.iter() {
+            buf.write_u16::<BigEndian>(c.key.len() as u16).unwrap();
+            buf.extend(c.key.iter().cloned());
+            buf.write_u32::<BigEndian>(c.size).unwrap();
+            buf.write_u8(c.tag.len() as u8).unwrap();
+            buf.extend(c.tag.iter().cloned());
+        }
+        buf // return
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> KineticResult<Manifest> {
+        if bytes.len() < 16 || &bytes[..4] != MANIFEST_MAGIC {
+            return Err(KineticError::InvalidData("not an object manifest"));
+        }
+
+        let mut r = Cursor::new(&bytes[4..]);
+        Manifest::read(&mut r).map_err(|_| KineticError::InvalidData("truncated object manifest"))
+    }
+
+    fn read(r: &mut Cursor<&[u8]>) -> io::Result<Manifest> {
+        let len = try!(r.read_u64::<BigEndian>());
+        let count = try!(r.read_u32::<BigEndian>());
+
+        // don't trust the count with an allocation before it's known to fit
+        let left = r.get_ref().len() as u64 - r.position();
+        if count as u64 * MIN_CHUNK_RECORD > left {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "more chunks than the manifest holds"));
+        }
+
+        let mut chunks = vec::Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let key_len = try!(r.read_u16::<BigEndian>());
+            let mut key = vec![0u8; key_len as usize];
+            try!(r.read_exact(&mut key));
+            let size = try!(r.read_u32::<BigEndian>());
+            let tag_len = try!(r.read_u8());
+            let mut tag = vec![0u8; tag_len as usize];
+            try!(r.read_exact(&mut tag));
+            chunks.push(Chunk { key: key, size: size, tag: tag });
+        }
+
+        Ok(Manifest { len: len, chunks: chunks })
+    }
+
+}
+
+/// Checks whether `key` is a chunk key under `prefix`
+///
+/// Other objects may be named with the same prefix, their manifests and chunks
+/// never have the exact shape of a chunk key.
+fn is_chunk_key(prefix: &[u8], key: &[u8]) -> bool {
+    if key.len() != prefix.len() + CHUNK_SUFFIX_LEN || !key.starts_with(prefix) { return false; }
+
+    let suffix = &key[prefix.len()..];
+    suffix[..24].iter().all(|&b| (b >= b'0' && b <= b'9') || (b >= b'a' && b <= b'f'))
+        && suffix[24] == b'.'
+        && suffix[25..].iter().all(|&b| b >= b'0' && b <= b'9')
+}
+
+/// Fills `buf` from `reader`, short only at the end of the stream
+fn read_full(reader: &mut Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(r) => n += r,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+/// Stores objects of any size on a single device
+///
+/// An object is split into chunks of at most `maxValueSize` bytes, stored under
+/// `<name>.chunks.<generation>.<index>`, and a manifest listing the chunk keys, sizes
+/// and SHA1 tags is stored under `<name>`.
+///
+/// The manifest is only written once all the chunks are, and it's the first thing removed
+/// on delete, so an interrupted write or delete never leaves a partial object visible.
+/// Overwriting an object writes a new generation of chunks, the old ones are removed after
+/// the new manifest is in place. Chunks left behind by an interruption can be removed with
+/// `collect_garbage`, which only ever touches keys shaped exactly like the chunk keys of the
+/// object, never the manifest or chunks of an object named `<name>.chunks.<anything>`.
+///
+/// # Example
+/// ```no_run
+/// use kinetic::Client;
+/// use kinetic::objects::ObjectStore;
+/// use std::fs::File;
+/// use std::io;
+///
+/// let c = Client::new("127.0.0.1:8123").unwrap();
+/// let store = ObjectStore::new(&c);
+///
+/// let mut f = File::open("backup.tar").unwrap();
+/// store.put("backup.tar".as_bytes(), &mut f).unwrap();
+///
+/// let mut r = store.get("backup.tar".as_bytes()).unwrap();
+/// io::copy(&mut r, &mut io::sink()).unwrap();
+/// ```
+pub struct ObjectStore<'a> {
+    client: &'a AsyncClient,
+    chunk_size: usize,
+}
+
+impl<'a> ObjectStore<'a> {
+
+    /// Creates a store with chunks as big as the device `maxValueSize`
+    pub fn new(client: &'a AsyncClient) -> ObjectStore<'a> {
+        let max = client.get_limits().get_maxValueSize() as usize;
+        ObjectStore::new_with_chunk_size(client, if max > 0 { max } else { DEFAULT_CHUNK_SIZE })
+    }
+
+    /// Creates a store with the given chunk size
+    pub fn new_with_chunk_size(client: &'a AsyncClient, chunk_size: usize) -> ObjectStore<'a> {
+        ObjectStore { client: client, chunk_size: cmp::max(1, chunk_size) }
+    }
+
+    #[inline]
+    fn chunks_prefix(name: &[u8]) -> vec::Vec<u8> {
+        let mut k = name.to_vec();
+        k.extend(b".chunks.".iter().cloned());
+        k
+    }
+
+    /// Reads the manifest of an object
+    pub fn get_manifest(&self, name: &[u8]) -> KineticResult<Manifest> {
+        let r = try!(self.client.send(Get { key: name.to_vec() }));
+        Manifest::from_bytes(&r.value)
+    }
+
+    /// Stores the object read from `reader` until the end of the stream
+    ///
+    /// # Returns
+    /// Returns the `Manifest` of the stored object.
+    pub fn put(&self, name: &[u8], reader: &mut Read) -> KineticResult<Manifest> {
+        let previous = match self.get_manifest(name) {
+            Ok(m) => Some(m),
+            Err(KineticError::RemoteError(ref s)) if s.get_code() == StatusCode::NOT_FOUND => None,
+            Err(e) => return Err(e),
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut prefix = ObjectStore::chunks_prefix(name);
+        prefix.extend(format!("{:016x}{:08x}.", now.as_secs(), now.subsec_nanos()).as_bytes().iter().cloned());
+
+        let mut manifest = Manifest { len: 0, chunks: vec::Vec::new() };
+        let mut in_flight: VecDeque<ResponseFuture<()>> = VecDeque::new();
+
+        let r = self.put_chunks(&prefix, reader, &mut manifest, &mut in_flight);
+        let r = r.and_then(|_| {
+            // wait for the last chunks, all of them even after a failure
+            let mut r = Ok(());
+            for f in in_flight.drain(..) {
+                r = r.and(f.wait());
+            }
+            r
+        });
+
+        // the object only becomes visible once the manifest is written
+        let r = r.and_then(|_| self.client.send(Put { key: name.to_vec(),
+                                                      value: manifest.to_bytes(),
+                                                      current_version: Versioning::Force,
+                                                      ..Default::default() }));
+
+        match r {
+            Ok(()) => {
+                if let Some(previous) = previous {
+                    self.delete_chunks(&previous);
+                }
+                Ok(manifest)
+            },
+            Err(e) => {
+                for f in in_flight.drain(..) { let _ = f.wait(); }
+
+                // the manifest may have been written with its response lost, check before cleaning up
+                match self.get_manifest(name) {
+                    Ok(ref m) if m.to_bytes() == manifest.to_bytes() => {
+                        if let Some(previous) = previous {
+                            self.delete_chunks(&previous);
+                        }
+                        return Ok(manifest);
+                    },
+                    // best effort, whatever is left is picked up by `collect_garbage`
+                    Ok(_) => self.delete_chunks(&manifest),
+                    Err(KineticError::RemoteError(ref s)) if s.get_code() == StatusCode::NOT_FOUND =>
+                        self.delete_chunks(&manifest),
+                    // can't tell, leave the chunks to `collect_garbage`
+                    Err(_) => (),
+                }
+                Err(e)
+            },
+        }
+    }
+
+    fn put_chunks(&self, prefix: &[u8], reader: &mut Read, manifest: &mut Manifest,
+                  in_flight: &mut VecDeque<ResponseFuture<()>>) -> KineticResult<()> {
+        let mut buf = vec![0u8; self.chunk_size];
+        loop {
+            let n = try!(read_full(reader, &mut buf));
+            if n == 0 { return Ok(()); }
+
+            let chunk = buf[..n].to_vec();
+            let mut key = prefix.to_vec();
+            key.extend(format!("{:010}", manifest.chunks.len()).as_bytes().iter().cloned());
+            let tag = sha1(&chunk);
+
+            manifest.len += n as u64;
+            manifest.chunks.push(Chunk { key: key.clone(), size: n as u32, tag: tag.clone() });
+
+            if in_flight.len() >= CHUNKS_IN_FLIGHT {
+                try!(in_flight.pop_front().unwrap().wait());
+            }
+            in_flight.push_back(self.client.send_future(Put {
+                key: key,
+                value: chunk,
+                current_version: Versioning::Force,
+                integrity: Some(Integrity { tag: tag, algorithm: Algorithm::SHA1 }),
+                ..Default::default() }));
+
+            if n < self.chunk_size { return Ok(()); }
+        }
+    }
+
+    /// Deletes the chunks of a manifest, best effort
+    fn delete_chunks(&self, manifest: &Manifest) {
+        let deletes = manifest.chunks.iter().map(|c| Delete::Forced { key: c.key.clone() });
+        for _ in self.client.delete_many(deletes) { }
+    }
+
+    /// Opens the object for reading
+    pub fn get(&self, name: &[u8]) -> KineticResult<ObjectReader<'a>> {
+        let manifest = try!(self.get_manifest(name));
+        Ok(ObjectReader::new(self.client, manifest))
+    }
+
+    /// Deletes the object and all its chunks
+    pub fn delete(&self, name: &[u8]) -> KineticResult<()> {
+        let manifest = try!(self.get_manifest(name));
+
+        // the manifest goes first, so the object is never half there
+        try!(self.client.send(Delete::Forced { key: name.to_vec() }));
+        self.delete_chunks(&manifest);
+        Ok(())
+    }
+
+    /// Removes the chunks of the object not referenced by its manifest
+    ///
+    /// These are left behind when a write or a delete is interrupted.
+    ///
+    /// # Returns
+    /// Returns the number of chunks removed.
+    pub fn collect_garbage(&self, name: &[u8]) -> KineticResult<usize> {
+        let referenced: vec::Vec<vec::Vec<u8>> = match self.get_manifest(name) {
+            Ok(m) => m.chunks.into_iter().map(|c| c.key).collect(),
+            Err(KineticError::RemoteError(ref s)) if s.get_code() == StatusCode::NOT_FOUND => vec![],
+            Err(e) => return Err(e),
+        };
+
+        let prefix = ObjectStore::chunks_prefix(name);
+        let (end, end_inclusive) = prefix_end(&prefix, self.client.get_limits().get_maxKeySize() as usize);
+        let max = cmp::max(1, self.client.get_limits().get_maxKeyRangeCount() as i32);
+
+        let mut orphans = vec::Vec::new();
+        let mut start = prefix.clone();
+        let mut start_inclusive = true;
+        loop {
+            let keys = try!(self.client.send(GetKeyRange { start: start.clone(),
+                                                           end: end.clone(),
+                                                           start_inclusive: start_inclusive,
+                                                           end_inclusive: end_inclusive,
+                                                           max_returned: max,
+                                                           reverse: false })).keys;
+            let done = (keys.len() as i32) < max;
+            if let Some(last) = keys.last() { start = last.clone(); }
+            start_inclusive = false;
+
+            orphans.extend(keys.into_iter().filter(|k| is_chunk_key(&prefix, k) && !referenced.contains(k)));
+            if done { break; }
+        }
+
+        let count = orphans.len();
+        for r in self.client.delete_many(orphans.into_iter().map(|k| Delete::Forced { key: k })) {
+            try!(r);
+        }
+        Ok(count)
+    }
+
+}
+
+/// Streams an object, chunk by chunk, verifying each one against the manifest
+///
+/// The next chunk is requested while the current one is being read.
+pub struct ObjectReader<'a> {
+    client: &'a AsyncClient,
+    manifest: Manifest,
+    next: usize,
+    prefetch: Option<ResponseFuture<GetResponse>>,
+    current: Cursor<vec::Vec<u8>>,
+}
+
+impl<'a> ObjectReader<'a> {
+
+    fn new(client: &'a AsyncClient, manifest: Manifest) -> ObjectReader<'a> {
+        let mut r = ObjectReader { client: client,
+                                   manifest: manifest,
+                                   next: 0,
+                                   prefetch: None,
+                                   current: Cursor::new(vec![]) };
+        r.request_next();
+        r
+    }
+
+    /// Gets the manifest of the object being read
+    #[inline]
+    pub fn get_manifest<'r>(&'r self) -> &'r Manifest {
+        &self.manifest
+    }
+
+    fn request_next(&mut self) {
+        self.prefetch = self.manifest.chunks.get(self.next)
+            .map(|c| self.client.send_future(Get { key: c.key.clone() }));
+    }
+
+    /// Moves on to the next chunk, `false` at the end of the object
+    fn advance(&mut self) -> io::Result<bool> {
+        let f = match self.prefetch.take() {
+            Some(f) => f,
+            None => return Ok(false),
+        };
+
+        let r = try!(f.wait().map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e))));
+
+        {
+            let chunk = &self.manifest.chunks[self.next];
+            if r.value.len() != chunk.size as usize || sha1(&r.value) != chunk.tag {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "object chunk does not match its manifest"));
+            }
+        }
+
+        self.current = Cursor::new(r.value);
+        self.next += 1;
+        self.request_next();
+        Ok(true)
+    }
+
+}
+
+impl<'a> Read for ObjectReader<'a> {
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = try!(self.current.read(buf));
+            if n > 0 || buf.is_empty() { return Ok(n); }
+            if !try!(self.advance()) { return Ok(0); }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ObjectStore, Manifest, Chunk, is_chunk_key};
+
+    #[test]
+    fn chunk_keys_of_other_objects_are_not_chunks() {
+        let prefix = ObjectStore::chunks_prefix(b"foo");
+        assert!(is_chunk_key(&prefix, b"foo.chunks.000000005f5e1000075bcd15.0000000003"));
+
+        // the manifest and the chunks of `foo.chunks.x`
+        assert!(!is_chunk_key(&prefix, b"foo.chunks.x"));
+        assert!(!is_chunk_key(&prefix, b"foo.chunks.x.chunks.000000005f5e1000075bcd15.0000000003"));
+        // almost the right shape
+        assert!(!is_chunk_key(&prefix, b"foo.chunks.000000005f5e1000075bcd15.000000000x"));
+        assert!(!is_chunk_key(&prefix, b"foo.chunks.000000005F5E1000075BCD15.0000000003"));
+        assert!(!is_chunk_key(&prefix, b"bar.chunks.000000005f5e1000075bcd15.0000000003"));
+    }
+
+    #[test]
+    fn manifest_roundtrip() {
+        let m = Manifest { len: 10,
+                           chunks: vec![Chunk { key: b"a.chunks.0".to_vec(), size: 6, tag: vec![1; 20] },
+                                        Chunk { key: b"a.chunks.1".to_vec(), size: 4, tag: vec![2; 20] }] };
+        let bytes = m.to_bytes();
+        let r = Manifest::from_bytes(&bytes).unwrap();
+        assert_eq!(r.len, 10);
+        assert_eq!(r.chunks.len(), 2);
+        assert_eq!(r.chunks[1].key, b"a.chunks.1");
+
+        assert!(Manifest::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn corrupt_chunk_count() {
+        // a count of 0xffffffff chunks in a 16 bytes manifest
+        assert!(Manifest::from_bytes(b"KOM1\0\0\0\0\0\0\0\x0a\xff\xff\xff\xff").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Pipelined execution of many commands over a single `Client`
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::mpsc::{Sender, Receiver, channel};
+use core::{Command, Response};
+use result::KineticResult;
+use client::AsyncClient;
+
+/// Keeps up to `depth` commands in flight and collects their results as they complete
+struct InFlight<'a, I, C, R> {
+    client: &'a AsyncClient,
+    commands: I,
+    depth: usize,
+    submitted: usize,
+    outstanding: usize,
+    tx: Sender<(usize, KineticResult<R>)>,
+    rx: Receiver<(usize, KineticResult<R>)>,
+    command_type: PhantomData<C>,
+}
+
+impl<'a, I, C, R> InFlight<'a, I, C, R>
+    where I: Iterator<Item=C>, C: Command<R>, R: Response + 'static {
+
+    fn new(client: &'a AsyncClient, commands: I, depth: usize) -> InFlight<'a, I, C, R> {
+        let (tx, rx) = channel();
+        InFlight { client: client,
+                   commands: commands,
+                   depth: if depth > 0 { depth } else { 1 },
+                   submitted: 0,
+                   outstanding: 0,
+                   tx: tx,
+                   rx: rx,
+                   command_type: PhantomData }
+    }
+
+    /// Sends commands until the pipeline is full or there are no more commands
+    fn fill(&mut self) {
+        while self.outstanding < self.depth {
+            let cmd = match self.commands.next() {
+                Some(cmd) => cmd,
+                None => break,
+            };
+
+            let index = self.submitted;
+            let tx = self.tx.clone();
+            let sent = self.client.send_with_callback(cmd, move |r| { let _ = tx.send((index, r)); });
+
+            // a command that could not be sent still gets its result, just an early one
+            if let Err(e) = sent {
+                let _ = self.tx.send((index, Err(e)));
+            }
+
+            self.submitted += 1;
+            self.outstanding += 1;
+        }
+    }
+
+    fn next_completed(&mut self) -> Option<(usize, KineticResult<R>)> {
+        self.fill();
+        if self.outstanding == 0 { return None; }
+
+        // our own sender is alive, this only returns once a result arrives
+        let r = self.rx.recv().unwrap();
+        self.outstanding -= 1;
+        Some(r)
+    }
+
+}
+
+/// The results of `Client::send_many`, in the same order as the commands
+///
+/// Commands are sent lazily, as results are consumed, keeping the pipeline full.
+pub struct Pipeline<'a, I, C, R> {
+    inner: InFlight<'a, I, C, R>,
+    next: usize,
+    completed: HashMap<usize, KineticResult<R>>,
+}
+
+impl<'a, I, C, R> Pipeline<'a, I, C, R>
+    where I: Iterator<Item=C>, C: Command<R>, R: Response + 'static {
+
+    pub fn new(client: &'a AsyncClient, commands: I, depth: usize) -> Pipeline<'a, I, C, R> {
+        Pipeline { inner: InFlight::new(client, commands, depth),
+                   next: 0,
+                   completed: HashMap::new() }
+    }
+
+    /// Yields the results as they complete instead, tagged with the index of their command
+    pub fn unordered(self) -> Unordered<'a, I, C, R> {
+        Unordered { inner: self.inner, completed: self.completed.into_iter().collect() }
+    }
+
+}
+
+impl<'a, I, C, R> Iterator for Pipeline<'a, I, C, R>
+    where I: Iterator<Item=C>, C: Command<R>, R: Response + 'static {
+    type Item = KineticResult<R>;
+
+    fn next(&mut self) -> Option<KineticResult<R>> {
+        loop {
+            if let Some(r) = self.completed.remove(&self.next) {
+                self.next += 1;
+                return Some(r);
+            }
+
+            match self.inner.next_completed() {
+                Some((index, r)) => { self.completed.insert(index, r); },
+                None => return None,
+            }
+        }
+    }
+
+}
+
+/// The results of `Client::send_many` as they complete, see `Pipeline::unordered`
+pub struct Unordered<'a, I, C, R> {
+    inner: InFlight<'a, I, C, R>,
+    completed: Vec<(usize, KineticResult<R>)>,
+}
+
+impl<'a, I, C, R> Iterator for Unordered<'a, I, C, R>
+    where I: Iterator<Item=C>, C: Command<R>, R: Response + 'static {
+    type Item = (usize, KineticResult<R>);
+
+    #[inline]
+    fn next(&mut self) -> Option<(usize, KineticResult<R>)> {
+        match self.completed.pop() {
+            Some(r) => Some(r),
+            None => self.inner.next_completed(),
+        }
+    }
+
+}
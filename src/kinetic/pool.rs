@@ -0,0 +1,376 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Pools of connections to a single kinetic device
+
+use std::{vec, cmp, thread};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Sender, Receiver, channel};
+use std::time::Duration;
+use channel::{AsyncChannel, ChannelSender, CompletionChannel, KineticChannel, Operation, Result, WindowPolicy};
+use events::DeviceEvent;
+use result::KineticResult;
+use error::KineticError;
+
+/// How often the pool checks its connections, in milliseconds
+static MONITOR_INTERVAL_MS: u64 = 500;
+
+/// Bytes of value that weigh as much as a request when balancing connections (64 KiB)
+static BYTES_PER_LOAD_UNIT: usize = 64 * 1024;
+
+/// A connection of the pool
+///
+/// Senders clone the `ChannelSender` out of its lock and send without holding it,
+/// so a connection blocked on a full window doesn't block the others or the monitor.
+struct Slot {
+    // only touched by the monitor, for the events and to replace the connection
+    channel: Mutex<Option<AsyncChannel>>,
+    sender: Mutex<Option<ChannelSender>>,
+    // whether the channel is open, kept apart so picking a connection doesn't take every lock
+    open: AtomicBool,
+    // requests in flight, plus one per `BYTES_PER_LOAD_UNIT` of value they carry
+    load: AtomicUsize,
+    // whether a new connection is being opened for this slot
+    connecting: AtomicBool,
+}
+
+impl Slot {
+
+    fn new(channel: Option<AsyncChannel>) -> Slot {
+        Slot { open: AtomicBool::new(channel.is_some()),
+               sender: Mutex::new(channel.as_ref().map(|c| c.get_sender())),
+               channel: Mutex::new(channel),
+               load: AtomicUsize::new(0),
+               connecting: AtomicBool::new(false) }
+    }
+
+    #[inline]
+    fn get_sender(&self) -> Option<ChannelSender> {
+        self.sender.lock().unwrap().clone()
+    }
+
+    fn set(&self, channel: Option<AsyncChannel>) {
+        let open = channel.is_some();
+        *self.sender.lock().unwrap() = channel.as_ref().map(|c| c.get_sender());
+        // the old connection is dropped here, failing whatever it still had in flight
+        *self.channel.lock().unwrap() = channel;
+        self.open.store(open, Ordering::SeqCst);
+    }
+
+    #[inline]
+    fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+}
+
+/// A `KineticChannel` spreading requests over several `AsyncChannel`s to the same device
+///
+/// Each request goes to the connection with the least outstanding requests. Values count too,
+/// so a connection busy with large `Put`s is avoided by small requests and doesn't
+/// head-of-line block them.
+///
+/// A background thread replaces connections that are closed, opening each new connection on
+/// a thread of its own so a device slow to answer doesn't hold up the other slots. Meanwhile
+/// requests go to the remaining ones. It also merges the events of all the connections in a single stream,
+/// `DeviceEvent::Disconnected` is not forwarded since the pool reconnects.
+///
+/// The `Configuration` and `Limits` are the ones received on the first _handshake_.
+pub struct PoolChannel {
+    slots: Arc<vec::Vec<Slot>>,
+    events_tx: Sender<DeviceEvent>,
+    events_rx: Receiver<DeviceEvent>,
+    shutdown: Arc<AtomicBool>,
+    configuration: ::proto::command::log::Configuration,
+    limits: ::proto::command::log::Limits,
+}
+
+impl Drop for PoolChannel {
+
+    #[inline]
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+
+}
+
+impl PoolChannel {
+
+    /// Opens up to `connections` connections to the device at `addr`
+    ///
+    /// `max_pending` and `policy` apply to each connection, see `AsyncChannel::new_with_policy`.
+    pub fn new<A: ToSocketAddrs>(addr: A, connections: usize, max_pending: usize, policy: WindowPolicy)
+            -> KineticResult<PoolChannel> {
+        // resolve once, reconnects go to the same address
+        let addr: SocketAddr = match try!(addr.to_socket_addrs()).next() {
+            Some(addr) => addr,
+            None => return Err(KineticError::ConnectionClosed),
+        };
+
+        let first = try!(AsyncChannel::new_with_policy(addr, max_pending, policy));
+        let configuration = first.get_configuration().clone();
+        let limits = first.get_limits().clone();
+
+        let max_connections = limits.get_maxConnections() as usize;
+        let connections = if max_connections > 0 { cmp::min(connections, max_connections) }
+                          else { connections };
+
+        let mut slots = vec![Slot::new(Some(first))];
+        for _ in 1..connections {
+            let c = try!(AsyncChannel::new_with_policy(addr, max_pending, policy));
+            slots.push(Slot::new(Some(c)));
+        }
+        let slots = Arc::new(slots);
+
+        let (events_tx, events_rx) = channel();
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        // monitor
+        let slots_monitor = slots.clone();
+        let events_tx_monitor = events_tx.clone();
+        let shutdown_monitor = shutdown.clone();
+        thread::spawn(move|| {
+            let slots = slots_monitor;
+            let events_tx = events_tx_monitor;
+            while !shutdown_monitor.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(MONITOR_INTERVAL_MS));
+
+                for (index, slot) in slots.iter().enumerate() {
+                    if slot.connecting.load(Ordering::SeqCst) { continue; }
+
+                    let dead = match *slot.channel.lock().unwrap() {
+                        Some(ref c) => {
+                            while let Ok(e) = c.get_event_receiver().try_recv() {
+                                match e {
+                                    DeviceEvent::Disconnected => (),
+                                    e => { let _ = events_tx.send(e); },
+                                }
+                            }
+                            c.is_closed()
+                        },
+                        None => true,
+                    };
+
+                    if dead {
+                        slot.open.store(false, Ordering::SeqCst);
+                        slot.connecting.store(true, Ordering::SeqCst);
+
+                        // connect on its own thread, a device slow to answer doesn't hold up
+                        // the other slots, and without any lock, the other connections keep serving
+                        let slots = slots.clone();
+                        let shutdown = shutdown_monitor.clone();
+                        thread::spawn(move|| {
+                            let c = AsyncChannel::new_with_policy(addr, max_pending, policy).ok();
+                            if !shutdown.load(Ordering::SeqCst) {
+                                slots[index].set(c); // or retry on the next round
+                            }
+                            slots[index].connecting.store(false, Ordering::SeqCst);
+                        });
+                    }
+                }
+            }
+        });
+
+        Ok(PoolChannel { slots: slots,
+                         events_tx: events_tx,
+                         events_rx: events_rx,
+                         shutdown: shutdown,
+                         configuration: configuration,
+                         limits: limits })
+    }
+
+    /// Gets the number of connections currently open
+    pub fn get_connection_count(&self) -> usize {
+        self.slots.iter().filter(|s| s.is_open()).count()
+    }
+
+    /// Sends an operation on the least loaded connection, see `AsyncChannel::send_with`
+    pub fn send_with<F>(&self, op: Operation, completion: F) -> KineticResult<()>
+        where F: FnOnce(KineticResult<Result>) + Send + 'static {
+
+        let index = match self.slots.iter().enumerate()
+                .filter(|&(_, s)| s.is_open())
+                .min_by_key(|&(_, s)| s.load.load(Ordering::SeqCst)) {
+            Some((index, _)) => index,
+            None => return Err(KineticError::ConnectionClosed),
+        };
+        let slot = &self.slots[index];
+
+        let cost = 1 + op.2.as_ref().map_or(0, |v| v.len() / BYTES_PER_LOAD_UNIT);
+        slot.load.fetch_add(cost, Ordering::SeqCst);
+
+        let slots = self.slots.clone();
+        let release = move || { slots[index].load.fetch_sub(cost, Ordering::SeqCst); };
+
+        // send without the lock, it may block on a full window
+        let r = match slot.get_sender() {
+            Some(c) => c.send_with(op, move |r| {
+                release();
+                completion(r);
+            }),
+            // replaced while we were picking it
+            None => Err(KineticError::ConnectionClosed),
+        };
+
+        if let Err(ref e) = r {
+            slot.load.fetch_sub(cost, Ordering::SeqCst);
+            // don't pick it again until the monitor replaces it
+            if let KineticError::ConnectionClosed = *e { slot.open.store(false, Ordering::SeqCst); }
+        }
+        r // return
+    }
+
+}
+
+impl CompletionChannel for PoolChannel {
+
+    #[inline]
+    fn send_with<F>(&self, op: Operation, completion: F) -> KineticResult<()>
+        where F: FnOnce(KineticResult<Result>) + Send + 'static {
+        PoolChannel::send_with(self, op, completion)
+    }
+
+}
+
+impl KineticChannel<Receiver<Result>> for PoolChannel {
+
+    #[inline]
+    fn is_closed(&self) -> bool { self.get_connection_count() == 0 }
+
+    #[inline]
+    fn get_event_receiver<'r>(&'r self) -> &'r Receiver<DeviceEvent> {
+        &self.events_rx
+    }
+
+    #[inline]
+    fn notify(&self, event: DeviceEvent) {
+        let _ = self.events_tx.send(event);
+    }
+
+    #[inline]
+    fn get_configuration<'r>(&'r self) -> &'r ::proto::command::log::Configuration {
+        &self.configuration
+    }
+
+    #[inline]
+    fn get_limits<'r>(&'r self) -> &'r ::proto::command::log::Limits {
+        &self.limits
+    }
+
+    #[inline]
+    fn send(&self, op: Operation) -> KineticResult<Receiver<Result>> {
+        let (tx,rx) = channel();
+        // on failure the sender is dropped, which `receive` reports as a closed connection
+        try!(self.send_with(op, move |r: KineticResult<Result>| {
+            if let Ok(r) = r { let _ = tx.send(r); }
+        }));
+        Ok(rx) //return rx
+    }
+
+    #[inline]
+    fn receive(rx: Receiver<Result>) -> KineticResult<Result> {
+        rx.recv().map_err(|_| KineticError::ConnectionClosed)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use futures::Future;
+    use super::PoolChannel;
+    use channel::{KineticChannel, Operation, WindowPolicy};
+    use client::Client;
+    use commands::Noop;
+    use proto::command::MessageType;
+    use testing::{Drive, Mode};
+
+    fn noop() -> Operation {
+        let mut cmd = ::proto::Command::new();
+        cmd.mut_header().set_messageType(MessageType::NOOP);
+        (Default::default(), cmd, None)
+    }
+
+    fn send(pool: &PoolChannel) -> bool {
+        pool.send(noop()).and_then(PoolChannel::receive).is_ok()
+    }
+
+    /// Waits up to 5 seconds for `f` to hold
+    fn eventually<F: Fn() -> bool>(f: F) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            if f() { return true; }
+            thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn pooled_client() {
+        let drive = Drive::start();
+        let c = Client::new_pooled(drive.address(), 3).unwrap();
+
+        for _ in 0..10 {
+            c.send(Noop).unwrap();
+        }
+        let futures: Vec<_> = (0..10).map(|_| c.send_future(Noop)).collect();
+        for f in futures.into_iter() {
+            f.wait().unwrap();
+        }
+    }
+
+    #[test]
+    fn replaces_lost_connections() {
+        let drive = Drive::start();
+        let pool = PoolChannel::new(drive.address(), 2, 16, WindowPolicy::Block).unwrap();
+        assert_eq!(pool.get_connection_count(), 2);
+
+        drive.set_mode(Mode::Hangup);
+        assert!(!send(&pool));
+        drive.set_mode(Mode::Serve);
+
+        assert!(eventually(|| pool.get_connection_count() == 2 && send(&pool)));
+    }
+
+    #[test]
+    fn stalled_reconnect_does_not_hold_up_other_slots() {
+        let drive = Drive::start();
+        let pool = PoolChannel::new(drive.address(), 2, 16, WindowPolicy::Block).unwrap();
+
+        // the first slot reconnects to a device that never completes the handshake
+        drive.set_mode(Mode::Hangup);
+        assert!(!send(&pool));
+        drive.set_mode(Mode::Stall);
+        assert!(eventually(|| pool.get_connection_count() == 1));
+        thread::sleep(Duration::from_millis(1000));
+
+        // the second slot is lost too, and still replaced
+        drive.set_mode(Mode::Hangup);
+        assert!(!send(&pool));
+        drive.set_mode(Mode::Serve);
+
+        assert!(eventually(|| pool.get_connection_count() == 1 && send(&pool)));
+    }
+}
@@ -42,6 +42,7 @@ pub mod command {
     pub use proto::raw::Command_MessageType as MessageType;
     pub use proto::raw::Command_Body as Body;
     pub use proto::raw::Command_Status as Status;
+    pub use proto::raw::Command_Priority as Priority;
 
     pub use proto::raw::Command_KeyValue as KeyValue;
     pub use proto::raw::Command_Algorithm as Algorithm;
@@ -63,6 +64,7 @@ pub mod command {
         pub use proto::raw::Command_GetLog_Configuration as Configuration;
         pub use proto::raw::Command_GetLog_Statistics as Statistics;
         pub use proto::raw::Command_GetLog_Limits as Limits;
+        pub use proto::raw::Command_GetLog_Device as Device;
         pub use proto::raw::Command_GetLog_Configuration_Interface as Interface;
     }
 }
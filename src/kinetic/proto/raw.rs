@@ -3,11 +3,13 @@
 
 // https://github.com/Manishearth/rust-clippy/issues/702
 #![allow(unknown_lints)]
-#![allow(clippy)]
+#![allow(clippy::all)]
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 
-#![allow(box_pointers)]
+#![allow(bare_trait_objects)]
+#![allow(mismatched_lifetime_syntaxes)]
+#![allow(static_mut_refs)]
 #![allow(dead_code)]
 #![allow(missing_docs)]
 #![allow(non_camel_case_types)]
@@ -0,0 +1,464 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! N-way replication of keys across kinetic devices
+
+use std::vec;
+use std::default::Default;
+use std::cmp;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{ByteOrder, BigEndian};
+use std::sync::mpsc::channel;
+use cluster::ClusterClient;
+use client::AsyncClient;
+use commands::{Put, Get, GetVersion};
+use commands::common::Versioning;
+use responses::{GetResponse, GetVersionResponse};
+use proto::StatusCode;
+use result::KineticResult;
+use error::KineticError;
+
+#[inline]
+fn is_not_found(e: &KineticError) -> bool {
+    match *e {
+        KineticError::RemoteError(ref status) => status.get_code() == StatusCode::NOT_FOUND,
+        _ => false,
+    }
+}
+
+#[inline]
+fn is_version_mismatch(e: &KineticError) -> bool {
+    match *e {
+        KineticError::RemoteError(ref status) => status.get_code() == StatusCode::VERSION_MISMATCH,
+        _ => false,
+    }
+}
+
+/// How many times a write retries a replica that changed under it
+static WRITE_ATTEMPTS: usize = 3;
+
+/// A reply from a replica during a write
+enum Step {
+    Read(KineticResult<GetVersionResponse>),
+    Written(KineticResult<()>),
+}
+
+/// The error of a read finding the key deleted
+#[inline]
+fn not_found() -> KineticError {
+    let mut status = ::proto::command::Status::new();
+    status.set_code(StatusCode::NOT_FOUND);
+    status.set_statusMessage("key deleted".to_string());
+    KineticError::RemoteError(status)
+}
+
+/// Versions are a timestamp, the store client id and the kind of entry
+static VERSION_SIZE: usize = 17;
+static VALUE: u8 = 0;
+static TOMBSTONE: u8 = 1;
+
+/// Whether a version marks a deleted key
+#[inline]
+fn is_tombstone(version: &[u8]) -> bool {
+    version.len() == VERSION_SIZE && version[VERSION_SIZE - 1] == TOMBSTONE
+}
+
+/// Either the operation succeeded or the key was not there
+#[inline]
+fn is_answer<T>(r: &KineticResult<T>) -> bool {
+    r.as_ref().err().map_or(true, is_not_found)
+}
+
+/// A key/value store replicating every key on `N` drives
+///
+/// Each key is written to the first `N` drives the `ClusterClient` ring places it on.
+/// A write succeeds once `W` of them acknowledge it and a read once `R` of them answer.
+/// Choosing `W + R > N` guarantees reads see the latest acknowledged write.
+///
+/// Every write is stamped with a new version, versions sort bytewise in write order:
+/// a timestamp in nanoseconds followed by the id of the store that wrote it, random
+/// unless given with `new_with_client_id`, so two stores never generate the same version.
+/// The timestamp never goes backwards for a store and is moved past any newer version
+/// it reads, so a store writing after reading a value always supersedes it, whatever
+/// the clock skew. Concurrent writes from stores with skewed clocks are still ordered
+/// by their clocks. Reads return the value with the highest version and repair the
+/// replicas found missing or stale, using `Versioning::Match` so a repair never
+/// overwrites a newer concurrent write. Writes are conditional as well, a replica
+/// already holding a newer version keeps it.
+///
+/// Deletes write a tombstone, an empty value whose version is marked as deleted,
+/// so read repair spreads the delete instead of bringing the key back on a replica
+/// that missed it. Tombstones are never removed, they take the space of an empty entry.
+///
+/// # Example
+/// ```no_run
+/// use kinetic::cluster::ClusterClient;
+/// use kinetic::replication::ReplicatedStore;
+///
+/// let mut c = ClusterClient::new();
+/// c.connect("10.0.0.1:8123").unwrap();
+/// c.connect("10.0.0.2:8123").unwrap();
+/// c.connect("10.0.0.3:8123").unwrap();
+///
+/// let store = ReplicatedStore::new(c, 3, 2, 2);
+/// store.put("hello".as_bytes(), "world".as_bytes().to_vec()).unwrap();
+/// let v = store.get("hello".as_bytes()).unwrap();
+/// ```
+pub struct ReplicatedStore {
+    cluster: ClusterClient,
+    replicas: usize,
+    write_quorum: usize,
+    read_quorum: usize,
+    client_id: u64,
+    clock: Mutex<u64>,
+}
+
+impl ReplicatedStore {
+
+    /// Creates a store with `replicas` copies, a write quorum `w` and a read quorum `r`
+    ///
+    /// # Panics
+    /// Panics if either quorum is zero or bigger than the number of replicas.
+    #[inline]
+    pub fn new(cluster: ClusterClient, replicas: usize, w: usize, r: usize) -> ReplicatedStore {
+        ReplicatedStore::new_with_client_id(cluster, replicas, w, r, ::rand::random())
+    }
+
+    /// Creates a store stamping its versions with `client_id`, which must be unique among
+    /// the stores writing to the same drives
+    ///
+    /// # Panics
+    /// Panics if either quorum is zero or bigger than the number of replicas.
+    pub fn new_with_client_id(cluster: ClusterClient, replicas: usize, w: usize, r: usize,
+                              client_id: u64) -> ReplicatedStore {
+        assert!(w > 0 && w <= replicas, "write quorum must be between 1 and the number of replicas");
+        assert!(r > 0 && r <= replicas, "read quorum must be between 1 and the number of replicas");
+
+        ReplicatedStore { cluster: cluster,
+                          replicas: replicas,
+                          write_quorum: w,
+                          read_quorum: r,
+                          client_id: client_id,
+                          clock: Mutex::new(0) }
+    }
+
+    /// Gets the underlying `ClusterClient`
+    #[inline]
+    pub fn get_cluster<'r>(&'r self) -> &'r ClusterClient {
+        &self.cluster
+    }
+
+    /// Gets the underlying `ClusterClient` to add or remove drives
+    #[inline]
+    pub fn get_cluster_mut<'r>(&'r mut self) -> &'r mut ClusterClient {
+        &mut self.cluster
+    }
+
+    /// Generates a version greater than any previously generated or seen by this store
+    fn next_version(&self, kind: u8) -> vec::Vec<u8> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let now = now.as_secs() * 1000000000 + now.subsec_nanos() as u64;
+
+        let timestamp = {
+            let mut clock = self.clock.lock().unwrap();
+            *clock = cmp::max(now, *clock + 1);
+            *clock
+        };
+
+        let mut version = vec![0u8; VERSION_SIZE];
+        <BigEndian as ByteOrder>::write_u64(&mut version[..8], timestamp);
+        <BigEndian as ByteOrder>::write_u64(&mut version[8..16], self.client_id);
+        version[VERSION_SIZE - 1] = kind;
+        version // return
+    }
+
+    /// Moves the clock past a version read from the drives
+    fn observe(&self, version: &[u8]) {
+        if version.len() != VERSION_SIZE { return; }
+        let timestamp = <BigEndian as ByteOrder>::read_u64(&version[..8]);
+        let mut clock = self.clock.lock().unwrap();
+        *clock = cmp::max(timestamp, *clock);
+    }
+
+    #[inline]
+    fn check_quorum(required: usize, reached: usize) -> KineticResult<()> {
+        if reached >= required {
+            Ok(())
+        } else {
+            Err(KineticError::QuorumNotReached { required: required, reached: reached })
+        }
+    }
+
+    /// Stores the value on all the replicas of the key
+    ///
+    /// # Returns
+    /// Returns the new version of the key once the write quorum acknowledged it.
+    pub fn put(&self, key: &[u8], value: vec::Vec<u8>) -> KineticResult<vec::Vec<u8>> {
+        let version = self.next_version(VALUE);
+        try!(self.write(key, value, &version));
+        Ok(version)
+    }
+
+    /// Writes an entry to all the replicas of the key and waits for the write quorum
+    ///
+    /// Each replica is written with a conditional put over the version it holds, a replica
+    /// already holding a newer version keeps it and counts as an acknowledgement, since the
+    /// entry was superseded. Returns as soon as the quorum acknowledged the write, replicas
+    /// that didn't answer by then are left to read repair.
+    fn write(&self, key: &[u8], value: vec::Vec<u8>, version: &[u8]) -> KineticResult<()> {
+        let drives: vec::Vec<&AsyncClient> = self.cluster.drive_names_for(key, self.replicas).into_iter()
+            .filter_map(|name| self.cluster.get_drive(name))
+            .collect();
+
+        let (tx, rx) = channel();
+        let read_version = |i: usize| {
+            let tx = tx.clone();
+            drives[i].send_with_callback(GetVersion { key: key.to_vec() },
+                                         move |r| { let _ = tx.send((i, Step::Read(r))); })
+        };
+
+        let mut attempts = vec![0; drives.len()];
+        let mut outstanding = 0;
+        for i in 0..drives.len() {
+            if read_version(i).is_ok() { outstanding += 1; }
+        }
+
+        let mut acks = 0;
+        while acks < self.write_quorum && outstanding > 0 {
+            let (i, step) = rx.recv().unwrap();
+            outstanding -= 1;
+
+            let sent = match step {
+                Step::Read(r) => {
+                    let current = match r {
+                        Ok(r) => r.version,
+                        // an empty version only matches a missing key
+                        Err(ref e) if is_not_found(e) => vec![],
+                        Err(_) => continue,
+                    };
+                    if &current[..] >= version {
+                        acks += 1;
+                        continue;
+                    }
+
+                    let tx = tx.clone();
+                    drives[i].send_with_callback(Put { key: key.to_vec(),
+                                                       value: value.clone(),
+                                                       new_version: version.to_vec(),
+                                                       current_version: Versioning::Match(current),
+                                                       ..Default::default() },
+                                                 move |r| { let _ = tx.send((i, Step::Written(r))); })
+                },
+                Step::Written(Ok(())) => {
+                    acks += 1;
+                    continue;
+                },
+                // a concurrent write got there first, see which one is newer
+                Step::Written(Err(ref e)) if is_version_mismatch(e) && attempts[i] < WRITE_ATTEMPTS => {
+                    attempts[i] += 1;
+                    read_version(i)
+                },
+                Step::Written(Err(_)) => continue,
+            };
+            if sent.is_ok() { outstanding += 1; }
+        }
+
+        ReplicatedStore::check_quorum(self.write_quorum, acks) // return
+    }
+
+    /// Gets the latest value of the key among the replicas
+    ///
+    /// Returns as soon as the read quorum answered. Replicas found missing the key, or
+    /// holding an older version, are repaired in the background. A key whose latest
+    /// version is a tombstone is not found.
+    pub fn get(&self, key: &[u8]) -> KineticResult<GetResponse> {
+        let drives = self.cluster.drive_names_for(key, self.replicas);
+
+        let (tx, rx) = channel();
+        let mut outstanding = 0;
+        for (i, name) in drives.iter().enumerate() {
+            if let Some(c) = self.cluster.get_drive(name) {
+                let tx = tx.clone();
+                if c.send_with_callback(Get { key: key.to_vec() }, move |r| { let _ = tx.send((i, r)); }).is_ok() {
+                    outstanding += 1;
+                }
+            }
+        }
+
+        let mut replies: vec::Vec<(&str, KineticResult<GetResponse>)> = vec::Vec::with_capacity(outstanding);
+        let mut answers = 0;
+        while answers < self.read_quorum && outstanding > 0 {
+            let (i, r) = rx.recv().unwrap();
+            outstanding -= 1;
+            // both having the key and not having it are answers
+            if is_answer(&r) { answers += 1; }
+            replies.push((drives[i], r));
+        }
+
+        try!(ReplicatedStore::check_quorum(self.read_quorum, answers));
+
+        let mut latest: Option<GetResponse> = None;
+        let mut missing: Option<KineticError> = None;
+        let mut stale = vec::Vec::new();
+        for (name, r) in replies.into_iter() {
+            match r {
+                Ok(r) => {
+                    let newer = match latest {
+                        Some(ref l) => r.version > l.version,
+                        None => true,
+                    };
+                    if newer {
+                        if let Some(l) = latest.take() { stale.push((name, l.version)); }
+                        latest = Some(r);
+                    } else {
+                        stale.push((name, r.version));
+                    }
+                },
+                Err(e) => if is_not_found(&e) {
+                    stale.push((name, vec![]));
+                    missing = Some(e);
+                },
+            }
+        }
+
+        match latest {
+            Some(latest) => {
+                self.observe(&latest.version);
+                self.repair(key, &latest, stale);
+                if is_tombstone(&latest.version) { Err(not_found()) } else { Ok(latest) }
+            },
+            // the quorum answered and no replica has the key
+            None => Err(missing.unwrap()),
+        }
+    }
+
+    /// Brings the stale replicas up to the latest version, a tombstone included, best effort
+    fn repair(&self, key: &[u8], latest: &GetResponse, stale: vec::Vec<(&str, vec::Vec<u8>)>) {
+        for (name, version) in stale.into_iter() {
+            if version == latest.version { continue; }
+
+            if let Some(c) = self.cluster.get_drive(name) {
+                // an empty version only matches a missing key
+                let _ = c.send_with_callback(Put { key: key.to_vec(),
+                                                   value: latest.value.clone(),
+                                                   new_version: latest.version.clone(),
+                                                   current_version: Versioning::Match(version),
+                                                   ..Default::default() }, |_| ());
+            }
+        }
+    }
+
+    /// Deletes the key from all its replicas, writing a tombstone
+    ///
+    /// Succeeds once the write quorum acknowledged the tombstone.
+    pub fn delete(&self, key: &[u8]) -> KineticResult<()> {
+        let version = self.next_version(TOMBSTONE);
+        self.write(key, vec![], &version) // return
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use super::{ReplicatedStore, is_tombstone, is_not_found, VALUE, TOMBSTONE};
+    use cluster::ClusterClient;
+    use client::Client;
+    use error::KineticError;
+    use testing::{Drive, Mode};
+
+    fn cluster(n: usize) -> (vec::Vec<Drive>, ClusterClient) {
+        let drives: vec::Vec<Drive> = (0..n).map(|_| Drive::start()).collect();
+        let mut c = ClusterClient::new();
+        for d in drives.iter() {
+            c.add_drive_with_weight(&d.address(), Client::new(d.address()).unwrap(), 1);
+        }
+        (drives, c)
+    }
+
+    #[test]
+    fn quorum_does_not_wait_for_a_silent_drive() {
+        let (drives, c) = cluster(3);
+        drives[1].set_mode(Mode::Silent);
+        let store = ReplicatedStore::new(c, 3, 2, 2);
+
+        let version = store.put(b"hello", b"world".to_vec()).unwrap();
+        let r = store.get(b"hello").unwrap();
+        assert_eq!(r.value, b"world");
+        assert_eq!(r.version, version);
+        assert!(drives[1].get(b"hello").is_none());
+
+        store.delete(b"hello").unwrap();
+        assert!(is_not_found(&store.get(b"hello").unwrap_err()));
+    }
+
+    #[test]
+    fn quorum_not_reached() {
+        let (drives, c) = cluster(3);
+        drives[0].set_mode(Mode::Hangup);
+        drives[2].set_mode(Mode::Hangup);
+        let store = ReplicatedStore::new(c, 3, 2, 2);
+
+        match store.put(b"hello", b"world".to_vec()) {
+            Err(KineticError::QuorumNotReached { required: 2, reached: 1 }) => (),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn late_writes_keep_the_newer_version() {
+        let (drives, c) = cluster(3);
+        let store = ReplicatedStore::new(c, 3, 3, 1);
+
+        let late = store.next_version(VALUE);
+        let version = store.put(b"hello", b"new".to_vec()).unwrap();
+        store.write(b"hello", b"old".to_vec(), &late).unwrap();
+
+        for d in drives.iter() {
+            assert_eq!(d.get(b"hello"), Some((b"new".to_vec(), version.clone())));
+        }
+    }
+
+    #[test]
+    fn versions_increase_and_carry_the_client_id() {
+        let store = ReplicatedStore::new_with_client_id(ClusterClient::new(), 1, 1, 1, 7);
+        let a = store.next_version(VALUE);
+        let b = store.next_version(TOMBSTONE);
+
+        assert!(b > a);
+        assert_eq!(&a[8..16], &[0, 0, 0, 0, 0, 0, 0, 7]);
+        assert!(!is_tombstone(&a));
+        assert!(is_tombstone(&b));
+    }
+
+    #[test]
+    fn versions_move_past_observed_ones() {
+        let store = ReplicatedStore::new(ClusterClient::new(), 1, 1, 1);
+        let mut future = vec![0xffu8; 8];
+        future.extend([0u8; 9].iter().cloned());
+        future[0] = 0x7f;
+
+        store.observe(&future);
+        assert!(store.next_version(VALUE) > future);
+    }
+}
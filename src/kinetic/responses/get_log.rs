@@ -45,3 +45,24 @@ impl Response for GetLogResponse {
 
 }
 
+/// A `GetDeviceLog` command result
+///
+/// The device log comes back as the value of the response.
+#[derive(Debug)]
+pub struct GetDeviceLogResponse {
+    pub value: vec::Vec<u8>,
+}
+
+impl Response for GetDeviceLogResponse {
+
+    fn from_proto(_: Message, mut cmd: Command, value: vec::Vec<u8>) -> KineticResult<GetDeviceLogResponse> {
+        let status = cmd.take_status();
+
+        if status.get_code() == ::proto::StatusCode::SUCCESS {
+            Ok(GetDeviceLogResponse { value: value })
+        } else {
+            Err(KineticError::RemoteError(status))
+        }
+    }
+
+}
@@ -32,6 +32,7 @@ use commands::common::Integrity;
 /// A `GetNext` command returns the value following the given key
 #[derive(Debug)]
 pub struct GetNextResponse {
+    pub key: vec::Vec<u8>,
     pub value: vec::Vec<u8>,
     pub version: vec::Vec<u8>,
     pub integrity: Integrity,
@@ -45,7 +46,8 @@ impl Response for GetNextResponse {
         if status.get_code() == ::proto::StatusCode::SUCCESS {
             let mut kv = cmd.take_body().take_keyValue();
 
-            Ok(GetNextResponse { value: value,
+            Ok(GetNextResponse { key: kv.take_key(),
+                                 value: value,
                                  version: kv.take_dbVersion(),
                                  integrity: Integrity { tag: kv.take_tag(),
                                                         algorithm: kv.get_algorithm() }})
@@ -32,6 +32,7 @@ use commands::common::Integrity;
 /// A `GetPrevious` command returns the value before the given key
 #[derive(Debug)]
 pub struct GetPreviousResponse {
+    pub key: vec::Vec<u8>,
     pub value: vec::Vec<u8>,
     pub version: vec::Vec<u8>,
     pub integrity: Integrity,
@@ -45,7 +46,8 @@ impl Response for GetPreviousResponse {
         if status.get_code() == ::proto::StatusCode::SUCCESS {
             let mut kv = cmd.take_body().take_keyValue();
 
-            Ok(GetPreviousResponse { value: value,
+            Ok(GetPreviousResponse { key: kv.take_key(),
+                                     value: value,
                                      version: kv.take_dbVersion(),
                                      integrity: Integrity { tag: kv.take_tag(),
                                                             algorithm: kv.get_algorithm() }})
@@ -23,7 +23,7 @@
 //! Kinetic responses for available commands
 
 pub use responses::get::GetResponse;
-pub use responses::get_log::GetLogResponse;
+pub use responses::get_log::{GetLogResponse, GetDeviceLogResponse};
 pub use responses::get_key_range::GetKeyRangeResponse;
 pub use responses::get_version::GetVersionResponse;
 pub use responses::get_next::GetNextResponse;
@@ -0,0 +1,226 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! An in-process drive speaking just enough of the protocol for the tests
+
+use protobuf::Message;
+use std::{vec, io, net, thread};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use proto::command::{MessageType, KeyValue, Algorithm};
+use proto::StatusCode;
+
+/// How a `Drive` treats the requests it receives
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Answers every request
+    Serve,
+    /// Reads requests but never answers them
+    Silent,
+    /// Answers requests of the given type with an error, serves the rest
+    Fail(MessageType, StatusCode),
+    /// Closes the connection as soon as a request arrives
+    Hangup,
+    /// Never completes the handshake of new connections, serves the others
+    Stall,
+}
+
+/// A stored entry: value, version, tag and algorithm
+type Entry = (vec::Vec<u8>, vec::Vec<u8>, vec::Vec<u8>, Algorithm);
+
+struct State {
+    mode: Mutex<Mode>,
+    store: Mutex<BTreeMap<vec::Vec<u8>, Entry>>,
+    cluster_version: Mutex<i64>,
+    window: u32,
+}
+
+/// A drive listening on a local port, keeping its keys in memory
+///
+/// It supports `PUT`, `GET`, `GETVERSION`, `DELETE` and `NOOP`, versions are checked
+/// unless the request is forced. Nothing is authenticated.
+pub struct Drive {
+    addr: net::SocketAddr,
+    state: Arc<State>,
+}
+
+impl Drive {
+
+    /// Starts a drive that doesn't advertise any in-flight limit
+    #[inline]
+    pub fn start() -> Drive {
+        Drive::with_window(0)
+    }
+
+    /// Starts a drive advertising `window` as its outstanding read and write limits
+    pub fn with_window(window: u32) -> Drive {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(State { mode: Mutex::new(Mode::Serve),
+                                     store: Mutex::new(BTreeMap::new()),
+                                     cluster_version: Mutex::new(0),
+                                     window: window });
+
+        let s = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let s = s.clone();
+                if let Ok(stream) = stream {
+                    thread::spawn(move || serve(stream, &s));
+                }
+            }
+        });
+
+        Drive { addr: addr, state: state }
+    }
+
+    /// Gets the `host:port` address of the drive
+    #[inline]
+    pub fn address(&self) -> String {
+        self.addr.to_string()
+    }
+
+    #[inline]
+    pub fn set_mode(&self, mode: Mode) {
+        *self.state.mode.lock().unwrap() = mode;
+    }
+
+    /// Sets the cluster version, requests carrying another one fail with `VERSION_FAILURE`
+    #[inline]
+    pub fn set_cluster_version(&self, cluster_version: i64) {
+        *self.state.cluster_version.lock().unwrap() = cluster_version;
+    }
+
+    /// Gets the value and version stored for `key`
+    pub fn get(&self, key: &[u8]) -> Option<(vec::Vec<u8>, vec::Vec<u8>)> {
+        self.state.store.lock().unwrap().get(key).map(|e| (e.0.clone(), e.1.clone()))
+    }
+
+    /// Gets the number of keys stored
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.state.store.lock().unwrap().len()
+    }
+
+}
+
+fn reply(stream: &mut net::TcpStream, cmd: ::proto::Command, value: &[u8]) -> bool {
+    let mut msg = ::proto::Message::new();
+    msg.set_authType(::proto::message::AuthType::HMACAUTH);
+    msg.set_commandBytes(cmd.write_to_bytes().unwrap());
+    ::network::send(stream, &msg, value).is_ok()
+}
+
+fn serve(mut stream: net::TcpStream, state: &State) {
+    if *state.mode.lock().unwrap() == Mode::Stall {
+        // until the client gives up
+        let _ = io::copy(&mut stream, &mut io::sink());
+        return;
+    }
+
+    // handshake
+    let mut cmd = ::proto::Command::new();
+    cmd.mut_header().set_connectionID(1);
+    cmd.mut_status().set_code(StatusCode::SUCCESS);
+    {
+        let limits = cmd.mut_body().mut_getLog().mut_limits();
+        limits.set_maxOutstandingReadRequests(state.window);
+        limits.set_maxOutstandingWriteRequests(state.window);
+    }
+    let mut msg = ::proto::Message::new();
+    msg.set_authType(::proto::message::AuthType::UNSOLICITEDSTATUS);
+    msg.set_commandBytes(cmd.write_to_bytes().unwrap());
+    if ::network::send(&mut stream, &msg, &[]).is_err() { return; }
+
+    loop {
+        let (_, mut request, value) = match ::network::recv(&mut stream) {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        let message_type = request.get_header().get_messageType();
+
+        let mut response = ::proto::Command::new();
+        response.mut_header().set_ackSequence(request.get_header().get_sequence());
+
+        let mut response_value = vec![];
+        match *state.mode.lock().unwrap() {
+            Mode::Silent => continue,
+            Mode::Stall | Mode::Serve => (),
+            Mode::Hangup => {
+                let _ = stream.shutdown(net::Shutdown::Both);
+                return;
+            },
+            Mode::Fail(t, code) if t == message_type => {
+                response.mut_status().set_code(code);
+                if !reply(&mut stream, response, &[]) { return; }
+                continue;
+            },
+            _ => (),
+        }
+
+        let cluster_version = *state.cluster_version.lock().unwrap();
+        if request.get_header().get_clusterVersion() != cluster_version {
+            response.mut_header().set_clusterVersion(cluster_version);
+            response.mut_status().set_code(StatusCode::VERSION_FAILURE);
+            if !reply(&mut stream, response, &[]) { return; }
+            continue;
+        }
+
+        let mut kv = request.mut_body().take_keyValue();
+        let code = {
+            let mut store = state.store.lock().unwrap();
+            let current = store.get(kv.get_key()).map(|e| e.1.clone());
+            let matches = kv.get_force() || current.as_ref().map_or(kv.get_dbVersion().is_empty(),
+                                                                   |v| &v[..] == kv.get_dbVersion());
+            match message_type {
+                MessageType::NOOP => StatusCode::SUCCESS,
+                MessageType::PUT if !matches => StatusCode::VERSION_MISMATCH,
+                MessageType::PUT => {
+                    store.insert(kv.take_key(), (value, kv.take_newVersion(), kv.take_tag(), kv.get_algorithm()));
+                    StatusCode::SUCCESS
+                },
+                MessageType::DELETE if current.is_none() => StatusCode::NOT_FOUND,
+                MessageType::DELETE if !matches => StatusCode::VERSION_MISMATCH,
+                MessageType::DELETE => {
+                    store.remove(kv.get_key());
+                    StatusCode::SUCCESS
+                },
+                MessageType::GET | MessageType::GETVERSION => match store.get(kv.get_key()) {
+                    Some(&(ref v, ref version, ref tag, algorithm)) => {
+                        let mut r = KeyValue::new();
+                        r.set_dbVersion(version.clone());
+                        r.set_tag(tag.clone());
+                        r.set_algorithm(algorithm);
+                        response.mut_body().set_keyValue(r);
+                        if message_type == MessageType::GET { response_value = v.clone(); }
+                        StatusCode::SUCCESS
+                    },
+                    None => StatusCode::NOT_FOUND,
+                },
+                _ => StatusCode::INVALID_REQUEST,
+            }
+        };
+
+        response.mut_status().set_code(code);
+        if !reply(&mut stream, response, &response_value) { return; }
+    }
+}
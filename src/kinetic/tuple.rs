@@ -0,0 +1,367 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Order preserving encoding of tuples into keys
+//!
+//! Encoded tuples sort, byte by byte, in the same order as the tuples they encode,
+//! so they can be used as keys and walked with `GetKeyRange`. Tuples are compared
+//! element by element, a shorter tuple sorts before the tuples it is a prefix of.
+//!
+//! Each element starts with a type code, which orders elements of different types:
+//! byte strings < strings < nested tuples < integers < UUIDs.
+//!
+//! | Type        | Encoding                                                             |
+//! |-------------|----------------------------------------------------------------------|
+//! | byte string | `0x01`, bytes with `0x00` escaped as `0x00 0xff`, `0x00`               |
+//! | string      | `0x02`, UTF-8 bytes escaped like byte strings, `0x00`                  |
+//! | tuple       | `0x05`, encoded elements, `0x00`                                     |
+//! | integer     | `0x14` for zero, `0x14 + n` then n big endian bytes for positives,   |
+//! |             | `0x14 - n` then the n bytes of the ones' complement for negatives    |
+//! | UUID        | `0x30`, 16 bytes                                                     |
+//!
+//! # Example
+//! ```
+//! use kinetic::tuple::{self, Element};
+//!
+//! let key = tuple::encode(&[Element::from("users"), Element::from(42)]);
+//! assert_eq!(tuple::decode(&key).unwrap(), vec![Element::from("users"), Element::from(42)]);
+//!
+//! // every key of the "users" tuples
+//! let (start, end) = tuple::range(&[Element::from("users")]);
+//! assert!(start < key && key < end);
+//! ```
+
+use std::vec;
+use result::KineticResult;
+use error::KineticError;
+
+const BYTES_CODE: u8 = 0x01;
+const STRING_CODE: u8 = 0x02;
+const TUPLE_CODE: u8 = 0x05;
+const INT_ZERO_CODE: u8 = 0x14;
+const UUID_CODE: u8 = 0x30;
+
+/// An element of a tuple
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Bytes(vec::Vec<u8>),
+    String(String),
+    Int(i64),
+    Uuid([u8; 16]),
+    Tuple(vec::Vec<Element>),
+}
+
+impl From<i64> for Element {
+    #[inline]
+    fn from(v: i64) -> Element { Element::Int(v) }
+}
+
+impl<'a> From<&'a str> for Element {
+    #[inline]
+    fn from(v: &'a str) -> Element { Element::String(v.to_string()) }
+}
+
+impl From<String> for Element {
+    #[inline]
+    fn from(v: String) -> Element { Element::String(v) }
+}
+
+impl From<vec::Vec<u8>> for Element {
+    #[inline]
+    fn from(v: vec::Vec<u8>) -> Element { Element::Bytes(v) }
+}
+
+impl From<vec::Vec<Element>> for Element {
+    #[inline]
+    fn from(v: vec::Vec<Element>) -> Element { Element::Tuple(v) }
+}
+
+fn encode_escaped(buf: &mut vec::Vec<u8>, code: u8, bytes: &[u8]) {
+    buf.push(code);
+    for &b in bytes {
+        buf.push(b);
+        if b == 0x00 { buf.push(0xff); }
+    }
+    buf.push(0x00);
+}
+
+fn encode_int(buf: &mut vec::Vec<u8>, v: i64) {
+    if v == 0 {
+        buf.push(INT_ZERO_CODE);
+        return;
+    }
+
+    // magnitude as u64, i64::MIN included
+    let magnitude = if v < 0 { (v as u64).wrapping_neg() } else { v as u64 };
+    let n = 8 - (magnitude.leading_zeros() / 8) as usize;
+    let bits = if v < 0 { !magnitude } else { magnitude };
+
+    buf.push(if v < 0 { INT_ZERO_CODE - n as u8 } else { INT_ZERO_CODE + n as u8 });
+    for i in (0..n).rev() {
+        buf.push((bits >> (8 * i)) as u8);
+    }
+}
+
+fn encode_element(buf: &mut vec::Vec<u8>, e: &Element) {
+    match *e {
+        Element::Bytes(ref b) => encode_escaped(buf, BYTES_CODE, b),
+        Element::String(ref s) => encode_escaped(buf, STRING_CODE, s.as_bytes()),
+        Element::Int(v) => encode_int(buf, v),
+        Element::Uuid(ref u) => {
+            buf.push(UUID_CODE);
+            buf.extend(u.iter().cloned());
+        },
+        Element::Tuple(ref t) => {
+            buf.push(TUPLE_CODE);
+            for e in t.iter() { encode_element(buf, e); }
+            buf.push(0x00);
+        },
+    }
+}
+
+/// Encodes a tuple into a key
+pub fn encode(tuple: &[Element]) -> vec::Vec<u8> {
+    let mut buf = vec::Vec::new();
+    for e in tuple.iter() { encode_element(&mut buf, e); }
+    buf
+}
+
+/// Decodes a key produced by `encode`
+pub fn decode(key: &[u8]) -> KineticResult<vec::Vec<Element>> {
+    let mut pos = 0;
+    let mut tuple = vec::Vec::new();
+    while pos < key.len() {
+        tuple.push(try!(decode_element(key, &mut pos)));
+    }
+    Ok(tuple)
+}
+
+fn decode_escaped(key: &[u8], pos: &mut usize) -> KineticResult<vec::Vec<u8>> {
+    let mut bytes = vec::Vec::new();
+    loop {
+        match key.get(*pos) {
+            None => return Err(KineticError::InvalidData("unterminated tuple string")),
+            Some(&0x00) if key.get(*pos + 1) == Some(&0xff) => {
+                bytes.push(0x00);
+                *pos += 2;
+            },
+            Some(&0x00) => {
+                *pos += 1;
+                return Ok(bytes);
+            },
+            Some(&b) => {
+                bytes.push(b);
+                *pos += 1;
+            },
+        }
+    }
+}
+
+fn decode_int(key: &[u8], pos: &mut usize, code: u8) -> KineticResult<i64> {
+    let negative = code < INT_ZERO_CODE;
+    let n = if negative { INT_ZERO_CODE - code } else { code - INT_ZERO_CODE } as usize;
+    if *pos + n > key.len() {
+        return Err(KineticError::InvalidData("truncated tuple integer"));
+    }
+
+    let mut bits = 0u64;
+    for &b in key[*pos..*pos + n].iter() {
+        bits = (bits << 8) | b as u64;
+    }
+    *pos += n;
+
+    if negative {
+        let mask = if n == 8 { !0u64 } else { (1u64 << (8 * n)) - 1 };
+        let magnitude = mask - bits;
+        if magnitude > 1u64 << 63 {
+            return Err(KineticError::InvalidData("tuple integer out of range"));
+        }
+        Ok((magnitude as i64).wrapping_neg())
+    } else {
+        if bits > i64::max_value() as u64 {
+            return Err(KineticError::InvalidData("tuple integer out of range"));
+        }
+        Ok(bits as i64)
+    }
+}
+
+fn decode_element(key: &[u8], pos: &mut usize) -> KineticResult<Element> {
+    let code = key[*pos];
+    *pos += 1;
+
+    match code {
+        BYTES_CODE => Ok(Element::Bytes(try!(decode_escaped(key, pos)))),
+        STRING_CODE => {
+            let bytes = try!(decode_escaped(key, pos));
+            String::from_utf8(bytes).map(Element::String)
+                .map_err(|_| KineticError::InvalidData("tuple string is not UTF-8"))
+        },
+        TUPLE_CODE => {
+            let mut tuple = vec::Vec::new();
+            loop {
+                match key.get(*pos) {
+                    None => return Err(KineticError::InvalidData("unterminated nested tuple")),
+                    Some(&0x00) => {
+                        *pos += 1;
+                        return Ok(Element::Tuple(tuple));
+                    },
+                    Some(_) => tuple.push(try!(decode_element(key, pos))),
+                }
+            }
+        },
+        UUID_CODE => {
+            if *pos + 16 > key.len() {
+                return Err(KineticError::InvalidData("truncated tuple UUID"));
+            }
+            let mut u = [0u8; 16];
+            u.clone_from_slice(&key[*pos..*pos + 16]);
+            *pos += 16;
+            Ok(Element::Uuid(u))
+        },
+        c if c >= INT_ZERO_CODE - 8 && c <= INT_ZERO_CODE + 8 => {
+            decode_int(key, pos, c).map(Element::Int)
+        },
+        _ => Err(KineticError::InvalidData("unknown tuple type code")),
+    }
+}
+
+/// Gets the `(start, end)` keys, both exclusive, around every tuple starting with `prefix`
+///
+/// The `prefix` tuple itself is not part of the range.
+pub fn range(prefix: &[Element]) -> (vec::Vec<u8>, vec::Vec<u8>) {
+    let key = encode(prefix);
+    let mut start = key.clone();
+    start.push(0x00);
+    let mut end = key;
+    end.push(0xff);
+    (start, end)
+}
+
+/// Gets a `GetKeyRange` over every tuple starting with `prefix`, see `range`
+pub fn key_range(prefix: &[Element], max_returned: i32) -> ::commands::GetKeyRange {
+    let (start, end) = range(prefix);
+    ::commands::GetKeyRange { start: start,
+                              end: end,
+                              start_inclusive: false,
+                              end_inclusive: false,
+                              max_returned: max_returned,
+                              reverse: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use super::{Element, encode, decode, range};
+
+    fn ints() -> vec::Vec<i64> {
+        vec![::std::i64::MIN, ::std::i64::MIN + 1, -(1 << 40), -65536, -65535, -256, -255, -1,
+             0, 1, 255, 256, 65535, 65536, 1 << 40, ::std::i64::MAX - 1, ::std::i64::MAX]
+    }
+
+    #[test]
+    fn roundtrip() {
+        let tuples = [
+            vec![],
+            vec![Element::from("users"), Element::from(42)],
+            vec![Element::Bytes(vec![]), Element::String(String::new())],
+            vec![Element::Bytes(vec![0x00, 0xff, 0x00, 0x00]), Element::from("a\u{0}b")],
+            vec![Element::Uuid([0u8; 16]), Element::Uuid([0xffu8; 16])],
+            vec![Element::Tuple(vec![Element::Bytes(vec![0x00]), Element::from(-1),
+                                     Element::Tuple(vec![])]),
+                 Element::from("after")],
+            ints().into_iter().map(Element::Int).collect(),
+        ];
+
+        for t in tuples.iter() {
+            assert_eq!(&decode(&encode(t)).unwrap(), t);
+        }
+    }
+
+    #[test]
+    fn preserves_the_order_of_integers() {
+        let keys: vec::Vec<vec::Vec<u8>> = ints().into_iter().map(|i| encode(&[Element::Int(i)])).collect();
+        for w in keys.windows(2) {
+            assert!(w[0] < w[1], "{:?} >= {:?}", w[0], w[1]);
+        }
+    }
+
+    #[test]
+    fn preserves_the_order_of_tuples() {
+        // in the order they must sort
+        let tuples = vec![
+            vec![],
+            vec![Element::Bytes(vec![])],
+            vec![Element::Bytes(vec![0x00])],
+            vec![Element::Bytes(vec![0x00]), Element::from(1)],
+            vec![Element::Bytes(vec![0x00, 0x00])],
+            vec![Element::Bytes(vec![0x01])],
+            vec![Element::from("a")],
+            vec![Element::from("a"), Element::from("b")],
+            vec![Element::from("ab")],
+            vec![Element::Tuple(vec![Element::from("a")])],
+            vec![Element::Tuple(vec![Element::from("a"), Element::from(0)])],
+            vec![Element::Tuple(vec![Element::from("b")])],
+            vec![Element::from(-1)],
+            vec![Element::from(0)],
+            vec![Element::from(0), Element::from("x")],
+            vec![Element::from(1)],
+            vec![Element::Uuid([0u8; 16])],
+        ];
+
+        let keys: vec::Vec<vec::Vec<u8>> = tuples.iter().map(|t| encode(t)).collect();
+        for (i, w) in keys.windows(2).enumerate() {
+            assert!(w[0] < w[1], "{:?} >= {:?}", tuples[i], tuples[i + 1]);
+        }
+    }
+
+    #[test]
+    fn range_covers_the_tuples_with_the_prefix_only() {
+        let prefix = [Element::from("users")];
+        let (start, end) = range(&prefix);
+
+        for t in [vec![Element::from("users"), Element::from(::std::i64::MIN)],
+                  vec![Element::from("users"), Element::Bytes(vec![])],
+                  vec![Element::from("users"), Element::Uuid([0xffu8; 16])]].iter() {
+            let k = encode(t);
+            assert!(start < k && k < end);
+        }
+
+        for t in [vec![Element::from("users")],
+                  vec![Element::from("user")],
+                  vec![Element::from("users\u{0}")],
+                  vec![Element::from("usersa")]].iter() {
+            let k = encode(t);
+            assert!(k <= start || k >= end, "{:?} in range", t);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(decode(&[0x01, b'a']).is_err()); // unterminated
+        assert!(decode(&[0x05, 0x14]).is_err()); // unterminated nested tuple
+        assert!(decode(&[0x16, 0x01]).is_err()); // truncated integer
+        assert!(decode(&[0x30, 0x00]).is_err()); // truncated UUID
+        assert!(decode(&[0x02, 0xff, 0x00]).is_err()); // not UTF-8
+        assert!(decode(&[0x1d, 0x80, 0, 0, 0, 0, 0, 0, 0, 0]).is_err()); // unknown code
+        assert!(decode(&[0x1c, 0x80, 0, 0, 0, 0, 0, 0, 0]).is_err()); // out of range
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (c) 2014 Seagate Technology
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+// author: Ignacio Corderi
+
+//! Generators of entry versions, used by `Client::update`
+
+use std::vec;
+use byteorder::{BigEndian, ByteOrder};
+use rand;
+
+/// Picks the version of an entry being written
+pub trait VersionGenerator {
+
+    /// Gets the version for `value`, replacing an entry at version `current` (if any)
+    fn next_version(&self, current: Option<&[u8]>, value: &[u8]) -> vec::Vec<u8>;
+
+}
+
+/// Versions are 8 bytes big endian counters, incremented on every write
+///
+/// Versions that are not 8 bytes long are treated as `0`, the first version is `1`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CounterVersions;
+
+impl VersionGenerator for CounterVersions {
+
+    fn next_version(&self, current: Option<&[u8]>, _: &[u8]) -> vec::Vec<u8> {
+        let counter = match current {
+            Some(v) if v.len() == 8 => <BigEndian as ByteOrder>::read_u64(v),
+            _ => 0,
+        };
+
+        let mut version = vec![0u8; 8];
+        <BigEndian as ByteOrder>::write_u64(&mut version, counter.wrapping_add(1));
+        version // return
+    }
+
+}
+
+/// Versions are random (version 4) UUIDs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidVersions;
+
+impl VersionGenerator for UuidVersions {
+
+    fn next_version(&self, _: Option<&[u8]>, _: &[u8]) -> vec::Vec<u8> {
+        let mut version: vec::Vec<u8> = (0..16).map(|_| rand::random::<u8>()).collect();
+        version[6] = (version[6] & 0x0f) | 0x40; // version 4
+        version[8] = (version[8] & 0x3f) | 0x80; // RFC 4122 variant
+        version // return
+    }
+
+}
+
+/// Versions are the SHA1 of the value
+///
+/// Writing the same value twice keeps the version, so a concurrent writer
+/// storing the same value does not cause a conflict.
+///
+/// # Warning
+/// The version says nothing about the history of the entry. A value that changes and then
+/// changes back (`A`, `B`, `A`) gets its old version back, so a compare-and-swap based on a
+/// read of the first `A` still succeeds after the two writes (the ABA problem). Only use it
+/// when writing a value over the same value is harmless, otherwise use `CounterVersions`
+/// or `UuidVersions`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContentHashVersions;
+
+impl VersionGenerator for ContentHashVersions {
+
+    fn next_version(&self, _: Option<&[u8]>, value: &[u8]) -> vec::Vec<u8> {
+        ::digest::sha1(value) // return
+    }
+
+}